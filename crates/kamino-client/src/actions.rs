@@ -0,0 +1,470 @@
+//! Obligation operations as complete instruction lists, with the accounts and
+//! refreshes klend needs around them, and blocking RPC flows that fetch the
+//! state they read, send them and return the obligation after.
+
+use std::collections::HashMap;
+
+use anchor_lang::{prelude::Pubkey, solana_program::instruction::Instruction};
+use kamino_lend::{
+    state::{LendingMarket, Obligation, Reserve, UserMetadata},
+    typedefs::{ReserveFarmKind, WithdrawalCaps},
+};
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::signature::{Keypair, Signer};
+
+use crate::{
+    error::KaminoClientError,
+    health::{collateral_to_liquidity, liquidity_to_collateral, max_withdraw_collateral},
+    instruction::{
+        borrow_obligation_liquidity, init_obligation_farms_for_reserve, init_referrer_token_state,
+        repay_obligation_liquidity, withdraw_obligation_collateral,
+        withdraw_obligation_collateral_and_redeem_reserve_collateral, ReserveAccounts,
+    },
+    pda,
+    refresh::{plan_refreshes, ObligationAction},
+    rpc::{get_account, get_anchor_account, get_clock, get_reserves, send_transaction},
+    token::{create_associated_token_account, KLEND_TOKEN_PROGRAM},
+};
+
+/// A liquidity amount, or as much as the operation allows.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Amount {
+    Exact(u64),
+    Max,
+}
+
+/// An obligation with the accounts operations on it read.
+#[derive(Clone)]
+pub struct ObligationState {
+    pub address: Pubkey,
+    pub obligation: Obligation,
+    pub lending_market: LendingMarket,
+    /// Every reserve of the obligation and of the operation.
+    pub reserves: HashMap<Pubkey, Reserve>,
+}
+
+impl ObligationState {
+    /// Reserves of the obligation, then `extra` ones not among them.
+    pub fn reserve_addresses(obligation: &Obligation, extra: &[Pubkey]) -> Vec<Pubkey> {
+        let mut addresses: Vec<Pubkey> = vec![];
+        let obligation_reserves = obligation
+            .deposits
+            .iter()
+            .map(|deposit| deposit.deposit_reserve)
+            .chain(obligation.borrows.iter().map(|borrow| borrow.borrow_reserve));
+        for reserve in obligation_reserves.chain(extra.iter().copied()) {
+            if reserve != Pubkey::default() && !addresses.contains(&reserve) {
+                addresses.push(reserve);
+            }
+        }
+        addresses
+    }
+
+    pub fn reserve(&self, reserve: &Pubkey) -> Result<&Reserve, KaminoClientError> {
+        self.reserves.get(reserve).ok_or(KaminoClientError::AccountNotFound(*reserve))
+    }
+
+    /// Addresses of `reserve`.
+    pub fn reserve_accounts(&self, reserve: &Pubkey) -> Result<ReserveAccounts, KaminoClientError> {
+        Ok(ReserveAccounts::from_state(reserve, self.reserve(reserve)?, &KLEND_TOKEN_PROGRAM))
+    }
+
+    /// `instructions` wrapped in the refreshes `action` needs.
+    pub(crate) fn with_refreshes(
+        &self,
+        owner: &Pubkey,
+        action: ObligationAction,
+        instructions: impl IntoIterator<Item = Instruction>,
+    ) -> Result<Vec<Instruction>, KaminoClientError> {
+        let plan = plan_refreshes(owner, &self.address, &self.obligation, &action, &self.reserves)?;
+        Ok(plan.wrap(instructions))
+    }
+}
+
+/// Borrows `amount` of `reserve` into `owner`'s liquidity ATA, created first
+/// if missing. `referrer` is the referrer of the owner's user metadata,
+/// whose token state for `reserve` must exist.
+pub fn plan_borrow(
+    owner: &Pubkey,
+    state: &ObligationState,
+    reserve: &Pubkey,
+    referrer: Option<&Pubkey>,
+    amount: u64,
+) -> Result<Vec<Instruction>, KaminoClientError> {
+    let accounts = state.reserve_accounts(reserve)?;
+    let referrer_token_state =
+        referrer.map(|referrer| pda::get_referrer_token_state(referrer, reserve));
+    let borrow = borrow_obligation_liquidity(
+        owner,
+        &state.address,
+        &accounts,
+        referrer_token_state.as_ref(),
+        amount,
+    )?;
+    let create_destination = create_associated_token_account(
+        owner,
+        owner,
+        &accounts.liquidity_mint,
+        &accounts.token_program,
+    );
+    let instructions =
+        state.with_refreshes(owner, ObligationAction::Borrow { reserve: *reserve }, [borrow])?;
+    Ok([create_destination].into_iter().chain(instructions).collect())
+}
+
+/// Repays `amount` of the obligation's debt in `reserve` from `owner`'s
+/// liquidity ATA; `Max` repays all of it, interest accrued in the same
+/// transaction included.
+pub fn plan_repay(
+    owner: &Pubkey,
+    state: &ObligationState,
+    reserve: &Pubkey,
+    amount: Amount,
+) -> Result<Vec<Instruction>, KaminoClientError> {
+    if !state.obligation.borrows.iter().any(|borrow| borrow.borrow_reserve == *reserve) {
+        return Err(KaminoClientError::InvalidInput(format!(
+            "obligation {} does not borrow from {reserve}",
+            state.address
+        )));
+    }
+    let amount = match amount {
+        Amount::Exact(amount) => amount,
+        Amount::Max => u64::MAX,
+    };
+    let accounts = state.reserve_accounts(reserve)?;
+    let repay = repay_obligation_liquidity(owner, &state.address, &accounts, amount)?;
+    state.with_refreshes(owner, ObligationAction::Repay { reserve: *reserve }, [repay])
+}
+
+/// Liquidity `caps` still let out of the reserve at `unix_timestamp`, `None`
+/// when disabled. klend starts a new interval once the current one has
+/// elapsed; deposits count negatively.
+pub fn withdrawal_capacity(caps: &WithdrawalCaps, unix_timestamp: u64) -> Option<u64> {
+    if caps.config_capacity <= 0 || caps.config_interval_length_seconds == 0 {
+        return None;
+    }
+    let elapsed = unix_timestamp.saturating_sub(caps.last_interval_start_timestamp);
+    let current_total =
+        if elapsed > caps.config_interval_length_seconds { 0 } else { caps.current_total };
+    Some(caps.config_capacity.saturating_sub(current_total).max(0) as u64)
+}
+
+/// Withdraws `amount` of liquidity from the obligation's deposit in
+/// `reserve` into `owner`'s liquidity ATA, created first if missing. `Max`
+/// withdraws as much as the obligation's debt, the reserve's available
+/// liquidity and its withdrawal cap at `unix_timestamp` allow.
+pub fn plan_withdraw(
+    owner: &Pubkey,
+    state: &ObligationState,
+    reserve: &Pubkey,
+    amount: Amount,
+    unix_timestamp: u64,
+) -> Result<Vec<Instruction>, KaminoClientError> {
+    let reserve_state = state.reserve(reserve)?;
+    let max_collateral = max_withdraw_collateral(
+        &state.obligation,
+        &state.lending_market,
+        &state.reserves,
+        reserve,
+    )?;
+    let capacity =
+        withdrawal_capacity(&reserve_state.config.deposit_withdrawal_cap, unix_timestamp);
+    let collateral_amount = match amount {
+        Amount::Exact(liquidity_amount) => {
+            if let Some(remaining) = capacity.filter(|remaining| liquidity_amount > *remaining) {
+                return Err(KaminoClientError::WithdrawalCapReached {
+                    reserve: *reserve,
+                    amount: liquidity_amount,
+                    remaining,
+                });
+            }
+            let collateral_amount = liquidity_to_collateral(reserve_state, liquidity_amount);
+            if collateral_amount > max_collateral {
+                return Err(KaminoClientError::InvalidInput(format!(
+                    "obligation {} can withdraw at most {max_collateral} collateral from \
+                     {reserve}, {collateral_amount} requested",
+                    state.address
+                )));
+            }
+            collateral_amount
+        }
+        Amount::Max => {
+            if max_collateral == 0 {
+                return Err(KaminoClientError::InvalidInput(format!(
+                    "obligation {} cannot withdraw from {reserve}",
+                    state.address
+                )));
+            }
+            if capacity == Some(0) {
+                return Err(KaminoClientError::WithdrawalCapReached {
+                    reserve: *reserve,
+                    amount: collateral_to_liquidity(reserve_state, max_collateral).to_num(),
+                    remaining: 0,
+                });
+            }
+            let available_amount = reserve_state.liquidity.available_amount;
+            let liquidity_limit = capacity.map_or(available_amount, |c| c.min(available_amount));
+            let collateral_limit = liquidity_to_collateral(reserve_state, liquidity_limit);
+            if collateral_limit == 0 {
+                return Err(KaminoClientError::InvalidInput(format!(
+                    "reserve {reserve} has no liquidity available"
+                )));
+            }
+            // Unless the reserve limits it, klend works the maximum out
+            // itself, from the freshly refreshed state.
+            if collateral_limit < max_collateral {
+                collateral_limit
+            } else {
+                u64::MAX
+            }
+        }
+    };
+    let accounts = state.reserve_accounts(reserve)?;
+    let withdraw = withdraw_obligation_collateral_and_redeem_reserve_collateral(
+        owner,
+        &state.address,
+        &accounts,
+        collateral_amount,
+    )?;
+    let create_destination = create_associated_token_account(
+        owner,
+        owner,
+        &accounts.liquidity_mint,
+        &accounts.token_program,
+    );
+    let instructions = state.with_refreshes(
+        owner,
+        ObligationAction::Withdraw { reserve: *reserve },
+        [withdraw],
+    )?;
+    Ok([create_destination].into_iter().chain(instructions).collect())
+}
+
+/// Withdraws `collateral_amount` of the obligation's deposit in `reserve` as
+/// cTokens into `owner`'s collateral ATA, created first if missing; `Max`
+/// withdraws as much as the obligation's debt allows.
+pub fn plan_withdraw_collateral(
+    owner: &Pubkey,
+    state: &ObligationState,
+    reserve: &Pubkey,
+    collateral_amount: Amount,
+) -> Result<Vec<Instruction>, KaminoClientError> {
+    let max_collateral = max_withdraw_collateral(
+        &state.obligation,
+        &state.lending_market,
+        &state.reserves,
+        reserve,
+    )?;
+    let collateral_amount = match collateral_amount {
+        Amount::Exact(amount) if amount > max_collateral => {
+            return Err(KaminoClientError::InvalidInput(format!(
+                "obligation {} can withdraw at most {max_collateral} collateral from {reserve}, \
+                 {amount} requested",
+                state.address
+            )));
+        }
+        Amount::Exact(amount) => amount,
+        Amount::Max => u64::MAX,
+    };
+    let accounts = state.reserve_accounts(reserve)?;
+    let withdraw =
+        withdraw_obligation_collateral(owner, &state.address, &accounts, collateral_amount)?;
+    let create_destination = create_associated_token_account(
+        owner,
+        owner,
+        &accounts.collateral_mint,
+        &accounts.token_program,
+    );
+    let instructions = state.with_refreshes(
+        owner,
+        ObligationAction::Withdraw { reserve: *reserve },
+        [withdraw],
+    )?;
+    Ok([create_destination].into_iter().chain(instructions).collect())
+}
+
+/// Fetches `obligation` with its market and its reserves and `extra` ones.
+pub fn get_obligation_state(
+    client: &RpcClient,
+    obligation: &Pubkey,
+    extra_reserves: &[Pubkey],
+) -> Result<ObligationState, KaminoClientError> {
+    let state: Obligation = get_anchor_account(client, obligation)?;
+    let addresses = ObligationState::reserve_addresses(&state, extra_reserves);
+    Ok(ObligationState {
+        address: *obligation,
+        lending_market: get_anchor_account(client, &state.lending_market)?,
+        reserves: get_reserves(client, &addresses)?,
+        obligation: state,
+    })
+}
+
+/// Creates the obligation's user state in the `kind` farm of `reserve`, if
+/// the reserve has such a farm and the obligation is not in it yet; klend
+/// fails the farm refreshes around an operation without it.
+pub fn init_missing_obligation_farm(
+    client: &RpcClient,
+    owner: &Pubkey,
+    state: &ObligationState,
+    reserve: &Pubkey,
+    kind: ReserveFarmKind,
+) -> Result<Option<Instruction>, KaminoClientError> {
+    let reserve_state = state.reserve(reserve)?;
+    let farm_state = match kind {
+        ReserveFarmKind::Collateral => reserve_state.farm_collateral,
+        ReserveFarmKind::Debt => reserve_state.farm_debt,
+    };
+    if farm_state == Pubkey::default() {
+        return Ok(None);
+    }
+    let user_state = pda::get_obligation_farm_user_state(&farm_state, &state.address);
+    match get_account(client, &user_state) {
+        Ok(_) => Ok(None),
+        Err(KaminoClientError::AccountNotFound(_)) => Ok(Some(init_obligation_farms_for_reserve(
+            owner,
+            owner,
+            &state.address,
+            &state.reserve_accounts(reserve)?,
+            &farm_state,
+            kind,
+        )?)),
+        Err(error) => Err(error),
+    }
+}
+
+/// Referrer of `owner`'s user metadata, if any.
+pub fn get_referrer(
+    client: &RpcClient,
+    owner: &Pubkey,
+) -> Result<Option<Pubkey>, KaminoClientError> {
+    let metadata: UserMetadata = get_anchor_account(client, &pda::get_user_metadata(owner))?;
+    Ok(Some(metadata.referrer).filter(|referrer| *referrer != Pubkey::default()))
+}
+
+/// Borrows `amount` of `reserve`, creating the obligation's debt farm state
+/// and the referrer's token state for the reserve if missing, and returns the
+/// obligation after.
+pub fn borrow(
+    client: &RpcClient,
+    owner: &Keypair,
+    obligation: &Pubkey,
+    reserve: &Pubkey,
+    amount: u64,
+) -> Result<Obligation, KaminoClientError> {
+    let state = get_obligation_state(client, obligation, &[*reserve])?;
+    let referrer = get_referrer(client, &owner.pubkey())?;
+    let mut instructions: Vec<_> = init_missing_obligation_farm(
+        client,
+        &owner.pubkey(),
+        &state,
+        reserve,
+        ReserveFarmKind::Debt,
+    )?
+    .into_iter()
+    .collect();
+    if let Some(referrer) = referrer {
+        let referrer_token_state = pda::get_referrer_token_state(&referrer, reserve);
+        match get_account(client, &referrer_token_state) {
+            Ok(_) => {}
+            Err(KaminoClientError::AccountNotFound(_)) => {
+                let accounts = state.reserve_accounts(reserve)?;
+                instructions.push(init_referrer_token_state(
+                    &owner.pubkey(),
+                    &referrer,
+                    &accounts,
+                )?);
+            }
+            Err(error) => return Err(error),
+        }
+    }
+    instructions.extend(plan_borrow(&owner.pubkey(), &state, reserve, referrer.as_ref(), amount)?);
+    send_transaction(client, &owner.pubkey(), &instructions, &[owner])?;
+    get_anchor_account(client, obligation)
+}
+
+/// Repays `amount` of the debt in `reserve` and returns the obligation after.
+pub fn repay(
+    client: &RpcClient,
+    owner: &Keypair,
+    obligation: &Pubkey,
+    reserve: &Pubkey,
+    amount: Amount,
+) -> Result<Obligation, KaminoClientError> {
+    let state = get_obligation_state(client, obligation, &[*reserve])?;
+    let instructions = plan_repay(&owner.pubkey(), &state, reserve, amount)?;
+    send_transaction(client, &owner.pubkey(), &instructions, &[owner])?;
+    get_anchor_account(client, obligation)
+}
+
+/// Withdraws `amount` of liquidity from the deposit in `reserve` and returns
+/// the obligation after.
+pub fn withdraw(
+    client: &RpcClient,
+    owner: &Keypair,
+    obligation: &Pubkey,
+    reserve: &Pubkey,
+    amount: Amount,
+) -> Result<Obligation, KaminoClientError> {
+    let state = get_obligation_state(client, obligation, &[])?;
+    let unix_timestamp = get_clock(client)?.unix_timestamp.max(0) as u64;
+    let instructions = plan_withdraw(&owner.pubkey(), &state, reserve, amount, unix_timestamp)?;
+    send_transaction(client, &owner.pubkey(), &instructions, &[owner])?;
+    get_anchor_account(client, obligation)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::zeroed;
+
+    fn state(deposit: Pubkey, borrow: Pubkey) -> ObligationState {
+        let mut obligation: Obligation = zeroed();
+        obligation.deposits[0].deposit_reserve = deposit;
+        obligation.borrows[0].borrow_reserve = borrow;
+        ObligationState {
+            address: Pubkey::new_unique(),
+            obligation,
+            lending_market: zeroed(),
+            reserves: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn test_reserve_addresses() {
+        let (usdc, sol, bonk) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let state = state(usdc, sol);
+        assert_eq!(
+            ObligationState::reserve_addresses(&state.obligation, &[sol, bonk, bonk]),
+            [usdc, sol, bonk]
+        );
+    }
+
+    #[test]
+    fn test_plan_repay_without_debt() {
+        let (usdc, sol) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let state = state(usdc, sol);
+        let error = plan_repay(&Pubkey::new_unique(), &state, &usdc, Amount::Max).err().unwrap();
+        assert!(matches!(error, KaminoClientError::InvalidInput(_)), "{error}");
+        let error = plan_repay(&Pubkey::new_unique(), &state, &sol, Amount::Max).err().unwrap();
+        assert!(matches!(error, KaminoClientError::AccountNotFound(address) if address == sol));
+    }
+
+    #[test]
+    fn test_withdrawal_capacity() {
+        let mut caps = WithdrawalCaps {
+            config_capacity: 1_000,
+            current_total: 300,
+            last_interval_start_timestamp: 10_000,
+            config_interval_length_seconds: 3_600,
+        };
+        assert_eq!(withdrawal_capacity(&caps, 10_000 + 3_600), Some(700));
+        assert_eq!(withdrawal_capacity(&caps, 10_000 + 3_601), Some(1_000));
+        // Deposits since the interval started raise the capacity.
+        caps.current_total = -500;
+        assert_eq!(withdrawal_capacity(&caps, 10_000), Some(1_500));
+        caps.current_total = 1_200;
+        assert_eq!(withdrawal_capacity(&caps, 10_000), Some(0));
+        caps.config_interval_length_seconds = 0;
+        assert_eq!(withdrawal_capacity(&caps, 10_000), None);
+    }
+}
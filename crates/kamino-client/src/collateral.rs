@@ -0,0 +1,254 @@
+//! klend collateral tokens (cTokens) held outside obligations: depositing
+//! liquidity for them, redeeming them, and moving them into an obligation.
+//!
+//! Quotes use the exchange rate the refresh sent ahead of each operation
+//! leaves, i.e. with interest accrued up to the slot it runs in.
+
+use anchor_lang::{prelude::Pubkey, solana_program::instruction::Instruction};
+use kamino_lend::{
+    state::{Obligation, Reserve},
+    typedefs::ReserveFarmKind,
+};
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::{
+    clock::Clock,
+    signature::{Keypair, Signer},
+};
+
+use crate::{
+    actions::{
+        get_obligation_state, init_missing_obligation_farm, withdrawal_capacity, Amount,
+        ObligationState,
+    },
+    error::KaminoClientError,
+    health::{collateral_to_liquidity, liquidity_to_collateral},
+    instruction::{
+        deposit_obligation_collateral, deposit_reserve_liquidity, redeem_reserve_collateral,
+        refresh_reserve, ReserveAccounts,
+    },
+    interest::project_reserve,
+    refresh::ObligationAction,
+    rpc::{get_anchor_account, get_clock, get_token_balance, send_transaction},
+    token::{create_associated_token_account, KLEND_TOKEN_PROGRAM},
+};
+
+/// cTokens `liquidity_amount` mints when deposited at `slot`.
+pub fn quote_deposit(
+    reserve: &Reserve,
+    slot: u64,
+    liquidity_amount: u64,
+) -> Result<u64, KaminoClientError> {
+    Ok(liquidity_to_collateral(&project_reserve(reserve, slot)?, liquidity_amount))
+}
+
+/// Liquidity `collateral_amount` cTokens redeem for at `slot`, rounded down
+/// as klend does.
+pub fn quote_redeem(
+    reserve: &Reserve,
+    slot: u64,
+    collateral_amount: u64,
+) -> Result<u64, KaminoClientError> {
+    let projected = project_reserve(reserve, slot)?;
+    Ok(collateral_to_liquidity(&projected, collateral_amount).saturating_to_num())
+}
+
+/// Deposits `liquidity_amount` from `owner`'s liquidity ATA for cTokens in
+/// their collateral ATA, created first if missing.
+pub fn plan_deposit_liquidity(
+    owner: &Pubkey,
+    reserve: &Pubkey,
+    state: &Reserve,
+    liquidity_amount: u64,
+) -> Result<Vec<Instruction>, KaminoClientError> {
+    let accounts = ReserveAccounts::from_state(reserve, state, &KLEND_TOKEN_PROGRAM);
+    Ok(vec![
+        create_associated_token_account(
+            owner,
+            owner,
+            &accounts.collateral_mint,
+            &accounts.token_program,
+        ),
+        refresh_reserve(reserve, state)?,
+        deposit_reserve_liquidity(owner, &accounts, liquidity_amount)?,
+    ])
+}
+
+/// Redeems `collateral_amount` cTokens from `owner`'s collateral ATA into
+/// their liquidity ATA, created first if missing. Fails if the liquidity
+/// they redeem for at `clock` is over the reserve's withdrawal cap.
+pub fn plan_redeem_collateral(
+    owner: &Pubkey,
+    reserve: &Pubkey,
+    state: &Reserve,
+    collateral_amount: u64,
+    clock: &Clock,
+) -> Result<Vec<Instruction>, KaminoClientError> {
+    let liquidity_amount = quote_redeem(state, clock.slot, collateral_amount)?;
+    let unix_timestamp = clock.unix_timestamp.max(0) as u64;
+    let capacity = withdrawal_capacity(&state.config.deposit_withdrawal_cap, unix_timestamp);
+    if let Some(remaining) = capacity.filter(|remaining| liquidity_amount > *remaining) {
+        return Err(KaminoClientError::WithdrawalCapReached {
+            reserve: *reserve,
+            amount: liquidity_amount,
+            remaining,
+        });
+    }
+    let accounts = ReserveAccounts::from_state(reserve, state, &KLEND_TOKEN_PROGRAM);
+    Ok(vec![
+        create_associated_token_account(
+            owner,
+            owner,
+            &accounts.liquidity_mint,
+            &accounts.token_program,
+        ),
+        refresh_reserve(reserve, state)?,
+        redeem_reserve_collateral(owner, &accounts, collateral_amount)?,
+    ])
+}
+
+/// Moves `collateral_amount` cTokens from `owner`'s collateral ATA into the
+/// obligation's deposit in `reserve`.
+pub fn plan_deposit_collateral(
+    owner: &Pubkey,
+    state: &ObligationState,
+    reserve: &Pubkey,
+    collateral_amount: u64,
+) -> Result<Vec<Instruction>, KaminoClientError> {
+    let accounts = state.reserve_accounts(reserve)?;
+    let deposit =
+        deposit_obligation_collateral(owner, &state.address, &accounts, collateral_amount)?;
+    state.with_refreshes(owner, ObligationAction::Deposit { reserve: *reserve }, [deposit])
+}
+
+/// Deposits `liquidity_amount` of `reserve` and returns the cTokens minted.
+pub fn deposit_liquidity(
+    client: &RpcClient,
+    owner: &Keypair,
+    reserve: &Pubkey,
+    liquidity_amount: u64,
+) -> Result<u64, KaminoClientError> {
+    let state: Reserve = get_anchor_account(client, reserve)?;
+    let accounts = ReserveAccounts::from_state(reserve, &state, &KLEND_TOKEN_PROGRAM);
+    let instructions = plan_deposit_liquidity(&owner.pubkey(), reserve, &state, liquidity_amount)?;
+    received(client, &accounts.user_collateral_ata(&owner.pubkey()), |client| {
+        send_transaction(client, &owner.pubkey(), &instructions, &[owner]).map(drop)
+    })
+}
+
+/// Redeems `amount` of `owner`'s cTokens of `reserve`, all of them for `Max`,
+/// and returns the liquidity received.
+pub fn redeem_collateral(
+    client: &RpcClient,
+    owner: &Keypair,
+    reserve: &Pubkey,
+    amount: Amount,
+) -> Result<u64, KaminoClientError> {
+    let state: Reserve = get_anchor_account(client, reserve)?;
+    let accounts = ReserveAccounts::from_state(reserve, &state, &KLEND_TOKEN_PROGRAM);
+    let collateral_amount = match amount {
+        Amount::Exact(amount) => amount,
+        Amount::Max => get_token_balance(client, &accounts.user_collateral_ata(&owner.pubkey()))?,
+    };
+    let clock = get_clock(client)?;
+    let instructions =
+        plan_redeem_collateral(&owner.pubkey(), reserve, &state, collateral_amount, &clock)?;
+    received(client, &accounts.user_liquidity_ata(&owner.pubkey()), |client| {
+        send_transaction(client, &owner.pubkey(), &instructions, &[owner]).map(drop)
+    })
+}
+
+/// Moves `amount` of `owner`'s cTokens of `reserve`, all of them for `Max`,
+/// into `obligation`, joining the reserve's collateral farm if needed, and
+/// returns the obligation after.
+pub fn deposit_collateral(
+    client: &RpcClient,
+    owner: &Keypair,
+    obligation: &Pubkey,
+    reserve: &Pubkey,
+    amount: Amount,
+) -> Result<Obligation, KaminoClientError> {
+    let state = get_obligation_state(client, obligation, &[*reserve])?;
+    let collateral_amount = match amount {
+        Amount::Exact(amount) => amount,
+        Amount::Max => {
+            let accounts = state.reserve_accounts(reserve)?;
+            get_token_balance(client, &accounts.user_collateral_ata(&owner.pubkey()))?
+        }
+    };
+    let init_farm = init_missing_obligation_farm(
+        client,
+        &owner.pubkey(),
+        &state,
+        reserve,
+        ReserveFarmKind::Collateral,
+    )?;
+    let instructions: Vec<_> = init_farm
+        .into_iter()
+        .chain(plan_deposit_collateral(&owner.pubkey(), &state, reserve, collateral_amount)?)
+        .collect();
+    send_transaction(client, &owner.pubkey(), &instructions, &[owner])?;
+    get_anchor_account(client, obligation)
+}
+
+/// How much `send` adds to `token_account`, which may not exist before.
+fn received(
+    client: &RpcClient,
+    token_account: &Pubkey,
+    send: impl FnOnce(&RpcClient) -> Result<(), KaminoClientError>,
+) -> Result<u64, KaminoClientError> {
+    let before = match get_token_balance(client, token_account) {
+        Ok(balance) => balance,
+        Err(KaminoClientError::AccountNotFound(_)) => 0,
+        Err(error) => return Err(error),
+    };
+    send(client)?;
+    Ok(get_token_balance(client, token_account)?.saturating_sub(before))
+}
+
+#[cfg(test)]
+mod tests {
+    use kamino_lend::Fraction;
+
+    use super::*;
+    use crate::test_util::zeroed;
+
+    #[test]
+    fn test_quotes() {
+        let mut reserve: Reserve = zeroed();
+        reserve.last_update.slot = 100;
+        reserve.liquidity.available_amount = 1_000_000;
+        reserve.liquidity.cumulative_borrow_rate_bsf.value[0] = 1 << 60;
+        reserve.collateral.mint_total_supply = 500_000;
+        assert_eq!(quote_deposit(&reserve, 100, 1_001).unwrap(), 500);
+        assert_eq!(quote_redeem(&reserve, 100, 500).unwrap(), 1_000);
+        assert!(quote_redeem(&reserve, 99, 500).is_err());
+
+        // Interest accrued since the last refresh raises what cTokens are
+        // worth.
+        reserve.liquidity.borrowed_amount_sf = Fraction::from_num(1_000_000).to_bits();
+        reserve.config.borrow_rate_curve.points[0].borrow_rate_bps = 10_000;
+        reserve.config.borrow_rate_curve.points[1].utilization_rate_bps = 10_000;
+        reserve.config.borrow_rate_curve.points[1].borrow_rate_bps = 10_000;
+        let redeemed = quote_redeem(&reserve, 100, 500).unwrap();
+        assert!(quote_redeem(&reserve, 1_000_000, 500).unwrap() > redeemed);
+        assert!(quote_deposit(&reserve, 1_000_000, 1_000).unwrap() < 250);
+    }
+
+    #[test]
+    fn test_redeem_over_withdrawal_cap() {
+        let mut reserve: Reserve = zeroed();
+        reserve.liquidity.available_amount = 1_000_000;
+        reserve.collateral.mint_total_supply = 1_000_000;
+        reserve.config.deposit_withdrawal_cap.config_capacity = 1_000;
+        reserve.config.deposit_withdrawal_cap.config_interval_length_seconds = 60;
+        let owner = Pubkey::new_unique();
+        let address = Pubkey::new_unique();
+        let clock = Clock::default();
+        assert_eq!(
+            plan_redeem_collateral(&owner, &address, &reserve, 1_000, &clock).unwrap().len(),
+            3
+        );
+        let error = plan_redeem_collateral(&owner, &address, &reserve, 1_001, &clock).unwrap_err();
+        assert!(matches!(error, KaminoClientError::WithdrawalCapReached { remaining: 1_000, .. }));
+    }
+}
@@ -0,0 +1,284 @@
+//! The SDK's error type, and the failing instruction and program error of a
+//! failed transaction.
+
+use std::{error::Error, fmt, io};
+
+use anchor_lang::prelude::Pubkey;
+use kamino_lend::{LendingError, ID as KAMINO_LENDING_ID};
+use num_traits::FromPrimitive;
+use solana_client::client_error::ClientError;
+use solana_sdk::{
+    instruction::{Instruction, InstructionError},
+    packet::PACKET_DATA_SIZE,
+    system_instruction::SystemError,
+    system_program,
+    transaction::{Transaction, TransactionError},
+};
+use spl_token::error::TokenError;
+use spl_token_2022::error::TokenError as Token2022Error;
+
+use crate::{reserve_config::ConfigError, token::UnsupportedMint};
+
+/// Everything the SDK can fail with, split by what a caller would do about
+/// it: retry an RPC failure, branch on a program error, fix an input.
+#[derive(Debug)]
+pub enum KaminoClientError {
+    /// The RPC node could not be reached or rejected the request.
+    Rpc(Box<ClientError>),
+    /// An instruction failed.
+    Program(InstructionFailure),
+    AccountNotFound(Pubkey),
+    /// An account or spec that does not decode as the expected type.
+    Deserialize(String),
+    /// Borsh serialization or reading a file failed.
+    Io(io::Error),
+    /// A reserve config klend would reject.
+    InvalidConfig(Vec<ConfigError>),
+    /// A mint klend cannot take as reserve liquidity.
+    UnsupportedMint {
+        mint: Pubkey,
+        reasons: Vec<UnsupportedMint>,
+    },
+    /// A transaction over the packet size, in bytes.
+    TransactionTooLarge {
+        size: usize,
+        max: usize,
+    },
+    /// A withdrawal over what the reserve's deposit withdrawal cap has left
+    /// in its current interval, both in liquidity.
+    WithdrawalCapReached {
+        reserve: Pubkey,
+        amount: u64,
+        remaining: u64,
+    },
+    /// A computation that overflows or leaves its domain.
+    Math(&'static str),
+    /// An argument the SDK cannot encode or act on.
+    InvalidInput(String),
+}
+
+impl KaminoClientError {
+    /// Reads the failing instruction out of `error`, resolving its program
+    /// from `instructions`, the instructions of the transaction sent. Other
+    /// failures are RPC failures.
+    pub fn from_client_error(error: ClientError, instructions: &[Instruction]) -> Self {
+        match InstructionFailure::from_client_error(&error) {
+            Some(failure) => Self::Program(failure.with_instructions(instructions)),
+            None => Self::Rpc(Box::new(error)),
+        }
+    }
+
+    /// The klend error, if an instruction failed with one.
+    pub fn lending_error(&self) -> Option<LendingError> {
+        match self {
+            Self::Program(failure) => failure.lending_error(),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for KaminoClientError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Rpc(error) => write!(f, "rpc: {error}"),
+            Self::Program(failure) => failure.fmt(f),
+            Self::AccountNotFound(address) => write!(f, "account {address} does not exist"),
+            Self::Deserialize(error) => write!(f, "cannot deserialize: {error}"),
+            Self::Io(error) => error.fmt(f),
+            Self::InvalidConfig(errors) => {
+                let errors: Vec<_> = errors.iter().map(ToString::to_string).collect();
+                write!(f, "invalid reserve config: {}", errors.join(", "))
+            }
+            Self::UnsupportedMint { mint, reasons } => {
+                let reasons: Vec<_> = reasons.iter().map(ToString::to_string).collect();
+                write!(f, "mint {mint} cannot be listed on klend: {}", reasons.join(", "))
+            }
+            Self::TransactionTooLarge { size, max } => {
+                write!(f, "transaction is {size} bytes, over the {max} byte limit")
+            }
+            Self::WithdrawalCapReached { reserve, amount, remaining } => write!(
+                f,
+                "withdrawing {amount} from reserve {reserve} exceeds the {remaining} its \
+                 withdrawal cap has left"
+            ),
+            Self::Math(error) => error.fmt(f),
+            Self::InvalidInput(error) => error.fmt(f),
+        }
+    }
+}
+
+impl Error for KaminoClientError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Rpc(error) => Some(error),
+            Self::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<ClientError> for KaminoClientError {
+    fn from(error: ClientError) -> Self { Self::from_client_error(error, &[]) }
+}
+
+impl From<io::Error> for KaminoClientError {
+    fn from(error: io::Error) -> Self { Self::Io(error) }
+}
+
+impl From<anchor_lang::error::Error> for KaminoClientError {
+    fn from(error: anchor_lang::error::Error) -> Self { Self::Deserialize(error.to_string()) }
+}
+
+impl From<Vec<ConfigError>> for KaminoClientError {
+    fn from(errors: Vec<ConfigError>) -> Self { Self::InvalidConfig(errors) }
+}
+
+/// Fails if `transaction` does not fit in a packet.
+pub fn check_transaction_size(transaction: &Transaction) -> Result<(), KaminoClientError> {
+    let size = bincode::serialized_size(transaction)
+        .map_err(|error| KaminoClientError::InvalidInput(error.to_string()))?
+        as usize;
+    if size > PACKET_DATA_SIZE {
+        return Err(KaminoClientError::TransactionTooLarge { size, max: PACKET_DATA_SIZE });
+    }
+    Ok(())
+}
+
+/// An instruction that failed, by its index in the transaction.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InstructionFailure {
+    pub index: u8,
+    pub error: InstructionError,
+    /// The program of the instruction, when the transaction is known.
+    pub program_id: Option<Pubkey>,
+}
+
+impl InstructionFailure {
+    pub fn from_transaction_error(error: &TransactionError) -> Option<Self> {
+        match error {
+            TransactionError::InstructionError(index, error) => {
+                Some(Self { index: *index, error: error.clone(), program_id: None })
+            }
+            _ => None,
+        }
+    }
+
+    /// Also finds the failure of a transaction rejected in preflight.
+    pub fn from_client_error(error: &ClientError) -> Option<Self> {
+        Self::from_transaction_error(&error.get_transaction_error()?)
+    }
+
+    /// Sets the program from the failing instruction of `instructions`.
+    pub fn with_instructions(self, instructions: &[Instruction]) -> Self {
+        let program_id = instructions.get(usize::from(self.index)).map(|ix| ix.program_id);
+        Self { program_id: program_id.or(self.program_id), ..self }
+    }
+
+    pub fn program_error(&self) -> ProgramError {
+        let InstructionError::Custom(code) = self.error else {
+            return ProgramError::Other(self.error.clone());
+        };
+        let decoded = match self.program_id {
+            Some(id) if id == spl_token::ID => TokenError::from_u32(code).map(ProgramError::Token),
+            Some(id) if id == spl_token_2022::ID => {
+                Token2022Error::from_u32(code).map(ProgramError::Token2022)
+            }
+            Some(id) if id == system_program::ID => {
+                SystemError::from_u32(code).map(ProgramError::System)
+            }
+            Some(id) if id != KAMINO_LENDING_ID => None,
+            _ => LendingError::from_code(code).map(ProgramError::Lending),
+        };
+        decoded.unwrap_or_else(|| ProgramError::Other(self.error.clone()))
+    }
+
+    /// The klend error of the instruction, if it failed with one of klend's
+    /// codes. Without the program, codes are not namespaced, so an error of
+    /// another Anchor program reads as klend's.
+    pub fn lending_error(&self) -> Option<LendingError> {
+        match self.program_error() {
+            ProgramError::Lending(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+/// `klend::ObligationStale (6017) at ix #2`.
+impl fmt::Display for InstructionFailure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at ix #{}", self.program_error(), self.index)
+    }
+}
+
+/// An instruction error decoded by the program that returned it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ProgramError {
+    Lending(LendingError),
+    Token(TokenError),
+    Token2022(Token2022Error),
+    System(SystemError),
+    Other(InstructionError),
+}
+
+impl fmt::Display for ProgramError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Lending(error) => error.fmt(f),
+            Self::Token(error) => write!(f, "token::{error:?} ({})", error.clone() as u32),
+            Self::Token2022(error) => {
+                write!(f, "token_2022::{error:?} ({})", error.clone() as u32)
+            }
+            Self::System(error) => write!(f, "system::{error:?} ({})", error.clone() as u32),
+            Self::Other(error) => error.fmt(f),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_client::client_error::ClientErrorKind;
+
+    use super::*;
+
+    #[test]
+    fn test_instruction_failure() {
+        let error = TransactionError::InstructionError(2, InstructionError::Custom(6017));
+        let failure = InstructionFailure::from_transaction_error(&error).unwrap();
+        assert_eq!(failure.lending_error(), Some(LendingError::ObligationStale));
+        assert_eq!(failure.to_string(), "klend::ObligationStale (6017) at ix #2");
+
+        let error = ClientError::from(ClientErrorKind::TransactionError(
+            TransactionError::InstructionError(0, InstructionError::Custom(1)),
+        ));
+        let failure = InstructionFailure::from_client_error(&error).unwrap();
+        assert_eq!(failure.to_string(), "custom program error: 0x1 at ix #0");
+
+        assert_eq!(
+            InstructionFailure::from_transaction_error(&TransactionError::AccountInUse),
+            None
+        );
+    }
+
+    #[test]
+    fn test_program_error_by_program() {
+        let instructions = [
+            Instruction::new_with_bytes(system_program::ID, &[], vec![]),
+            Instruction::new_with_bytes(spl_token::ID, &[], vec![]),
+            Instruction::new_with_bytes(spl_token_2022::ID, &[], vec![]),
+        ];
+        let failure = |index, code| {
+            let error = ClientError::from(ClientErrorKind::TransactionError(
+                TransactionError::InstructionError(index, InstructionError::Custom(code)),
+            ));
+            KaminoClientError::from_client_error(error, &instructions).to_string()
+        };
+        assert_eq!(failure(0, 0), "system::AccountAlreadyInUse (0) at ix #0");
+        assert_eq!(failure(1, 1), "token::InsufficientFunds (1) at ix #1");
+        assert_eq!(failure(1, 6017), "custom program error: 0x1781 at ix #1");
+        assert_eq!(failure(2, 1), "token_2022::InsufficientFunds (1) at ix #2");
+        assert_eq!(failure(2, 34), "token_2022::ImmutableOwner (34) at ix #2");
+
+        let error = ClientError::from(ClientErrorKind::Custom("connection refused".into()));
+        assert!(matches!(KaminoClientError::from(error), KaminoClientError::Rpc(_)));
+    }
+}
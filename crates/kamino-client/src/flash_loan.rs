@@ -0,0 +1,368 @@
+//! Flash loans: instructions wrapped between `flashBorrowReserveLiquidity`
+//! and `flashRepayReserveLiquidity`, laid out as klend's instruction
+//! introspection requires.
+//!
+//! A flash borrow must be a top-level instruction followed, before any other
+//! flash borrow, by a top-level repay of the same reserve and amount whose
+//! `borrow_instruction_index` points back at it. klend only reads the
+//! instructions sysvar, so indices count every instruction of the
+//! transaction, compute budget ones included.
+
+use anchor_lang::{
+    prelude::Pubkey, solana_program::instruction::Instruction, AnchorDeserialize, Discriminator,
+};
+use kamino_lend::{instruction, state::Reserve, Fraction, LendingError, ID as KAMINO_LENDING_ID};
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::{
+    compute_budget::{self, ComputeBudgetInstruction},
+    instruction::InstructionError,
+    signature::{Keypair, Signature, Signer},
+};
+
+use crate::{
+    error::{InstructionFailure, KaminoClientError},
+    instruction::{flash_borrow_reserve_liquidity, flash_repay_reserve_liquidity, ReserveAccounts},
+    rpc::{get_anchor_account, send_transaction},
+    token::{create_associated_token_account, KLEND_TOKEN_PROGRAM},
+};
+
+/// `flash_loan_fee_sf` of reserves that do not lend flash.
+pub(crate) const FLASH_LOANS_DISABLED: u64 = u64::MAX;
+
+/// Compute budget instructions a composed transaction starts with.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ComputeBudget {
+    pub unit_limit: Option<u32>,
+    pub unit_price_micro_lamports: Option<u64>,
+}
+
+impl ComputeBudget {
+    pub fn instructions(&self) -> Vec<Instruction> {
+        let limit = self.unit_limit.map(ComputeBudgetInstruction::set_compute_unit_limit);
+        let price =
+            self.unit_price_micro_lamports.map(ComputeBudgetInstruction::set_compute_unit_price);
+        limit.into_iter().chain(price).collect()
+    }
+}
+
+/// A flash loan transaction's instructions and what it costs.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FlashLoan {
+    pub instructions: Vec<Instruction>,
+    pub borrow_index: u8,
+    pub repay_index: u8,
+    /// Charged on top of the amount at repayment, referral share included.
+    pub fee: u64,
+}
+
+/// Fee klend charges on a flash loan of `amount`, at least 1 when the
+/// reserve has a fee; `None` if the reserve has flash loans disabled.
+pub fn flash_loan_fee(reserve: &Reserve, amount: u64) -> Option<u64> {
+    let fee_sf = reserve.config.fees.flash_loan_fee_sf;
+    if fee_sf == FLASH_LOANS_DISABLED {
+        return None;
+    }
+    let fee_rate = Fraction::from_bits(fee_sf.into());
+    if fee_rate == Fraction::ZERO || amount == 0 {
+        return Some(0);
+    }
+    let fee = (Fraction::from_num(amount) * fee_rate).max(Fraction::ONE);
+    Some(fee.round().saturating_to_num())
+}
+
+/// Borrows `amount` of `reserve` into `user`'s liquidity ATA, created first
+/// if missing, runs `instructions` and repays the amount plus the fee from
+/// the same ATA, after `compute_budget`'s instructions. `instructions` must
+/// leave the ATA holding both and must not set the compute budget.
+pub fn compose_flash_loan(
+    user: &Pubkey,
+    reserve: &Pubkey,
+    state: &Reserve,
+    referrer: Option<&Pubkey>,
+    amount: u64,
+    compute_budget: &ComputeBudget,
+    instructions: impl IntoIterator<Item = Instruction>,
+) -> Result<FlashLoan, KaminoClientError> {
+    let instructions: Vec<_> = instructions.into_iter().collect();
+    if instructions.iter().any(|ix| ix.program_id == compute_budget::ID) {
+        return Err(KaminoClientError::InvalidInput(
+            "set the compute budget of a flash loan through `ComputeBudget`".to_string(),
+        ));
+    }
+    let accounts = ReserveAccounts::from_state(reserve, state, &KLEND_TOKEN_PROGRAM);
+    let mut composed = compute_budget.instructions();
+    composed.push(create_associated_token_account(
+        user,
+        user,
+        &accounts.liquidity_mint,
+        &accounts.token_program,
+    ));
+    let borrow_index = instruction_index(composed.len())?;
+    let repay_index = instruction_index(composed.len() + instructions.len() + 1)?;
+    let fee = flash_loan_fee(state, amount)
+        .ok_or_else(|| lending_failure(borrow_index, LendingError::FlashLoansDisabled))?;
+    composed.push(flash_borrow_reserve_liquidity(user, &accounts, referrer, amount)?);
+    composed.extend(instructions);
+    composed.push(flash_repay_reserve_liquidity(user, &accounts, referrer, amount, borrow_index)?);
+    validate_flash_loans(&composed)?;
+    Ok(FlashLoan { instructions: composed, borrow_index, repay_index, fee })
+}
+
+/// Checks the flash borrows and repays of a transaction's `instructions`
+/// the way klend does, failing with the error and index klend would.
+pub fn validate_flash_loans(instructions: &[Instruction]) -> Result<(), KaminoClientError> {
+    for (index, ix) in instructions.iter().enumerate() {
+        let failure = match FlashInstruction::decode(ix) {
+            Some(FlashInstruction::Borrow { reserve, amount }) => {
+                let next = instructions[index + 1..]
+                    .iter()
+                    .filter_map(FlashInstruction::decode)
+                    .find(|flash| !matches!(flash, FlashInstruction::Other));
+                match next {
+                    None => Some(LendingError::NoFlashRepayFound),
+                    Some(FlashInstruction::Repay {
+                        reserve: repay_reserve,
+                        amount: repaid,
+                        borrow_index,
+                    }) => (usize::from(borrow_index) != index
+                        || (repay_reserve, repaid) != (reserve, amount))
+                        .then_some(LendingError::InvalidFlashRepay),
+                    Some(_) => Some(LendingError::MultipleFlashBorrows),
+                }
+            }
+            Some(FlashInstruction::Repay { reserve, amount, borrow_index }) => {
+                let borrow = instructions
+                    .get(usize::from(borrow_index))
+                    .filter(|_| usize::from(borrow_index) < index)
+                    .and_then(FlashInstruction::decode);
+                match borrow {
+                    Some(FlashInstruction::Borrow {
+                        reserve: borrow_reserve,
+                        amount: borrowed,
+                    }) if (borrow_reserve, borrowed) == (reserve, amount) => None,
+                    _ => Some(LendingError::InvalidFlashRepay),
+                }
+            }
+            Some(FlashInstruction::Other) | None => None,
+        };
+        if let Some(error) = failure {
+            return Err(lending_failure(instruction_index(index)?, error));
+        }
+    }
+    Ok(())
+}
+
+/// Flash-borrows `amount` of `reserve` around `instructions` and sends it
+/// all in one transaction signed by `user` and `signers`.
+pub fn flash_loan(
+    client: &RpcClient,
+    user: &Keypair,
+    reserve: &Pubkey,
+    amount: u64,
+    compute_budget: &ComputeBudget,
+    instructions: Vec<Instruction>,
+    signers: &[&Keypair],
+) -> Result<Signature, KaminoClientError> {
+    let state: Reserve = get_anchor_account(client, reserve)?;
+    let flash_loan = compose_flash_loan(
+        &user.pubkey(),
+        reserve,
+        &state,
+        None,
+        amount,
+        compute_budget,
+        instructions,
+    )?;
+    let signers: Vec<_> = [user].into_iter().chain(signers.iter().copied()).collect();
+    send_transaction(client, &user.pubkey(), &flash_loan.instructions, &signers)
+}
+
+/// A klend instruction as flash loan checks see it.
+enum FlashInstruction {
+    Borrow { reserve: Pubkey, amount: u64 },
+    Repay { reserve: Pubkey, amount: u64, borrow_index: u8 },
+    Other,
+}
+
+impl FlashInstruction {
+    /// Reserve account of both flash instructions.
+    const RESERVE_ACCOUNT: usize = 3;
+
+    /// `None` for instructions of other programs.
+    fn decode(ix: &Instruction) -> Option<Self> {
+        if ix.program_id != KAMINO_LENDING_ID {
+            return None;
+        }
+        let reserve = ix.accounts.get(Self::RESERVE_ACCOUNT).map(|meta| meta.pubkey);
+        let (discriminator, mut args) = ix.data.split_at(ix.data.len().min(8));
+        let flash = if discriminator == instruction::FlashBorrowReserveLiquidity::DISCRIMINATOR {
+            let args = instruction::FlashBorrowReserveLiquidity::deserialize(&mut args).ok();
+            reserve
+                .zip(args)
+                .map(|(reserve, args)| Self::Borrow { reserve, amount: args._liquidity_amount })
+        } else if discriminator == instruction::FlashRepayReserveLiquidity::DISCRIMINATOR {
+            let args = instruction::FlashRepayReserveLiquidity::deserialize(&mut args).ok();
+            reserve.zip(args).map(|(reserve, args)| Self::Repay {
+                reserve,
+                amount: args._liquidity_amount,
+                borrow_index: args._borrow_instruction_index,
+            })
+        } else {
+            None
+        };
+        Some(flash.unwrap_or(Self::Other))
+    }
+}
+
+fn instruction_index(index: usize) -> Result<u8, KaminoClientError> {
+    u8::try_from(index).map_err(|_| {
+        KaminoClientError::InvalidInput(format!("instruction index {index} is over 255"))
+    })
+}
+
+/// The failure klend would report for instruction `index`.
+fn lending_failure(index: u8, error: LendingError) -> KaminoClientError {
+    KaminoClientError::Program(InstructionFailure {
+        index,
+        error: InstructionError::Custom(error.code()),
+        program_id: Some(KAMINO_LENDING_ID),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use kamino_lend::FractionExtra;
+
+    use super::*;
+    use crate::test_util::zeroed;
+
+    fn reserve(fee_bps: u16) -> Reserve {
+        let mut reserve: Reserve = zeroed();
+        reserve.lending_market = Pubkey::new_unique();
+        reserve.liquidity.mint_pubkey = Pubkey::new_unique();
+        reserve.config.fees.flash_loan_fee_sf = Fraction::from_bps(fee_bps).to_bits() as u64;
+        reserve
+    }
+
+    fn failure(error: KaminoClientError) -> (u8, Option<LendingError>) {
+        let KaminoClientError::Program(failure) = error else { panic!("{error}") };
+        (failure.index, failure.lending_error())
+    }
+
+    #[test]
+    fn test_flash_loan_fee() {
+        let mut state = reserve(30);
+        assert_eq!(flash_loan_fee(&state, 1_000_000), Some(3_000));
+        assert_eq!(flash_loan_fee(&state, 10), Some(1));
+        assert_eq!(flash_loan_fee(&state, 0), Some(0));
+        assert_eq!(flash_loan_fee(&reserve(0), 1_000_000), Some(0));
+        state.config.fees.flash_loan_fee_sf = FLASH_LOANS_DISABLED;
+        assert_eq!(flash_loan_fee(&state, 1_000_000), None);
+    }
+
+    #[test]
+    fn test_compose_flash_loan() {
+        let (user, address, state) = (Pubkey::new_unique(), Pubkey::new_unique(), reserve(30));
+        let compute_budget =
+            ComputeBudget { unit_limit: Some(400_000), unit_price_micro_lamports: Some(1) };
+        let inner = vec![other_instruction(); 2];
+        let loan = compose_flash_loan(
+            &user,
+            &address,
+            &state,
+            None,
+            1_000,
+            &compute_budget,
+            inner.clone(),
+        )
+        .unwrap();
+        // Compute budget, ATA creation, then the borrow.
+        assert_eq!((loan.borrow_index, loan.repay_index, loan.fee), (3, 6, 3));
+        assert_eq!(loan.instructions.len(), 7);
+        assert_eq!(loan.instructions[..2], compute_budget.instructions());
+
+        let mut disabled_state = reserve(0);
+        disabled_state.config.fees.flash_loan_fee_sf = FLASH_LOANS_DISABLED;
+        let error = compose_flash_loan(
+            &user,
+            &address,
+            &disabled_state,
+            None,
+            1_000,
+            &ComputeBudget::default(),
+            inner,
+        )
+        .unwrap_err();
+        assert_eq!(failure(error), (1, Some(LendingError::FlashLoansDisabled)));
+
+        let set_limit = ComputeBudgetInstruction::set_compute_unit_limit(1);
+        let error = compose_flash_loan(
+            &user,
+            &address,
+            &state,
+            None,
+            1_000,
+            &ComputeBudget::default(),
+            [set_limit],
+        )
+        .unwrap_err();
+        assert!(matches!(error, KaminoClientError::InvalidInput(_)), "{error}");
+    }
+
+    #[test]
+    fn test_validate_flash_loans() {
+        let (user, address, state) = (Pubkey::new_unique(), Pubkey::new_unique(), reserve(0));
+        let loan = |inner: Vec<Instruction>| {
+            compose_flash_loan(
+                &user,
+                &address,
+                &state,
+                None,
+                1_000,
+                &ComputeBudget::default(),
+                inner,
+            )
+            .unwrap()
+            .instructions
+        };
+        let accounts = ReserveAccounts::from_state(&address, &state, &KLEND_TOKEN_PROGRAM);
+        let borrow = flash_borrow_reserve_liquidity(&user, &accounts, None, 1_000).unwrap();
+
+        let mut instructions = loan(vec![]);
+        let repay = instructions.pop().unwrap();
+        assert_eq!(
+            failure(validate_flash_loans(&instructions).unwrap_err()),
+            (1, Some(LendingError::NoFlashRepayFound))
+        );
+
+        instructions.insert(2, borrow.clone());
+        instructions.push(repay.clone());
+        assert_eq!(
+            failure(validate_flash_loans(&instructions).unwrap_err()),
+            (1, Some(LendingError::MultipleFlashBorrows))
+        );
+
+        // A repay pointing at another instruction, or of another amount.
+        let mut instructions = loan(vec![other_instruction()]);
+        instructions[3] = flash_repay_reserve_liquidity(&user, &accounts, None, 1_000, 0).unwrap();
+        assert_eq!(
+            failure(validate_flash_loans(&instructions).unwrap_err()),
+            (1, Some(LendingError::InvalidFlashRepay))
+        );
+        instructions[3] = flash_repay_reserve_liquidity(&user, &accounts, None, 999, 1).unwrap();
+        assert_eq!(
+            failure(validate_flash_loans(&instructions).unwrap_err()),
+            (1, Some(LendingError::InvalidFlashRepay))
+        );
+        // A repay without a borrow.
+        assert_eq!(
+            failure(validate_flash_loans(&[other_instruction(), repay]).unwrap_err()),
+            (1, Some(LendingError::InvalidFlashRepay))
+        );
+    }
+
+    /// An instruction of another program.
+    fn other_instruction() -> Instruction {
+        Instruction::new_with_bytes(Pubkey::new_unique(), b"memo", vec![])
+    }
+}
@@ -0,0 +1,336 @@
+//! Offline obligation health, mirroring klend's `refreshObligation`.
+//!
+//! Values are computed from the prices and cumulative borrow rates stored in
+//! the reserves, i.e. as of their last `refreshReserve`.
+
+use std::collections::HashMap;
+
+use anchor_lang::prelude::Pubkey;
+pub use kamino_lend::Fraction;
+use kamino_lend::{
+    state::{LendingMarket, Obligation, Reserve},
+    typedefs::ElevationGroup,
+    FractionExtra,
+};
+
+use crate::error::KaminoClientError;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DepositPosition {
+    pub reserve: Pubkey,
+    /// Deposited collateral (cToken) amount.
+    pub deposited_amount: u64,
+    /// Liquidity the collateral redeems for.
+    pub liquidity_amount: Fraction,
+    pub market_value: Fraction,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BorrowPosition {
+    pub reserve: Pubkey,
+    /// Borrowed liquidity including interest accrued up to the reserve's
+    /// cumulative borrow rate.
+    pub borrowed_amount: Fraction,
+    pub market_value: Fraction,
+    pub borrow_factor_adjusted_market_value: Fraction,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ObligationHealth {
+    pub deposits: Vec<DepositPosition>,
+    pub borrows: Vec<BorrowPosition>,
+    pub deposited_value: Fraction,
+    pub borrowed_assets_market_value: Fraction,
+    pub borrow_factor_adjusted_debt_value: Fraction,
+    pub allowed_borrow_value: Fraction,
+    pub unhealthy_borrow_value: Fraction,
+}
+
+impl ObligationHealth {
+    /// Borrow-factor-adjusted debt over deposited value.
+    pub fn loan_to_value(&self) -> Fraction {
+        ratio(self.borrow_factor_adjusted_debt_value, self.deposited_value)
+    }
+
+    /// The LTV at which the obligation becomes liquidatable.
+    pub fn liquidation_ltv(&self) -> Fraction {
+        ratio(self.unhealthy_borrow_value, self.deposited_value)
+    }
+
+    pub fn is_unhealthy(&self) -> bool {
+        self.borrow_factor_adjusted_debt_value > Fraction::ZERO
+            && self.loan_to_value() >= self.liquidation_ltv()
+    }
+}
+
+/// Computes the health of `obligation`. `reserves` must hold every reserve the
+/// obligation deposits into or borrows from.
+pub fn obligation_health(
+    obligation: &Obligation,
+    lending_market: &LendingMarket,
+    reserves: &HashMap<Pubkey, Reserve>,
+) -> Result<ObligationHealth, KaminoClientError> {
+    let elevation_group = get_elevation_group(lending_market, obligation.elevation_group)?;
+    let mut health = ObligationHealth::default();
+
+    for deposit in obligation.deposits.iter().filter(|d| d.deposit_reserve != Pubkey::default()) {
+        let reserve = get_reserve(reserves, &deposit.deposit_reserve)?;
+        let liquidity_amount = collateral_to_liquidity(reserve, deposit.deposited_amount);
+        let market_value = market_value(reserve, liquidity_amount);
+        let (ltv_pct, liquidation_threshold_pct) =
+            ltv_and_liquidation_threshold_pct(reserve, elevation_group);
+        health.deposited_value += market_value;
+        health.allowed_borrow_value += market_value * Fraction::from_percent(ltv_pct);
+        health.unhealthy_borrow_value +=
+            market_value * Fraction::from_percent(liquidation_threshold_pct);
+        health.deposits.push(DepositPosition {
+            reserve: deposit.deposit_reserve,
+            deposited_amount: deposit.deposited_amount,
+            liquidity_amount,
+            market_value,
+        });
+    }
+
+    for borrow in obligation.borrows.iter().filter(|b| b.borrow_reserve != Pubkey::default()) {
+        let reserve = get_reserve(reserves, &borrow.borrow_reserve)?;
+        let former_rate = borrow.cumulative_borrow_rate();
+        let current_rate = reserve.cumulative_borrow_rate();
+        let mut borrowed_amount = borrow.borrowed_amount();
+        if current_rate > former_rate {
+            borrowed_amount *=
+                Fraction::try_from(current_rate / former_rate).map_err(KaminoClientError::Math)?;
+        }
+        let market_value = market_value(reserve, borrowed_amount);
+        let borrow_factor_adjusted_market_value =
+            market_value * borrow_factor(reserve, elevation_group);
+        health.borrowed_assets_market_value += market_value;
+        health.borrow_factor_adjusted_debt_value += borrow_factor_adjusted_market_value;
+        health.borrows.push(BorrowPosition {
+            reserve: borrow.borrow_reserve,
+            borrowed_amount,
+            market_value,
+            borrow_factor_adjusted_market_value,
+        });
+    }
+    Ok(health)
+}
+
+/// Liquidity the reserve holds or has lent out, net of fees owed to the
+/// protocol and referrers.
+pub fn total_liquidity(reserve: &Reserve) -> Fraction {
+    Fraction::from_num(reserve.liquidity.available_amount) + reserve.borrowed_amount()
+        - reserve.accumulated_protocol_fees()
+        - reserve.accumulated_referrer_fees()
+        - reserve.pending_referrer_fees()
+}
+
+/// Collateral minted per unit of liquidity; 1 for an empty reserve.
+pub fn collateral_exchange_rate(reserve: &Reserve) -> Fraction {
+    let total_liquidity = total_liquidity(reserve);
+    let mint_total_supply = reserve.collateral.mint_total_supply;
+    if mint_total_supply == 0 || total_liquidity == Fraction::ZERO {
+        Fraction::ONE
+    } else {
+        Fraction::from_num(mint_total_supply) / total_liquidity
+    }
+}
+
+pub fn collateral_to_liquidity(reserve: &Reserve, collateral_amount: u64) -> Fraction {
+    Fraction::from_num(collateral_amount) / collateral_exchange_rate(reserve)
+}
+
+/// Collateral `liquidity_amount` is worth, rounded down as klend does.
+pub fn liquidity_to_collateral(reserve: &Reserve, liquidity_amount: u64) -> u64 {
+    (Fraction::from_num(liquidity_amount) * collateral_exchange_rate(reserve)).saturating_to_num()
+}
+
+/// The most collateral `obligation` can withdraw from `reserve` and keep its
+/// debt within its allowed borrow value; klend withdraws as much for a
+/// collateral amount of `u64::MAX`.
+pub fn max_withdraw_collateral(
+    obligation: &Obligation,
+    lending_market: &LendingMarket,
+    reserves: &HashMap<Pubkey, Reserve>,
+    reserve: &Pubkey,
+) -> Result<u64, KaminoClientError> {
+    let health = obligation_health(obligation, lending_market, reserves)?;
+    let deposit =
+        health.deposits.iter().find(|deposit| deposit.reserve == *reserve).ok_or_else(|| {
+            KaminoClientError::InvalidInput(format!("obligation has no deposit in {reserve}"))
+        })?;
+    if health.borrows.is_empty() {
+        return Ok(deposit.deposited_amount);
+    }
+    if health.allowed_borrow_value <= health.borrow_factor_adjusted_debt_value {
+        return Ok(0);
+    }
+    let elevation_group = get_elevation_group(lending_market, obligation.elevation_group)?;
+    let (ltv_pct, _) =
+        ltv_and_liquidation_threshold_pct(get_reserve(reserves, reserve)?, elevation_group);
+    if ltv_pct == 0 {
+        return Ok(deposit.deposited_amount);
+    }
+    let max_withdraw_value = (health.allowed_borrow_value
+        - health.borrow_factor_adjusted_debt_value)
+        / Fraction::from_percent(ltv_pct);
+    if max_withdraw_value >= deposit.market_value {
+        return Ok(deposit.deposited_amount);
+    }
+    let withdraw_share = max_withdraw_value / deposit.market_value;
+    Ok((Fraction::from_num(deposit.deposited_amount) * withdraw_share).to_num())
+}
+
+/// Value of `amount` lamports of the reserve's liquidity at its last price.
+pub fn market_value(reserve: &Reserve, amount: Fraction) -> Fraction {
+    let factor = 10u128.pow(reserve.liquidity.mint_decimals as u32);
+    amount * reserve.market_price() / factor
+}
+
+/// The elevation group `id` of the market, `None` for the default group 0.
+pub fn get_elevation_group(
+    lending_market: &LendingMarket,
+    id: u8,
+) -> Result<Option<&ElevationGroup>, KaminoClientError> {
+    if id == 0 {
+        return Ok(None);
+    }
+    lending_market
+        .elevation_groups
+        .get(usize::from(id) - 1)
+        .filter(|group| group.id == id)
+        .map(Some)
+        .ok_or_else(|| {
+            KaminoClientError::InvalidInput(format!("elevation group {id} is not configured"))
+        })
+}
+
+/// LTV and liquidation threshold of deposits, overridden by the elevation
+/// group when the obligation is in one.
+pub fn ltv_and_liquidation_threshold_pct(
+    reserve: &Reserve,
+    elevation_group: Option<&ElevationGroup>,
+) -> (u8, u8) {
+    match elevation_group {
+        Some(group) => (group.ltv_pct, group.liquidation_threshold_pct),
+        None => (reserve.config.loan_to_value_pct, reserve.config.liquidation_threshold_pct),
+    }
+}
+
+/// Borrow factor of debt; elevation groups ignore it.
+pub fn borrow_factor(reserve: &Reserve, elevation_group: Option<&ElevationGroup>) -> Fraction {
+    match elevation_group {
+        Some(_) => Fraction::ONE,
+        None => Fraction::from_num(reserve.config.borrow_factor_pct.max(100)) / 100,
+    }
+}
+
+fn ratio(numerator: Fraction, denominator: Fraction) -> Fraction {
+    if denominator == Fraction::ZERO {
+        Fraction::ZERO
+    } else {
+        numerator / denominator
+    }
+}
+
+fn get_reserve<'a>(
+    reserves: &'a HashMap<Pubkey, Reserve>,
+    reserve: &Pubkey,
+) -> Result<&'a Reserve, KaminoClientError> {
+    reserves.get(reserve).ok_or(KaminoClientError::AccountNotFound(*reserve))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::zeroed;
+
+    fn reserve(price: u64, ltv_pct: u8, liquidation_threshold_pct: u8) -> Reserve {
+        let mut reserve: Reserve = zeroed();
+        reserve.liquidity.mint_decimals = 6;
+        reserve.liquidity.market_price_sf = Fraction::from_num(price).to_bits();
+        reserve.liquidity.available_amount = 1_000_000;
+        reserve.liquidity.cumulative_borrow_rate_bsf.value[0] = 1 << 60;
+        reserve.collateral.mint_total_supply = 500_000;
+        reserve.config.loan_to_value_pct = ltv_pct;
+        reserve.config.liquidation_threshold_pct = liquidation_threshold_pct;
+        reserve.config.borrow_factor_pct = 100;
+        reserve
+    }
+
+    fn set_up() -> (Obligation, LendingMarket, HashMap<Pubkey, Reserve>) {
+        let collateral_reserve = Pubkey::new_unique();
+        let debt_reserve = Pubkey::new_unique();
+        let mut debt = reserve(1, 0, 0);
+        debt.config.borrow_factor_pct = 200;
+        debt.liquidity.cumulative_borrow_rate_bsf.value[0] = 3 << 60;
+
+        let mut obligation: Obligation = zeroed();
+        obligation.deposits[0].deposit_reserve = collateral_reserve;
+        obligation.deposits[0].deposited_amount = 500_000;
+        obligation.borrows[0].borrow_reserve = debt_reserve;
+        obligation.borrows[0].borrowed_amount_sf = Fraction::from_num(1_000_000).to_bits();
+        obligation.borrows[0].cumulative_borrow_rate_bsf.value[0] = 2 << 60;
+
+        let reserves =
+            HashMap::from([(collateral_reserve, reserve(2, 50, 75)), (debt_reserve, debt)]);
+        (obligation, zeroed(), reserves)
+    }
+
+    #[test]
+    fn test_obligation_health() {
+        let (obligation, lending_market, reserves) = set_up();
+        let health = obligation_health(&obligation, &lending_market, &reserves).unwrap();
+
+        // 500_000 cTokens redeem for 1_000_000 lamports, i.e. 1 token at $2.
+        assert_eq!(health.deposited_value, Fraction::from_num(2));
+        assert_eq!(health.allowed_borrow_value, Fraction::from_num(1));
+        assert_eq!(health.unhealthy_borrow_value, Fraction::from_num(1.5));
+        // The cumulative borrow rate grew by 1.5x since the borrow.
+        assert_eq!(health.borrows[0].borrowed_amount, Fraction::from_num(1_500_000));
+        assert_eq!(health.borrowed_assets_market_value, Fraction::from_num(1.5));
+        assert_eq!(health.borrow_factor_adjusted_debt_value, Fraction::from_num(3));
+        assert_eq!(health.loan_to_value(), Fraction::from_num(1.5));
+        assert_eq!(health.liquidation_ltv(), Fraction::from_num(0.75));
+        assert!(health.is_unhealthy());
+    }
+
+    #[test]
+    fn test_elevation_group_overrides() {
+        let (mut obligation, mut lending_market, reserves) = set_up();
+        obligation.elevation_group = 1;
+        assert!(obligation_health(&obligation, &lending_market, &reserves).is_err());
+
+        lending_market.elevation_groups[0].id = 1;
+        lending_market.elevation_groups[0].ltv_pct = 75;
+        lending_market.elevation_groups[0].liquidation_threshold_pct = 90;
+        let health = obligation_health(&obligation, &lending_market, &reserves).unwrap();
+        assert_eq!(health.allowed_borrow_value, Fraction::from_num(1.5));
+        assert_eq!(health.borrow_factor_adjusted_debt_value, Fraction::from_num(1.5));
+        assert!(!health.is_unhealthy());
+    }
+
+    #[test]
+    fn test_max_withdraw_collateral() {
+        let (mut obligation, lending_market, mut reserves) = set_up();
+        let collateral = obligation.deposits[0].deposit_reserve;
+        assert_eq!(liquidity_to_collateral(&reserves[&collateral], 1_000_001), 500_000);
+        // Debt over the allowed borrow value leaves nothing to withdraw.
+        let max = max_withdraw_collateral(&obligation, &lending_market, &reserves, &collateral);
+        assert_eq!(max.unwrap(), 0);
+
+        // $1.5 of debt against $4 of deposits at 50% LTV: withdrawing $1 of
+        // the deposit, i.e. a quarter, brings the allowed borrow value down
+        // to the debt.
+        let debt = obligation.borrows[0].borrow_reserve;
+        reserves.get_mut(&debt).unwrap().config.borrow_factor_pct = 100;
+        reserves.get_mut(&collateral).unwrap().liquidity.market_price_sf =
+            Fraction::from_num(4).to_bits();
+        let max = max_withdraw_collateral(&obligation, &lending_market, &reserves, &collateral);
+        assert_eq!(max.unwrap(), 125_000);
+
+        obligation.borrows[0] = zeroed();
+        let max = max_withdraw_collateral(&obligation, &lending_market, &reserves, &collateral);
+        assert_eq!(max.unwrap(), 500_000);
+        assert!(max_withdraw_collateral(&obligation, &lending_market, &reserves, &debt).is_err());
+    }
+}
@@ -0,0 +1,104 @@
+use anchor_lang::{
+    prelude::Pubkey,
+    solana_program::{instruction::Instruction, system_program::ID as SYSTEM_ID, sysvar},
+    InstructionData, ToAccountMetas,
+};
+use kamino_lend::{accounts, instruction, typedefs::ReserveFarmKind, ID as KAMINO_LENDING_ID};
+
+use super::ReserveAccounts;
+use crate::{
+    error::KaminoClientError,
+    pda::{self, farms::ID as FARMS_PROGRAM_ID},
+};
+
+/// Attaches `farm_state`, an account pre-allocated for the farms program, to
+/// the reserve as its collateral or debt farm.
+pub fn init_farms_for_reserve(
+    lending_market_owner: &Pubkey,
+    reserve: &ReserveAccounts,
+    farms_global_config: &Pubkey,
+    farm_state: &Pubkey,
+    kind: ReserveFarmKind,
+) -> Result<Instruction, KaminoClientError> {
+    Ok(Instruction {
+        program_id: KAMINO_LENDING_ID,
+        accounts: accounts::InitFarmsForReserve {
+            lending_market_owner: *lending_market_owner,
+            lending_market: reserve.lending_market,
+            lending_market_authority: reserve.lending_market_authority(),
+            reserve: reserve.address,
+            farms_program: FARMS_PROGRAM_ID,
+            farms_global_config: *farms_global_config,
+            farm_state: *farm_state,
+            farms_vault_authority: pda::get_farm_vault_authority(farm_state),
+            rent: sysvar::rent::ID,
+            token_program: reserve.token_program,
+            system_program: SYSTEM_ID,
+        }
+        .to_account_metas(Some(true)),
+        data: instruction::InitFarmsForReserve { _mode: kind as u8 }.data(),
+    })
+}
+
+/// Creates the farm user state of `obligation` in the reserve's
+/// `reserve_farm_state`.
+pub fn init_obligation_farms_for_reserve(
+    payer: &Pubkey,
+    owner: &Pubkey,
+    obligation: &Pubkey,
+    reserve: &ReserveAccounts,
+    reserve_farm_state: &Pubkey,
+    kind: ReserveFarmKind,
+) -> Result<Instruction, KaminoClientError> {
+    Ok(Instruction {
+        program_id: KAMINO_LENDING_ID,
+        accounts: accounts::InitObligationFarmsForReserve {
+            payer: *payer,
+            owner: *owner,
+            obligation: *obligation,
+            lending_market_authority: reserve.lending_market_authority(),
+            reserve: reserve.address,
+            reserve_farm_state: *reserve_farm_state,
+            obligation_farm: pda::get_obligation_farm_user_state(reserve_farm_state, obligation),
+            lending_market: reserve.lending_market,
+            farms_program: FARMS_PROGRAM_ID,
+            rent: sysvar::rent::ID,
+            token_program: reserve.token_program,
+            system_program: SYSTEM_ID,
+        }
+        .to_account_metas(Some(true)),
+        data: instruction::InitObligationFarmsForReserve { _mode: kind as u8 }.data(),
+    })
+}
+
+/// Syncs the obligation's stake in `reserve_farm_state` with its current
+/// deposit or borrow in the reserve.
+pub fn refresh_obligation_farms_for_reserve(
+    crank: &Pubkey,
+    obligation: &Pubkey,
+    reserve: &ReserveAccounts,
+    reserve_farm_state: &Pubkey,
+    kind: ReserveFarmKind,
+) -> Result<Instruction, KaminoClientError> {
+    Ok(Instruction {
+        program_id: KAMINO_LENDING_ID,
+        accounts: accounts::RefreshObligationFarmsForReserve {
+            crank: *crank,
+            obligation: *obligation,
+            lending_market_authority: reserve.lending_market_authority(),
+            reserve: reserve.address,
+            reserve_farm_state: *reserve_farm_state,
+            obligation_farm_user_state: pda::get_obligation_farm_user_state(
+                reserve_farm_state,
+                obligation,
+            ),
+            lending_market: reserve.lending_market,
+            farms_program: FARMS_PROGRAM_ID,
+            rent: sysvar::rent::ID,
+            token_program: reserve.token_program,
+            system_program: SYSTEM_ID,
+        }
+        .to_account_metas(Some(true)),
+        data: instruction::RefreshObligationFarmsForReserve { _mode: kind as u8 }.data(),
+    })
+}
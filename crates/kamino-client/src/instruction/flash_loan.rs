@@ -0,0 +1,73 @@
+use anchor_lang::{
+    prelude::Pubkey,
+    solana_program::{instruction::Instruction, sysvar},
+    InstructionData, ToAccountMetas,
+};
+use kamino_lend::{accounts, instruction, ID as KAMINO_LENDING_ID};
+
+use super::{optional_account, ReserveAccounts};
+use crate::{error::KaminoClientError, pda};
+
+/// Borrows into `user`'s liquidity ATA; must be followed in the same
+/// transaction by [`flash_repay_reserve_liquidity`].
+pub fn flash_borrow_reserve_liquidity(
+    user: &Pubkey,
+    reserve: &ReserveAccounts,
+    referrer: Option<&Pubkey>,
+    liquidity_amount: u64,
+) -> Result<Instruction, KaminoClientError> {
+    let referrer_token_state = referrer.map(|r| pda::get_referrer_token_state(r, &reserve.address));
+    Ok(Instruction {
+        program_id: KAMINO_LENDING_ID,
+        accounts: accounts::FlashBorrowReserveLiquidity {
+            user_transfer_authority: *user,
+            lending_market_authority: reserve.lending_market_authority(),
+            lending_market: reserve.lending_market,
+            reserve: reserve.address,
+            reserve_source_liquidity: reserve.liquidity_supply,
+            user_destination_liquidity: reserve.user_liquidity_ata(user),
+            reserve_liquidity_fee_receiver: reserve.fee_vault,
+            referrer_token_state: optional_account(referrer_token_state.as_ref()),
+            referrer_account: optional_account(referrer),
+            sysvar_info: sysvar::instructions::ID,
+            token_program: reserve.token_program,
+        }
+        .to_account_metas(Some(true)),
+        data: instruction::FlashBorrowReserveLiquidity { _liquidity_amount: liquidity_amount }
+            .data(),
+    })
+}
+
+/// Repays a flash loan from `user`'s liquidity ATA. `borrow_instruction_index`
+/// is the index of the matching borrow in the transaction.
+pub fn flash_repay_reserve_liquidity(
+    user: &Pubkey,
+    reserve: &ReserveAccounts,
+    referrer: Option<&Pubkey>,
+    liquidity_amount: u64,
+    borrow_instruction_index: u8,
+) -> Result<Instruction, KaminoClientError> {
+    let referrer_token_state = referrer.map(|r| pda::get_referrer_token_state(r, &reserve.address));
+    Ok(Instruction {
+        program_id: KAMINO_LENDING_ID,
+        accounts: accounts::FlashRepayReserveLiquidity {
+            user_transfer_authority: *user,
+            lending_market_authority: reserve.lending_market_authority(),
+            lending_market: reserve.lending_market,
+            reserve: reserve.address,
+            reserve_destination_liquidity: reserve.liquidity_supply,
+            user_source_liquidity: reserve.user_liquidity_ata(user),
+            reserve_liquidity_fee_receiver: reserve.fee_vault,
+            referrer_token_state: optional_account(referrer_token_state.as_ref()),
+            referrer_account: optional_account(referrer),
+            sysvar_info: sysvar::instructions::ID,
+            token_program: reserve.token_program,
+        }
+        .to_account_metas(Some(true)),
+        data: instruction::FlashRepayReserveLiquidity {
+            _liquidity_amount: liquidity_amount,
+            _borrow_instruction_index: borrow_instruction_index,
+        }
+        .data(),
+    })
+}
@@ -0,0 +1,79 @@
+use anchor_lang::{
+    prelude::Pubkey,
+    solana_program::{instruction::Instruction, system_program::ID as SYSTEM_ID, sysvar},
+    InstructionData, ToAccountMetas,
+};
+use kamino_lend::{accounts, instruction, state::LendingMarket, ID as KAMINO_LENDING_ID};
+
+use crate::{
+    error::KaminoClientError,
+    lending_market::{diff_lending_market, LendingMarketUpdate},
+    pda,
+};
+
+pub fn init_lending_market(
+    owner: &Pubkey,
+    lending_market: &Pubkey,
+    quote_currency: [u8; 32],
+) -> Result<Instruction, KaminoClientError> {
+    Ok(Instruction {
+        program_id: KAMINO_LENDING_ID,
+        accounts: accounts::InitLendingMarket {
+            lending_market_owner: *owner,
+            lending_market: *lending_market,
+            lending_market_authority: pda::get_market_authority(lending_market),
+            system_program: SYSTEM_ID,
+            rent: sysvar::rent::ID,
+        }
+        .to_account_metas(Some(true)),
+        data: instruction::InitLendingMarket { _quote_currency: quote_currency }.data(),
+    })
+}
+
+pub fn update_lending_market(
+    owner: &Pubkey,
+    lending_market: &Pubkey,
+    update: &LendingMarketUpdate,
+) -> Result<Instruction, KaminoClientError> {
+    let (mode, value) = update.encode()?;
+    Ok(Instruction {
+        program_id: KAMINO_LENDING_ID,
+        accounts: accounts::UpdateLendingMarket {
+            lending_market_owner: *owner,
+            lending_market: *lending_market,
+        }
+        .to_account_metas(Some(true)),
+        data: instruction::UpdateLendingMarket { _mode: mode, _value: value }.data(),
+    })
+}
+
+/// One `updateLendingMarket` per field that differs between `current` and
+/// `desired`, in the order of [`diff_lending_market`].
+pub fn update_lending_market_to(
+    owner: &Pubkey,
+    lending_market: &Pubkey,
+    current: &LendingMarket,
+    desired: &LendingMarket,
+) -> Result<Vec<Instruction>, KaminoClientError> {
+    diff_lending_market(current, desired)?
+        .iter()
+        .map(|update| update_lending_market(owner, lending_market, update))
+        .collect()
+}
+
+/// Accepts a pending ownership transfer; `owner_cached` is the new owner set
+/// with `UpdateOwner`.
+pub fn update_lending_market_owner(
+    owner_cached: &Pubkey,
+    lending_market: &Pubkey,
+) -> Result<Instruction, KaminoClientError> {
+    Ok(Instruction {
+        program_id: KAMINO_LENDING_ID,
+        accounts: accounts::UpdateLendingMarketOwner {
+            lending_market_owner_cached: *owner_cached,
+            lending_market: *lending_market,
+        }
+        .to_account_metas(Some(true)),
+        data: instruction::UpdateLendingMarketOwner {}.data(),
+    })
+}
@@ -0,0 +1,100 @@
+//! Builders for every klend instruction.
+//!
+//! Each builder derives the PDAs it needs and fills unused optional accounts
+//! with the klend program id, which is how Anchor encodes `None`.
+//! `idlMissingTypes` only exists to export types through the IDL and has no
+//! builder.
+
+mod farms;
+mod flash_loan;
+mod market;
+mod obligation;
+mod referrer;
+mod reserve;
+
+use anchor_lang::prelude::Pubkey;
+pub use farms::*;
+pub use flash_loan::*;
+use kamino_lend::{state::Reserve, ID as KAMINO_LENDING_ID};
+pub use market::*;
+pub use obligation::*;
+pub use referrer::*;
+pub use reserve::*;
+use spl_associated_token_account::get_associated_token_address_with_program_id;
+
+use crate::pda;
+
+/// Addresses of a reserve and the vaults and mints it owns.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ReserveAccounts {
+    pub address: Pubkey,
+    pub lending_market: Pubkey,
+    pub liquidity_mint: Pubkey,
+    pub liquidity_supply: Pubkey,
+    pub fee_vault: Pubkey,
+    pub collateral_mint: Pubkey,
+    pub collateral_supply: Pubkey,
+    pub token_program: Pubkey,
+}
+
+impl ReserveAccounts {
+    /// Derives the vault and mint addresses of a reserve initialized by
+    /// [`init_reserve`].
+    pub fn new(
+        lending_market: &Pubkey,
+        reserve: &Pubkey,
+        liquidity_mint: &Pubkey,
+        token_program: &Pubkey,
+    ) -> Self {
+        Self {
+            address: *reserve,
+            lending_market: *lending_market,
+            liquidity_mint: *liquidity_mint,
+            liquidity_supply: pda::get_reserve_liquidity_supply(lending_market, liquidity_mint),
+            fee_vault: pda::get_reserve_fee_vault(lending_market, liquidity_mint),
+            collateral_mint: pda::get_reserve_collateral_mint(lending_market, liquidity_mint),
+            collateral_supply: pda::get_reserve_collateral_supply(lending_market, liquidity_mint),
+            token_program: *token_program,
+        }
+    }
+
+    /// Reads the addresses stored in a decoded reserve account.
+    pub fn from_state(reserve: &Pubkey, state: &Reserve, token_program: &Pubkey) -> Self {
+        Self {
+            address: *reserve,
+            lending_market: state.lending_market,
+            liquidity_mint: state.liquidity.mint_pubkey,
+            liquidity_supply: state.liquidity.supply_vault,
+            fee_vault: state.liquidity.fee_vault,
+            collateral_mint: state.collateral.mint_pubkey,
+            collateral_supply: state.collateral.supply_vault,
+            token_program: *token_program,
+        }
+    }
+
+    pub fn lending_market_authority(&self) -> Pubkey {
+        pda::get_market_authority(&self.lending_market)
+    }
+
+    /// Associated liquidity token account of `owner`.
+    pub fn user_liquidity_ata(&self, owner: &Pubkey) -> Pubkey {
+        get_associated_token_address_with_program_id(
+            owner,
+            &self.liquidity_mint,
+            &self.token_program,
+        )
+    }
+
+    /// Associated collateral (cToken) account of `owner`.
+    pub fn user_collateral_ata(&self, owner: &Pubkey) -> Pubkey {
+        get_associated_token_address_with_program_id(
+            owner,
+            &self.collateral_mint,
+            &self.token_program,
+        )
+    }
+}
+
+fn optional_account(account: Option<&Pubkey>) -> Pubkey {
+    account.copied().unwrap_or(KAMINO_LENDING_ID)
+}
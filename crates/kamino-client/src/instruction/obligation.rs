@@ -0,0 +1,367 @@
+use anchor_lang::{
+    prelude::Pubkey,
+    solana_program::{
+        instruction::{AccountMeta, Instruction},
+        system_program::ID as SYSTEM_ID,
+        sysvar,
+    },
+    InstructionData, ToAccountMetas,
+};
+use kamino_lend::{accounts, instruction, state::Obligation, ID as KAMINO_LENDING_ID};
+
+use super::{optional_account, ReserveAccounts};
+use crate::{error::KaminoClientError, obligation::ObligationType, pda};
+
+pub fn init_user_metadata(
+    user: &Pubkey,
+    referrer_user_metadata: Option<&Pubkey>,
+) -> Result<Instruction, KaminoClientError> {
+    Ok(Instruction {
+        program_id: KAMINO_LENDING_ID,
+        accounts: accounts::InitUserMetadata {
+            user_metadata: pda::get_user_metadata(user),
+            owner: *user,
+            fee_payer: *user,
+            referrer_user_metadata: optional_account(referrer_user_metadata),
+            system_program: SYSTEM_ID,
+            rent: sysvar::rent::ID,
+        }
+        .to_account_metas(Some(true)),
+        data: instruction::InitUserMetadata { _user_lookup_table: Pubkey::default() }.data(),
+    })
+}
+
+pub fn init_obligation(
+    owner: &Pubkey,
+    lending_market: &Pubkey,
+    token_program: &Pubkey,
+    obligation_type: &ObligationType,
+) -> Result<Instruction, KaminoClientError> {
+    let (seed1_account, seed2_account) = obligation_type.seed_accounts();
+    Ok(Instruction {
+        program_id: KAMINO_LENDING_ID,
+        accounts: accounts::InitObligation {
+            obligation_owner: *owner,
+            fee_payer: *owner,
+            obligation: obligation_type.address(lending_market, owner),
+            lending_market: *lending_market,
+            seed1_account,
+            seed2_account,
+            owner_user_metadata: pda::get_user_metadata(owner),
+            token_program: *token_program,
+            system_program: SYSTEM_ID,
+            rent: sysvar::rent::ID,
+        }
+        .to_account_metas(Some(true)),
+        data: instruction::InitObligation { _args: obligation_type.to_args() }.data(),
+    })
+}
+
+/// Refreshes an obligation. klend expects its deposit reserves, then its
+/// borrow reserves, then (when it has a referrer) the referrer token state of
+/// each borrow reserve as remaining accounts.
+pub fn refresh_obligation(
+    obligation: &Pubkey,
+    state: &Obligation,
+) -> Result<Instruction, KaminoClientError> {
+    let mut accounts = accounts::RefreshObligation {
+        obligation: *obligation,
+        lending_market: state.lending_market,
+    }
+    .to_account_metas(Some(true));
+    let deposit_reserves = state
+        .deposits
+        .iter()
+        .map(|deposit| deposit.deposit_reserve)
+        .filter(|reserve| *reserve != Pubkey::default());
+    let borrow_reserves = state
+        .borrows
+        .iter()
+        .map(|borrow| borrow.borrow_reserve)
+        .filter(|reserve| *reserve != Pubkey::default());
+    accounts.extend(
+        deposit_reserves
+            .chain(borrow_reserves.clone())
+            .map(|reserve| AccountMeta::new_readonly(reserve, false)),
+    );
+    if state.referrer != Pubkey::default() {
+        accounts.extend(borrow_reserves.map(|reserve| {
+            AccountMeta::new(pda::get_referrer_token_state(&state.referrer, &reserve), false)
+        }));
+    }
+    Ok(Instruction {
+        program_id: KAMINO_LENDING_ID,
+        accounts,
+        data: instruction::RefreshObligation {}.data(),
+    })
+}
+
+pub fn deposit_reserve_liquidity_and_obligation_collateral(
+    owner: &Pubkey,
+    obligation: &Pubkey,
+    reserve: &ReserveAccounts,
+    liquidity_amount: u64,
+) -> Result<Instruction, KaminoClientError> {
+    Ok(Instruction {
+        program_id: KAMINO_LENDING_ID,
+        accounts: accounts::DepositReserveLiquidityAndObligationCollateral {
+            owner: *owner,
+            obligation: *obligation,
+            lending_market: reserve.lending_market,
+            lending_market_authority: reserve.lending_market_authority(),
+            reserve: reserve.address,
+            reserve_liquidity_supply: reserve.liquidity_supply,
+            reserve_collateral_mint: reserve.collateral_mint,
+            reserve_destination_deposit_collateral: reserve.collateral_supply,
+            user_source_liquidity: reserve.user_liquidity_ata(owner),
+            placeholder_user_destination_collateral: KAMINO_LENDING_ID,
+            token_program: reserve.token_program,
+            instruction_sysvar_account: sysvar::instructions::ID,
+        }
+        .to_account_metas(Some(true)),
+        data: instruction::DepositReserveLiquidityAndObligationCollateral {
+            _liquidity_amount: liquidity_amount,
+        }
+        .data(),
+    })
+}
+
+/// Moves cTokens held in `owner`'s collateral ATA into the obligation.
+pub fn deposit_obligation_collateral(
+    owner: &Pubkey,
+    obligation: &Pubkey,
+    reserve: &ReserveAccounts,
+    collateral_amount: u64,
+) -> Result<Instruction, KaminoClientError> {
+    Ok(Instruction {
+        program_id: KAMINO_LENDING_ID,
+        accounts: accounts::DepositObligationCollateral {
+            owner: *owner,
+            obligation: *obligation,
+            lending_market: reserve.lending_market,
+            deposit_reserve: reserve.address,
+            reserve_destination_collateral: reserve.collateral_supply,
+            user_source_collateral: reserve.user_collateral_ata(owner),
+            token_program: reserve.token_program,
+            instruction_sysvar_account: sysvar::instructions::ID,
+        }
+        .to_account_metas(Some(true)),
+        data: instruction::DepositObligationCollateral { _collateral_amount: collateral_amount }
+            .data(),
+    })
+}
+
+/// Withdraws cTokens from the obligation into `owner`'s collateral ATA.
+pub fn withdraw_obligation_collateral(
+    owner: &Pubkey,
+    obligation: &Pubkey,
+    reserve: &ReserveAccounts,
+    collateral_amount: u64,
+) -> Result<Instruction, KaminoClientError> {
+    Ok(Instruction {
+        program_id: KAMINO_LENDING_ID,
+        accounts: accounts::WithdrawObligationCollateral {
+            owner: *owner,
+            obligation: *obligation,
+            lending_market: reserve.lending_market,
+            lending_market_authority: reserve.lending_market_authority(),
+            withdraw_reserve: reserve.address,
+            reserve_source_collateral: reserve.collateral_supply,
+            user_destination_collateral: reserve.user_collateral_ata(owner),
+            token_program: reserve.token_program,
+            instruction_sysvar_account: sysvar::instructions::ID,
+        }
+        .to_account_metas(Some(true)),
+        data: instruction::WithdrawObligationCollateral { _collateral_amount: collateral_amount }
+            .data(),
+    })
+}
+
+/// Withdraws cTokens from the obligation and redeems them into `owner`'s
+/// liquidity ATA.
+pub fn withdraw_obligation_collateral_and_redeem_reserve_collateral(
+    owner: &Pubkey,
+    obligation: &Pubkey,
+    reserve: &ReserveAccounts,
+    collateral_amount: u64,
+) -> Result<Instruction, KaminoClientError> {
+    Ok(Instruction {
+        program_id: KAMINO_LENDING_ID,
+        accounts: accounts::WithdrawObligationCollateralAndRedeemReserveCollateral {
+            owner: *owner,
+            obligation: *obligation,
+            lending_market: reserve.lending_market,
+            lending_market_authority: reserve.lending_market_authority(),
+            withdraw_reserve: reserve.address,
+            reserve_source_collateral: reserve.collateral_supply,
+            reserve_collateral_mint: reserve.collateral_mint,
+            reserve_liquidity_supply: reserve.liquidity_supply,
+            user_destination_liquidity: reserve.user_liquidity_ata(owner),
+            placeholder_user_destination_collateral: KAMINO_LENDING_ID,
+            token_program: reserve.token_program,
+            instruction_sysvar_account: sysvar::instructions::ID,
+        }
+        .to_account_metas(Some(true)),
+        data: instruction::WithdrawObligationCollateralAndRedeemReserveCollateral {
+            _collateral_amount: collateral_amount,
+        }
+        .data(),
+    })
+}
+
+/// Borrows into `owner`'s liquidity ATA. `referrer_token_state` must be set
+/// when the owner's user metadata has a referrer.
+pub fn borrow_obligation_liquidity(
+    owner: &Pubkey,
+    obligation: &Pubkey,
+    reserve: &ReserveAccounts,
+    referrer_token_state: Option<&Pubkey>,
+    liquidity_amount: u64,
+) -> Result<Instruction, KaminoClientError> {
+    Ok(Instruction {
+        program_id: KAMINO_LENDING_ID,
+        accounts: accounts::BorrowObligationLiquidity {
+            owner: *owner,
+            obligation: *obligation,
+            lending_market: reserve.lending_market,
+            lending_market_authority: reserve.lending_market_authority(),
+            borrow_reserve: reserve.address,
+            reserve_source_liquidity: reserve.liquidity_supply,
+            borrow_reserve_liquidity_fee_receiver: reserve.fee_vault,
+            user_destination_liquidity: reserve.user_liquidity_ata(owner),
+            referrer_token_state: optional_account(referrer_token_state),
+            token_program: reserve.token_program,
+            instruction_sysvar_account: sysvar::instructions::ID,
+        }
+        .to_account_metas(Some(true)),
+        data: instruction::BorrowObligationLiquidity { _liquidity_amount: liquidity_amount }.data(),
+    })
+}
+
+/// Repays from `owner`'s liquidity ATA. `u64::MAX` repays the whole debt.
+pub fn repay_obligation_liquidity(
+    owner: &Pubkey,
+    obligation: &Pubkey,
+    reserve: &ReserveAccounts,
+    liquidity_amount: u64,
+) -> Result<Instruction, KaminoClientError> {
+    Ok(Instruction {
+        program_id: KAMINO_LENDING_ID,
+        accounts: accounts::RepayObligationLiquidity {
+            owner: *owner,
+            obligation: *obligation,
+            lending_market: reserve.lending_market,
+            repay_reserve: reserve.address,
+            reserve_destination_liquidity: reserve.liquidity_supply,
+            user_source_liquidity: reserve.user_liquidity_ata(owner),
+            token_program: reserve.token_program,
+            instruction_sysvar_account: sysvar::instructions::ID,
+        }
+        .to_account_metas(Some(true)),
+        data: instruction::RepayObligationLiquidity { _liquidity_amount: liquidity_amount }.data(),
+    })
+}
+
+/// Repays debt of `repay_reserve` from the liquidator's ATA and redeems the
+/// seized collateral of `withdraw_reserve` into the liquidator's ATAs.
+pub fn liquidate_obligation_and_redeem_reserve_collateral(
+    liquidator: &Pubkey,
+    obligation: &Pubkey,
+    repay_reserve: &ReserveAccounts,
+    withdraw_reserve: &ReserveAccounts,
+    liquidity_amount: u64,
+    min_acceptable_received_collateral_amount: u64,
+    max_allowed_ltv_override_percent: u64,
+) -> Result<Instruction, KaminoClientError> {
+    Ok(Instruction {
+        program_id: KAMINO_LENDING_ID,
+        accounts: accounts::LiquidateObligationAndRedeemReserveCollateral {
+            liquidator: *liquidator,
+            obligation: *obligation,
+            lending_market: repay_reserve.lending_market,
+            lending_market_authority: repay_reserve.lending_market_authority(),
+            repay_reserve: repay_reserve.address,
+            repay_reserve_liquidity_supply: repay_reserve.liquidity_supply,
+            withdraw_reserve: withdraw_reserve.address,
+            withdraw_reserve_collateral_mint: withdraw_reserve.collateral_mint,
+            withdraw_reserve_collateral_supply: withdraw_reserve.collateral_supply,
+            withdraw_reserve_liquidity_supply: withdraw_reserve.liquidity_supply,
+            withdraw_reserve_liquidity_fee_receiver: withdraw_reserve.fee_vault,
+            user_source_liquidity: repay_reserve.user_liquidity_ata(liquidator),
+            user_destination_collateral: withdraw_reserve.user_collateral_ata(liquidator),
+            user_destination_liquidity: withdraw_reserve.user_liquidity_ata(liquidator),
+            token_program: repay_reserve.token_program,
+            instruction_sysvar_account: sysvar::instructions::ID,
+        }
+        .to_account_metas(Some(true)),
+        data: instruction::LiquidateObligationAndRedeemReserveCollateral {
+            _liquidity_amount: liquidity_amount,
+            _min_acceptable_received_collateral_amount: min_acceptable_received_collateral_amount,
+            _max_allowed_ltv_override_percent: max_allowed_ltv_override_percent,
+        }
+        .data(),
+    })
+}
+
+/// Writes off `liquidity_amount` of bad debt; signed by the market's risk
+/// council.
+pub fn socialize_loss(
+    risk_council: &Pubkey,
+    obligation: &Pubkey,
+    reserve: &ReserveAccounts,
+    liquidity_amount: u64,
+) -> Result<Instruction, KaminoClientError> {
+    Ok(Instruction {
+        program_id: KAMINO_LENDING_ID,
+        accounts: accounts::SocializeLoss {
+            risk_council: *risk_council,
+            obligation: *obligation,
+            lending_market: reserve.lending_market,
+            reserve: reserve.address,
+            instruction_sysvar_account: sysvar::instructions::ID,
+        }
+        .to_account_metas(Some(true)),
+        data: instruction::SocializeLoss { _liquidity_amount: liquidity_amount }.data(),
+    })
+}
+
+pub fn request_elevation_group(
+    owner: &Pubkey,
+    obligation: &Pubkey,
+    lending_market: &Pubkey,
+    elevation_group: u8,
+) -> Result<Instruction, KaminoClientError> {
+    Ok(Instruction {
+        program_id: KAMINO_LENDING_ID,
+        accounts: accounts::RequestElevationGroup {
+            owner: *owner,
+            obligation: *obligation,
+            lending_market: *lending_market,
+        }
+        .to_account_metas(Some(true)),
+        data: instruction::RequestElevationGroup { _elevation_group: elevation_group }.data(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_borrow_without_referrer_uses_placeholder() {
+        let owner = Pubkey::new_unique();
+        let obligation = Pubkey::new_unique();
+        let reserve = ReserveAccounts::new(
+            &Pubkey::new_unique(),
+            &Pubkey::new_unique(),
+            &Pubkey::new_unique(),
+            &spl_token::ID,
+        );
+        let ix = borrow_obligation_liquidity(&owner, &obligation, &reserve, None, 100).unwrap();
+        assert_eq!(ix.accounts.len(), 11);
+        assert_eq!(ix.accounts[8].pubkey, KAMINO_LENDING_ID);
+        assert_eq!(ix.accounts[9].pubkey, spl_token::ID);
+        assert_eq!(ix.accounts[5].pubkey, reserve.liquidity_supply);
+        assert_eq!(ix.accounts[7].pubkey, reserve.user_liquidity_ata(&owner));
+    }
+}
@@ -0,0 +1,95 @@
+use anchor_lang::{
+    prelude::Pubkey,
+    solana_program::{instruction::Instruction, system_program::ID as SYSTEM_ID, sysvar},
+    InstructionData, ToAccountMetas,
+};
+use kamino_lend::{accounts, instruction, ID as KAMINO_LENDING_ID};
+use spl_associated_token_account::get_associated_token_address_with_program_id;
+
+use super::ReserveAccounts;
+use crate::{error::KaminoClientError, pda};
+
+/// Creates the account collecting `referrer`'s share of fees in a reserve.
+pub fn init_referrer_token_state(
+    payer: &Pubkey,
+    referrer: &Pubkey,
+    reserve: &ReserveAccounts,
+) -> Result<Instruction, KaminoClientError> {
+    Ok(Instruction {
+        program_id: KAMINO_LENDING_ID,
+        accounts: accounts::InitReferrerTokenState {
+            payer: *payer,
+            lending_market: reserve.lending_market,
+            reserve: reserve.address,
+            referrer_token_state: pda::get_referrer_token_state(referrer, &reserve.address),
+            rent: sysvar::rent::ID,
+            system_program: SYSTEM_ID,
+        }
+        .to_account_metas(Some(true)),
+        data: instruction::InitReferrerTokenState { _referrer: *referrer }.data(),
+    })
+}
+
+/// Withdraws the referrer's unclaimed fees to the referrer's liquidity ATA.
+pub fn withdraw_referrer_fees(
+    referrer: &Pubkey,
+    reserve: &ReserveAccounts,
+) -> Result<Instruction, KaminoClientError> {
+    Ok(Instruction {
+        program_id: KAMINO_LENDING_ID,
+        accounts: accounts::WithdrawReferrerFees {
+            referrer: *referrer,
+            referrer_token_state: pda::get_referrer_token_state(referrer, &reserve.address),
+            reserve: reserve.address,
+            reserve_supply_liquidity: reserve.liquidity_supply,
+            referrer_token_account: get_associated_token_address_with_program_id(
+                referrer,
+                &reserve.liquidity_mint,
+                &reserve.token_program,
+            ),
+            lending_market: reserve.lending_market,
+            lending_market_authority: reserve.lending_market_authority(),
+            token_program: reserve.token_program,
+        }
+        .to_account_metas(Some(true)),
+        data: instruction::WithdrawReferrerFees {}.data(),
+    })
+}
+
+pub fn init_referrer_state_and_short_url(
+    referrer: &Pubkey,
+    short_url: &str,
+) -> Result<Instruction, KaminoClientError> {
+    Ok(Instruction {
+        program_id: KAMINO_LENDING_ID,
+        accounts: accounts::InitReferrerStateAndShortUrl {
+            referrer: *referrer,
+            referrer_state: pda::get_referrer_state(referrer),
+            referrer_short_url: pda::get_referrer_short_url(short_url),
+            referrer_user_metadata: pda::get_user_metadata(referrer),
+            rent: sysvar::rent::ID,
+            system_program: SYSTEM_ID,
+        }
+        .to_account_metas(Some(true)),
+        data: instruction::InitReferrerStateAndShortUrl { _short_url: short_url.to_string() }
+            .data(),
+    })
+}
+
+pub fn delete_referrer_state_and_short_url(
+    referrer: &Pubkey,
+    short_url: &str,
+) -> Result<Instruction, KaminoClientError> {
+    Ok(Instruction {
+        program_id: KAMINO_LENDING_ID,
+        accounts: accounts::DeleteReferrerStateAndShortUrl {
+            referrer: *referrer,
+            referrer_state: pda::get_referrer_state(referrer),
+            short_url: pda::get_referrer_short_url(short_url),
+            rent: sysvar::rent::ID,
+            system_program: SYSTEM_ID,
+        }
+        .to_account_metas(Some(true)),
+        data: instruction::DeleteReferrerStateAndShortUrl {}.data(),
+    })
+}
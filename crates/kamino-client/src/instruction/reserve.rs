@@ -0,0 +1,243 @@
+use anchor_lang::{
+    prelude::Pubkey,
+    solana_program::{instruction::Instruction, system_program::ID as SYSTEM_ID, sysvar},
+    InstructionData, ToAccountMetas,
+};
+use kamino_lend::{
+    accounts, instruction, state::Reserve, typedefs::ReserveConfig, ID as KAMINO_LENDING_ID,
+};
+use spl_associated_token_account::get_associated_token_address_with_program_id;
+
+use super::{optional_account, ReserveAccounts};
+use crate::{
+    error::KaminoClientError,
+    oracle::ReserveOracles,
+    pda,
+    reserve_config::{
+        plan_reserve_config_update, ReserveConfigUpdate, ENTIRE_VALUE_LEN, SINGLE_VALUE_LEN,
+    },
+};
+
+pub fn init_reserve(
+    lending_market: &Pubkey,
+    reserve: &Pubkey,
+    reserve_liquidity_mint: &Pubkey,
+    lending_market_owner: &Pubkey,
+    token_program: &Pubkey,
+) -> Result<Instruction, KaminoClientError> {
+    let reserve_accounts =
+        ReserveAccounts::new(lending_market, reserve, reserve_liquidity_mint, token_program);
+    Ok(Instruction {
+        program_id: KAMINO_LENDING_ID,
+        accounts: accounts::InitReserve {
+            reserve: *reserve,
+            reserve_liquidity_supply: reserve_accounts.liquidity_supply,
+            reserve_collateral_mint: reserve_accounts.collateral_mint,
+            reserve_collateral_supply: reserve_accounts.collateral_supply,
+            fee_receiver: reserve_accounts.fee_vault,
+            reserve_liquidity_mint: *reserve_liquidity_mint,
+            lending_market_owner: *lending_market_owner,
+            lending_market: *lending_market,
+            lending_market_authority: pda::get_market_authority(lending_market),
+            system_program: SYSTEM_ID,
+            rent: sysvar::rent::ID,
+            token_program: *token_program,
+        }
+        .to_account_metas(Some(true)),
+        data: instruction::InitReserve {}.data(),
+    })
+}
+
+/// Updates one field of the reserve config. Fails if the value does not fit
+/// the instruction, like [`ReserveConfigUpdate::BorrowRateCurve`].
+pub fn update_single_reserve_config(
+    lending_market_owner: &Pubkey,
+    lending_market: &Pubkey,
+    reserve: &Pubkey,
+    update: &ReserveConfigUpdate,
+) -> Result<Instruction, KaminoClientError> {
+    let (mode, value) = update.encode::<SINGLE_VALUE_LEN>()?;
+    Ok(Instruction {
+        program_id: KAMINO_LENDING_ID,
+        accounts: accounts::UpdateSingleReserveConfig {
+            lending_market_owner: *lending_market_owner,
+            lending_market: *lending_market,
+            reserve: *reserve,
+        }
+        .to_account_metas(Some(true)),
+        data: instruction::UpdateSingleReserveConfig { _mode: mode, _value: value }.data(),
+    })
+}
+
+pub fn update_entire_reserve_config(
+    reserve: &Pubkey,
+    lending_market_owner: &Pubkey,
+    lending_market: &Pubkey,
+    reserve_config: ReserveConfig,
+) -> Result<Instruction, KaminoClientError> {
+    let update = ReserveConfigUpdate::EntireConfig(Box::new(reserve_config));
+    let (mode, value) = update.encode::<ENTIRE_VALUE_LEN>()?;
+    Ok(Instruction {
+        program_id: KAMINO_LENDING_ID,
+        accounts: accounts::UpdateEntireReserveConfig {
+            reserve: *reserve,
+            lending_market_owner: *lending_market_owner,
+            lending_market: *lending_market,
+        }
+        .to_account_metas(Some(true)),
+        data: instruction::UpdateEntireReserveConfig { _mode: mode, _value: value }.data(),
+    })
+}
+
+/// The instructions of [`plan_reserve_config_update`], turning the config of
+/// `reserve` from `current` into `desired`.
+pub fn update_reserve_config_to(
+    lending_market_owner: &Pubkey,
+    lending_market: &Pubkey,
+    reserve: &Pubkey,
+    current: &ReserveConfig,
+    desired: &ReserveConfig,
+) -> Result<Vec<Instruction>, KaminoClientError> {
+    plan_reserve_config_update(current, desired)?
+        .iter()
+        .map(|update| match update {
+            ReserveConfigUpdate::EntireConfig(config) => update_entire_reserve_config(
+                reserve,
+                lending_market_owner,
+                lending_market,
+                **config,
+            ),
+            update => {
+                update_single_reserve_config(lending_market_owner, lending_market, reserve, update)
+            }
+        })
+        .collect()
+}
+
+/// Refreshes a reserve, passing the oracles configured in its
+/// `config.token_info`.
+pub fn refresh_reserve(
+    reserve: &Pubkey,
+    state: &Reserve,
+) -> Result<Instruction, KaminoClientError> {
+    refresh_reserve_with_oracles(
+        reserve,
+        &state.lending_market,
+        &ReserveOracles::from_token_info(&state.config.token_info),
+    )
+}
+
+pub fn refresh_reserve_with_oracles(
+    reserve: &Pubkey,
+    lending_market: &Pubkey,
+    oracles: &ReserveOracles,
+) -> Result<Instruction, KaminoClientError> {
+    Ok(Instruction {
+        program_id: KAMINO_LENDING_ID,
+        accounts: accounts::RefreshReserve {
+            reserve: *reserve,
+            lending_market: *lending_market,
+            pyth_oracle: optional_account(oracles.pyth.as_ref()),
+            switchboard_price_oracle: optional_account(oracles.switchboard_price.as_ref()),
+            switchboard_twap_oracle: optional_account(oracles.switchboard_twap.as_ref()),
+            scope_prices: optional_account(oracles.scope_prices.as_ref()),
+        }
+        .to_account_metas(Some(true)),
+        data: instruction::RefreshReserve {}.data(),
+    })
+}
+
+/// Deposits liquidity from `owner`'s ATA and mints cTokens to `owner`'s
+/// collateral ATA, without touching any obligation.
+pub fn deposit_reserve_liquidity(
+    owner: &Pubkey,
+    reserve: &ReserveAccounts,
+    liquidity_amount: u64,
+) -> Result<Instruction, KaminoClientError> {
+    Ok(Instruction {
+        program_id: KAMINO_LENDING_ID,
+        accounts: accounts::DepositReserveLiquidity {
+            owner: *owner,
+            reserve: reserve.address,
+            lending_market: reserve.lending_market,
+            lending_market_authority: reserve.lending_market_authority(),
+            reserve_liquidity_supply: reserve.liquidity_supply,
+            reserve_collateral_mint: reserve.collateral_mint,
+            user_source_liquidity: reserve.user_liquidity_ata(owner),
+            user_destination_collateral: reserve.user_collateral_ata(owner),
+            token_program: reserve.token_program,
+            instruction_sysvar_account: sysvar::instructions::ID,
+        }
+        .to_account_metas(Some(true)),
+        data: instruction::DepositReserveLiquidity { _liquidity_amount: liquidity_amount }.data(),
+    })
+}
+
+/// Burns cTokens from `owner`'s collateral ATA and returns the underlying
+/// liquidity to `owner`'s ATA.
+pub fn redeem_reserve_collateral(
+    owner: &Pubkey,
+    reserve: &ReserveAccounts,
+    collateral_amount: u64,
+) -> Result<Instruction, KaminoClientError> {
+    Ok(Instruction {
+        program_id: KAMINO_LENDING_ID,
+        accounts: accounts::RedeemReserveCollateral {
+            owner: *owner,
+            lending_market: reserve.lending_market,
+            reserve: reserve.address,
+            lending_market_authority: reserve.lending_market_authority(),
+            reserve_collateral_mint: reserve.collateral_mint,
+            reserve_liquidity_supply: reserve.liquidity_supply,
+            user_source_collateral: reserve.user_collateral_ata(owner),
+            user_destination_liquidity: reserve.user_liquidity_ata(owner),
+            token_program: reserve.token_program,
+            instruction_sysvar_account: sysvar::instructions::ID,
+        }
+        .to_account_metas(Some(true)),
+        data: instruction::RedeemReserveCollateral { _collateral_amount: collateral_amount }.data(),
+    })
+}
+
+/// Moves accumulated protocol fees from the liquidity supply to the fee vault.
+pub fn redeem_fees(reserve: &ReserveAccounts) -> Result<Instruction, KaminoClientError> {
+    Ok(Instruction {
+        program_id: KAMINO_LENDING_ID,
+        accounts: accounts::RedeemFees {
+            reserve: reserve.address,
+            reserve_liquidity_fee_receiver: reserve.fee_vault,
+            reserve_supply_liquidity: reserve.liquidity_supply,
+            lending_market: reserve.lending_market,
+            lending_market_authority: reserve.lending_market_authority(),
+            token_program: reserve.token_program,
+        }
+        .to_account_metas(Some(true)),
+        data: instruction::RedeemFees {}.data(),
+    })
+}
+
+/// Withdraws `amount` from the fee vault to the market owner's ATA.
+pub fn withdraw_protocol_fee(
+    lending_market_owner: &Pubkey,
+    reserve: &ReserveAccounts,
+    amount: u64,
+) -> Result<Instruction, KaminoClientError> {
+    Ok(Instruction {
+        program_id: KAMINO_LENDING_ID,
+        accounts: accounts::WithdrawProtocolFee {
+            lending_market_owner: *lending_market_owner,
+            lending_market: reserve.lending_market,
+            reserve: reserve.address,
+            lending_market_authority: reserve.lending_market_authority(),
+            fee_vault: reserve.fee_vault,
+            lending_market_owner_ata: get_associated_token_address_with_program_id(
+                lending_market_owner,
+                &reserve.liquidity_mint,
+                &reserve.token_program,
+            ),
+            token_program: reserve.token_program,
+        }
+        .to_account_metas(Some(true)),
+        data: instruction::WithdrawProtocolFee { _amount: amount }.data(),
+    })
+}
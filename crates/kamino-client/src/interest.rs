@@ -0,0 +1,250 @@
+//! Offline interest accrual, mirroring klend's `refreshReserve`, and rate
+//! quotes derived from a reserve's borrow rate curve.
+
+use kamino_lend::{
+    state::Reserve,
+    typedefs::{BorrowRateCurve, CurvePoint},
+    BigFraction, Fraction, FractionExtra,
+};
+
+use crate::{error::KaminoClientError, health::total_liquidity};
+
+/// klend assumes 500ms slots, i.e. 2 per second.
+pub const SLOTS_PER_YEAR: u64 = 2 * 31_536_000;
+
+/// Annualized rates of a reserve at its current utilization.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ReserveRates {
+    pub utilization: Fraction,
+    pub borrow_apr: Fraction,
+    pub borrow_apy: Fraction,
+    /// Borrow interest paid to depositors, net of the protocol take rate.
+    pub supply_apr: Fraction,
+    pub supply_apy: Fraction,
+}
+
+pub fn reserve_rates(reserve: &Reserve) -> Result<ReserveRates, KaminoClientError> {
+    let utilization = utilization_rate(reserve);
+    let borrow_apr = borrow_rate(&reserve.config.borrow_rate_curve, utilization)?;
+    let depositors_share =
+        Fraction::ONE - Fraction::from_percent(reserve.config.protocol_take_rate_pct);
+    let supply_apr = borrow_apr * utilization * depositors_share;
+    Ok(ReserveRates {
+        utilization,
+        borrow_apr,
+        borrow_apy: apr_to_apy(borrow_apr)?,
+        supply_apr,
+        supply_apy: apr_to_apy(supply_apr)?,
+    })
+}
+
+/// Borrowed share of the reserve's total liquidity; 0 for an empty reserve.
+pub fn utilization_rate(reserve: &Reserve) -> Fraction {
+    let total_liquidity = total_liquidity(reserve);
+    if total_liquidity == Fraction::ZERO {
+        Fraction::ZERO
+    } else {
+        reserve.borrowed_amount() / total_liquidity
+    }
+}
+
+/// Borrow APR at `utilization`, interpolated linearly between the two curve
+/// points around it. Utilization above 100% is priced as 100%.
+pub fn borrow_rate(
+    curve: &BorrowRateCurve,
+    utilization: Fraction,
+) -> Result<Fraction, KaminoClientError> {
+    let utilization = utilization.min(Fraction::ONE);
+    let utilization_bps: u32 = (utilization * 10_000).to_num();
+    let (start, end) = curve
+        .points
+        .windows(2)
+        .map(|segment| (&segment[0], &segment[1]))
+        .find(|(start, end)| {
+            start.utilization_rate_bps <= utilization_bps
+                && utilization_bps <= end.utilization_rate_bps
+        })
+        .ok_or_else(|| {
+            KaminoClientError::InvalidInput(format!(
+                "borrow rate curve does not cover {utilization_bps} bps utilization"
+            ))
+        })?;
+
+    if utilization_bps == start.utilization_rate_bps {
+        return Ok(Fraction::from_bps(start.borrow_rate_bps));
+    }
+    if utilization_bps == end.utilization_rate_bps {
+        return Ok(Fraction::from_bps(end.borrow_rate_bps));
+    }
+    let rate_delta_bps = end
+        .borrow_rate_bps
+        .checked_sub(start.borrow_rate_bps)
+        .ok_or(KaminoClientError::Math("borrow rate curve is decreasing"))?;
+    let utilization_delta_bps = end.utilization_rate_bps - start.utilization_rate_bps;
+    let progress_bps = utilization * 10_000 - Fraction::from_num(start.utilization_rate_bps);
+    let rate_bps = Fraction::from_num(start.borrow_rate_bps)
+        + Fraction::from_num(rate_delta_bps) * progress_bps / u128::from(utilization_delta_bps);
+    Ok(rate_bps / 10_000)
+}
+
+/// Growth of one unit of debt over `slots` at `borrow_rate`, using klend's
+/// three-term series rather than exact compounding so projections track
+/// on-chain state. Meant for the few slots between refreshes; precision
+/// degrades over long gaps.
+pub fn compounded_interest(borrow_rate: Fraction, slots: u64) -> Fraction {
+    let base = borrow_rate / u128::from(SLOTS_PER_YEAR);
+    match slots {
+        0 => return Fraction::ONE,
+        1..=4 => return (0..slots).fold(Fraction::ONE, |acc, _| acc * (Fraction::ONE + base)),
+        _ => {}
+    }
+    let exp = u128::from(slots);
+    let base_power_two = base * base;
+    let base_power_three = base_power_two * base;
+    let first_term = base * exp;
+    let second_term = base_power_two * exp * (exp - 1) / 2;
+    let third_term = base_power_three * exp * (exp - 1) * (exp - 2) / 6;
+    Fraction::ONE + first_term + second_term + third_term
+}
+
+/// Compounds `apr` once per slot over a year.
+pub fn apr_to_apy(apr: Fraction) -> Result<Fraction, KaminoClientError> {
+    let growth = checked_pow(Fraction::ONE + apr / u128::from(SLOTS_PER_YEAR), SLOTS_PER_YEAR)
+        .ok_or(KaminoClientError::Math("APY overflows a fraction"))?;
+    Ok(growth - Fraction::ONE)
+}
+
+/// Accrues interest on `reserve` up to `current_slot` the way
+/// `refreshReserve` does, updating the debt, cumulative borrow rate and fees.
+pub fn accrue_interest(reserve: &mut Reserve, current_slot: u64) -> Result<(), KaminoClientError> {
+    let slots_elapsed = current_slot.checked_sub(reserve.last_update.slot).ok_or_else(|| {
+        KaminoClientError::InvalidInput(
+            "current slot is before the reserve's last update".to_string(),
+        )
+    })?;
+    if slots_elapsed == 0 {
+        return Ok(());
+    }
+    let borrow_apr = borrow_rate(&reserve.config.borrow_rate_curve, utilization_rate(reserve))?;
+    let compounded = compounded_interest(borrow_apr, slots_elapsed);
+
+    let cumulative_borrow_rate = reserve.cumulative_borrow_rate() * BigFraction::from(compounded);
+    let previous_debt = reserve.borrowed_amount();
+    let new_debt = previous_debt * compounded;
+    let net_new_debt = new_debt - previous_debt;
+    let protocol_fees =
+        net_new_debt * Fraction::from_percent(reserve.config.protocol_take_rate_pct);
+    let referrer_fees = net_new_debt * reserve.absolute_referral_rate();
+
+    let liquidity = &mut reserve.liquidity;
+    liquidity.cumulative_borrow_rate_bsf = cumulative_borrow_rate.into();
+    liquidity.borrowed_amount_sf = new_debt.to_bits();
+    liquidity.accumulated_protocol_fees_sf =
+        (Fraction::from_bits(liquidity.accumulated_protocol_fees_sf) + protocol_fees
+            - referrer_fees)
+            .to_bits();
+    liquidity.pending_referrer_fees_sf =
+        (Fraction::from_bits(liquidity.pending_referrer_fees_sf) + referrer_fees).to_bits();
+    reserve.last_update.slot = current_slot;
+    Ok(())
+}
+
+/// `reserve` as it would look after a refresh at `slot`, assuming its
+/// balances do not change in between.
+pub fn project_reserve(reserve: &Reserve, slot: u64) -> Result<Reserve, KaminoClientError> {
+    let mut projected = *reserve;
+    accrue_interest(&mut projected, slot)?;
+    Ok(projected)
+}
+
+/// Curve with `points`, padded with the last point as klend requires.
+pub fn borrow_rate_curve(points: &[CurvePoint]) -> BorrowRateCurve {
+    let mut curve = BorrowRateCurve::default();
+    let last = points.last().copied().unwrap_or_default();
+    for (i, point) in curve.points.iter_mut().enumerate() {
+        *point = points.get(i).copied().unwrap_or(last);
+    }
+    curve
+}
+
+fn checked_pow(base: Fraction, mut exp: u64) -> Option<Fraction> {
+    let mut result = Fraction::ONE;
+    let mut base = base;
+    while exp > 0 {
+        if exp & 1 == 1 {
+            result = result.checked_mul(base)?;
+        }
+        exp >>= 1;
+        if exp > 0 {
+            base = base.checked_mul(base)?;
+        }
+    }
+    Some(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::zeroed;
+
+    fn point(utilization_rate_bps: u32, borrow_rate_bps: u32) -> CurvePoint {
+        CurvePoint { utilization_rate_bps, borrow_rate_bps }
+    }
+
+    fn reserve(available_amount: u64, borrowed_amount: u64) -> Reserve {
+        let mut reserve: Reserve = zeroed();
+        reserve.liquidity.available_amount = available_amount;
+        reserve.liquidity.borrowed_amount_sf = Fraction::from_num(borrowed_amount).to_bits();
+        reserve.liquidity.cumulative_borrow_rate_bsf = BigFraction::ONE.into();
+        reserve.config.borrow_rate_curve =
+            borrow_rate_curve(&[point(0, 0), point(8_000, 1_000), point(10_000, 5_000)]);
+        reserve.config.protocol_take_rate_pct = 20;
+        reserve
+    }
+
+    #[test]
+    fn test_borrow_rate_interpolates() {
+        let curve = reserve(0, 0).config.borrow_rate_curve;
+        let rate = |pct: u8| {
+            borrow_rate(&curve, Fraction::from_percent(pct)).unwrap().to_decimal().to_string()
+        };
+        assert_eq!(rate(0), "0");
+        assert_eq!(rate(40), "0.05");
+        assert_eq!(rate(80), "0.1");
+        assert_eq!(rate(90), "0.3");
+        assert_eq!(rate(150), "0.5");
+        assert!(borrow_rate(&BorrowRateCurve::default(), Fraction::ONE).is_err());
+    }
+
+    #[test]
+    fn test_reserve_rates() {
+        let rates = reserve_rates(&reserve(500, 500)).unwrap();
+        assert_eq!(rates.utilization, Fraction::from_percent(50u8));
+        assert_eq!(rates.borrow_apr.to_decimal().to_string(), "0.0625");
+        assert_eq!(rates.supply_apr.to_decimal().to_string(), "0.025");
+        // e^0.0625 - 1
+        assert!((rates.borrow_apy.to_num::<f64>() - 0.064494).abs() < 1e-6);
+        assert!(rates.supply_apy > rates.supply_apr);
+    }
+
+    #[test]
+    fn test_accrue_interest() {
+        let mut reserve = reserve(0, 1_000_000);
+        reserve.liquidity.absolute_referral_rate_sf = Fraction::from_percent(5u8).to_bits();
+        accrue_interest(&mut reserve, 1_000).unwrap();
+
+        // Fully utilized at 50% APR.
+        let expected = 1_000_000.0 * (1.0 + 0.5 / SLOTS_PER_YEAR as f64).powi(1_000);
+        assert!((reserve.borrowed_amount().to_num::<f64>() - expected).abs() < 1e-6);
+        let compounded = compounded_interest(Fraction::from_percent(50u8), 1_000);
+        assert_eq!(reserve.cumulative_borrow_rate(), BigFraction::from(compounded));
+        let interest = reserve.borrowed_amount() - Fraction::from_num(1_000_000);
+        assert_eq!(reserve.pending_referrer_fees(), interest * Fraction::from_percent(5u8));
+        assert_eq!(
+            reserve.accumulated_protocol_fees(),
+            interest * Fraction::from_percent(20u8) - reserve.pending_referrer_fees()
+        );
+        assert_eq!(reserve.last_update.slot, 1_000);
+        assert!(accrue_interest(&mut reserve, 0).is_err());
+    }
+}
@@ -0,0 +1,244 @@
+//! Typed `updateLendingMarket` values and the diff between two markets.
+//!
+//! klend takes every update as a mode and a 72-byte value, which it reads as
+//! the raw little-endian encoding of the mode's type, zero-padded.
+
+use anchor_lang::{prelude::Pubkey, AnchorSerialize};
+use kamino_lend::{
+    state::LendingMarket,
+    typedefs::{ElevationGroup, UpdateLendingMarketMode},
+};
+
+use crate::error::KaminoClientError;
+
+pub const VALUE_LEN: usize = 72;
+
+/// One `updateLendingMarket` mode with its value.
+#[derive(Clone, Copy, Debug)]
+pub enum LendingMarketUpdate {
+    /// Proposes a new owner, who accepts with `updateLendingMarketOwner`.
+    Owner(Pubkey),
+    EmergencyMode(bool),
+    LiquidationCloseFactorPct(u8),
+    /// Max debt market value liquidated in one instruction.
+    LiquidationMaxValue(u64),
+    GlobalUnhealthyBorrowValue(u64),
+    GlobalAllowedBorrowValue(u64),
+    RiskCouncil(Pubkey),
+    MinFullLiquidationValueThreshold(u64),
+    InsolvencyRiskLtvPct(u8),
+    /// Sets the group with the same id.
+    ElevationGroup(ElevationGroup),
+    ReferralFeeBps(u16),
+    MultiplierPoints([u8; 8]),
+    PriceRefreshTriggerToMaxAgePct(u8),
+    AutodeleverageEnabled(bool),
+    BorrowingDisabled(bool),
+}
+
+impl LendingMarketUpdate {
+    pub fn mode(&self) -> UpdateLendingMarketMode {
+        match self {
+            Self::Owner(_) => UpdateLendingMarketMode::UpdateOwner,
+            Self::EmergencyMode(_) => UpdateLendingMarketMode::UpdateEmergencyMode,
+            Self::LiquidationCloseFactorPct(_) => {
+                UpdateLendingMarketMode::UpdateLiquidationCloseFactor
+            }
+            Self::LiquidationMaxValue(_) => UpdateLendingMarketMode::UpdateLiquidationMaxValue,
+            Self::GlobalUnhealthyBorrowValue(_) => {
+                UpdateLendingMarketMode::UpdateGlobalUnhealthyBorrow
+            }
+            Self::GlobalAllowedBorrowValue(_) => UpdateLendingMarketMode::UpdateGlobalAllowedBorrow,
+            Self::RiskCouncil(_) => UpdateLendingMarketMode::UpdateRiskCouncil,
+            Self::MinFullLiquidationValueThreshold(_) => {
+                UpdateLendingMarketMode::UpdateMinFullLiquidationThreshold
+            }
+            Self::InsolvencyRiskLtvPct(_) => UpdateLendingMarketMode::UpdateInsolvencyRiskLtv,
+            Self::ElevationGroup(_) => UpdateLendingMarketMode::UpdateElevationGroup,
+            Self::ReferralFeeBps(_) => UpdateLendingMarketMode::UpdateReferralFeeBps,
+            Self::MultiplierPoints(_) => UpdateLendingMarketMode::UpdateMultiplierPoints,
+            Self::PriceRefreshTriggerToMaxAgePct(_) => {
+                UpdateLendingMarketMode::UpdatePriceRefreshTriggerToMaxAgePct
+            }
+            Self::AutodeleverageEnabled(_) => UpdateLendingMarketMode::UpdateAutodeleverageEnabled,
+            Self::BorrowingDisabled(_) => UpdateLendingMarketMode::UpdateBorrowingDisabled,
+        }
+    }
+
+    /// The mode and value arguments of `updateLendingMarket`.
+    pub fn encode(&self) -> Result<(u64, [u8; VALUE_LEN]), KaminoClientError> {
+        // anchor-gen drops the fields of `UpdateLendingMarketConfigValue`, so
+        // the value is encoded here rather than through it.
+        let bytes = match *self {
+            Self::Owner(pubkey) | Self::RiskCouncil(pubkey) => pubkey.to_bytes().to_vec(),
+            Self::EmergencyMode(value)
+            | Self::AutodeleverageEnabled(value)
+            | Self::BorrowingDisabled(value) => vec![u8::from(value)],
+            Self::LiquidationCloseFactorPct(value)
+            | Self::InsolvencyRiskLtvPct(value)
+            | Self::PriceRefreshTriggerToMaxAgePct(value) => vec![value],
+            Self::LiquidationMaxValue(value)
+            | Self::GlobalUnhealthyBorrowValue(value)
+            | Self::GlobalAllowedBorrowValue(value)
+            | Self::MinFullLiquidationValueThreshold(value) => value.to_le_bytes().to_vec(),
+            Self::ElevationGroup(group) => group.try_to_vec()?,
+            Self::ReferralFeeBps(value) => value.to_le_bytes().to_vec(),
+            Self::MultiplierPoints(points) => points.to_vec(),
+        };
+        let mut value = [0; VALUE_LEN];
+        value
+            .get_mut(..bytes.len())
+            .ok_or_else(|| {
+                KaminoClientError::InvalidInput(format!(
+                    "{:?} does not fit in {VALUE_LEN} bytes",
+                    self.mode()
+                ))
+            })?
+            .copy_from_slice(&bytes);
+        Ok((self.mode() as u64, value))
+    }
+}
+
+/// Updates that turn `current` into `desired`, the owner last since it
+/// needs the current owner's signature for everything else.
+///
+/// Elevation groups are matched by id and groups missing from `desired` are
+/// left as they are. The quote currency cannot be updated and is ignored.
+pub fn diff_lending_market(
+    current: &LendingMarket,
+    desired: &LendingMarket,
+) -> Result<Vec<LendingMarketUpdate>, KaminoClientError> {
+    use LendingMarketUpdate as Update;
+    let mut updates = vec![];
+    let mut push_if_changed = |changed: bool, update| {
+        if changed {
+            updates.push(update);
+        }
+    };
+    push_if_changed(
+        current.emergency_mode != desired.emergency_mode,
+        Update::EmergencyMode(desired.emergency_mode != 0),
+    );
+    push_if_changed(
+        current.liquidation_max_debt_close_factor_pct
+            != desired.liquidation_max_debt_close_factor_pct,
+        Update::LiquidationCloseFactorPct(desired.liquidation_max_debt_close_factor_pct),
+    );
+    push_if_changed(
+        current.max_liquidatable_debt_market_value_at_once
+            != desired.max_liquidatable_debt_market_value_at_once,
+        Update::LiquidationMaxValue(desired.max_liquidatable_debt_market_value_at_once),
+    );
+    push_if_changed(
+        current.global_unhealthy_borrow_value != desired.global_unhealthy_borrow_value,
+        Update::GlobalUnhealthyBorrowValue(desired.global_unhealthy_borrow_value),
+    );
+    push_if_changed(
+        current.global_allowed_borrow_value != desired.global_allowed_borrow_value,
+        Update::GlobalAllowedBorrowValue(desired.global_allowed_borrow_value),
+    );
+    push_if_changed(
+        current.risk_council != desired.risk_council,
+        Update::RiskCouncil(desired.risk_council),
+    );
+    push_if_changed(
+        current.min_full_liquidation_value_threshold
+            != desired.min_full_liquidation_value_threshold,
+        Update::MinFullLiquidationValueThreshold(desired.min_full_liquidation_value_threshold),
+    );
+    push_if_changed(
+        current.insolvency_risk_unhealthy_ltv_pct != desired.insolvency_risk_unhealthy_ltv_pct,
+        Update::InsolvencyRiskLtvPct(desired.insolvency_risk_unhealthy_ltv_pct),
+    );
+    push_if_changed(
+        current.referral_fee_bps != desired.referral_fee_bps,
+        Update::ReferralFeeBps(desired.referral_fee_bps),
+    );
+    push_if_changed(
+        current.multiplier_points_tag_boost != desired.multiplier_points_tag_boost,
+        Update::MultiplierPoints(desired.multiplier_points_tag_boost),
+    );
+    push_if_changed(
+        current.price_refresh_trigger_to_max_age_pct
+            != desired.price_refresh_trigger_to_max_age_pct,
+        Update::PriceRefreshTriggerToMaxAgePct(desired.price_refresh_trigger_to_max_age_pct),
+    );
+    push_if_changed(
+        current.autodeleverage_enabled != desired.autodeleverage_enabled,
+        Update::AutodeleverageEnabled(desired.autodeleverage_enabled != 0),
+    );
+    push_if_changed(
+        current.borrow_disabled != desired.borrow_disabled,
+        Update::BorrowingDisabled(desired.borrow_disabled != 0),
+    );
+    for group in desired.elevation_groups.iter().filter(|group| group.id != 0) {
+        let existing = current.elevation_groups.iter().find(|existing| existing.id == group.id);
+        if existing.map(|existing| existing.try_to_vec()).transpose()? != Some(group.try_to_vec()?)
+        {
+            updates.push(Update::ElevationGroup(*group));
+        }
+    }
+    let owner_changed = desired.lending_market_owner != current.lending_market_owner
+        && desired.lending_market_owner != current.lending_market_owner_cached;
+    if owner_changed {
+        updates.push(Update::Owner(desired.lending_market_owner));
+    }
+    Ok(updates)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::zeroed;
+
+    #[test]
+    fn test_encode() {
+        let owner = Pubkey::new_unique();
+        let (mode, value) = LendingMarketUpdate::Owner(owner).encode().unwrap();
+        assert_eq!(mode, 0);
+        assert_eq!(value[..32], owner.to_bytes());
+        assert_eq!(value[32..], [0; 40]);
+
+        let (mode, value) = LendingMarketUpdate::ReferralFeeBps(0x0102).encode().unwrap();
+        assert_eq!((mode, value[..3].to_vec()), (10, vec![2, 1, 0]));
+        let (mode, value) = LendingMarketUpdate::BorrowingDisabled(true).encode().unwrap();
+        assert_eq!((mode, value[0]), (14, 1));
+
+        let group = ElevationGroup {
+            id: 3,
+            ltv_pct: 90,
+            max_liquidation_bonus_bps: 100,
+            ..Default::default()
+        };
+        let (mode, value) = LendingMarketUpdate::ElevationGroup(group).encode().unwrap();
+        assert_eq!(mode, UpdateLendingMarketMode::UpdateElevationGroup as u64);
+        assert_eq!(value[..4], [100, 0, 3, 90]);
+    }
+
+    #[test]
+    fn test_diff_lending_market() {
+        let current: LendingMarket = zeroed();
+        assert!(diff_lending_market(&current, &current).unwrap().is_empty());
+
+        let mut desired = current;
+        desired.lending_market_owner = Pubkey::new_unique();
+        desired.global_allowed_borrow_value = 1_000;
+        desired.elevation_groups[0] = ElevationGroup { id: 1, ltv_pct: 80, ..Default::default() };
+        let updates = diff_lending_market(&current, &desired).unwrap();
+        let modes: Vec<_> = updates.iter().map(|update| update.mode() as u64).collect();
+        assert_eq!(
+            modes,
+            [
+                UpdateLendingMarketMode::UpdateGlobalAllowedBorrow as u64,
+                UpdateLendingMarketMode::UpdateElevationGroup as u64,
+                UpdateLendingMarketMode::UpdateOwner as u64,
+            ]
+        );
+
+        let mut proposed = current;
+        proposed.lending_market_owner_cached = desired.lending_market_owner;
+        desired.global_allowed_borrow_value = 0;
+        desired.elevation_groups[0] = ElevationGroup::default();
+        assert!(diff_lending_market(&proposed, &desired).unwrap().is_empty());
+    }
+}
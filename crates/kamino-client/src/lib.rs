@@ -0,0 +1,19 @@
+pub mod actions;
+pub mod collateral;
+pub mod error;
+pub mod flash_loan;
+pub mod health;
+pub mod instruction;
+pub mod interest;
+pub mod lending_market;
+pub mod liquidation;
+pub mod market_spec;
+pub mod obligation;
+pub mod oracle;
+pub mod pda;
+pub mod refresh;
+pub mod reserve_config;
+pub mod rpc;
+#[cfg(test)]
+mod test_util;
+pub mod token;
@@ -0,0 +1,402 @@
+//! Liquidations with `liquidateObligationAndRedeemReserveCollateral`, quoted
+//! offline from the obligation's health the way klend settles them.
+//!
+//! Quotes read prices and exchange rates as of the reserves' last refresh.
+//! The refreshes sent with the liquidation may move them; the slippage the
+//! plan allows bounds by how much, through klend's
+//! `min_acceptable_received_collateral_amount`.
+
+use anchor_lang::{prelude::Pubkey, solana_program::instruction::Instruction};
+use kamino_lend::{state::Reserve, typedefs::ElevationGroup, Fraction, FractionExtra};
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::signature::{Keypair, Signer};
+
+use crate::{
+    actions::{get_obligation_state, Amount, ObligationState},
+    error::KaminoClientError,
+    health::{
+        collateral_to_liquidity, get_elevation_group, market_value, obligation_health,
+        BorrowPosition, ObligationHealth,
+    },
+    instruction::liquidate_obligation_and_redeem_reserve_collateral,
+    refresh::ObligationAction,
+    rpc::send_transaction,
+    token::create_associated_token_account,
+};
+
+/// What liquidating an obligation settles, before slippage.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LiquidationQuote {
+    pub repay_reserve: Pubkey,
+    pub withdraw_reserve: Pubkey,
+    /// Liquidity of the repay reserve the liquidator pays.
+    pub repay_amount: u64,
+    /// The most of the debt a single liquidation may repay.
+    pub max_repay_amount: u64,
+    pub bonus_rate: Fraction,
+    /// Collateral (cTokens) taken from the obligation's deposit.
+    pub withdraw_collateral_amount: u64,
+    /// The protocol's share of the bonus, in liquidity of the withdraw
+    /// reserve.
+    pub protocol_fee: u64,
+    /// Liquidity of the withdraw reserve the liquidator receives, net of the
+    /// protocol fee.
+    pub received_amount: u64,
+    pub repaid_value: Fraction,
+    pub received_value: Fraction,
+}
+
+impl LiquidationQuote {
+    pub fn is_profitable(&self) -> bool { self.received_value > self.repaid_value }
+
+    /// Value received over value repaid; zero for an unprofitable quote.
+    pub fn profit(&self) -> Fraction { self.received_value.saturating_sub(self.repaid_value) }
+
+    /// The least collateral klend may take for the liquidation to go
+    /// through: the quoted amount less `slippage_bps`.
+    pub fn min_acceptable_received_collateral_amount(&self, slippage_bps: u16) -> u64 {
+        let slippage = Fraction::from_bps(slippage_bps.min(10_000));
+        (Fraction::from_num(self.withdraw_collateral_amount) * (Fraction::ONE - slippage)).to_num()
+    }
+}
+
+/// The most of `borrow` one liquidation may repay: the market's close factor
+/// of the obligation's debt, or all of the borrow when the debt is under the
+/// market's full liquidation threshold, capped by the market's maximum per
+/// liquidation.
+pub fn max_liquidatable_amount(
+    health: &ObligationHealth,
+    liquidation_max_debt_close_factor_pct: u8,
+    min_full_liquidation_value_threshold: u64,
+    max_liquidatable_debt_market_value_at_once: u64,
+    borrow: &BorrowPosition,
+) -> Fraction {
+    if borrow.market_value == Fraction::ZERO {
+        return borrow.borrowed_amount;
+    }
+    let debt_value = health.borrowed_assets_market_value;
+    let max_value = if debt_value < Fraction::from_num(min_full_liquidation_value_threshold) {
+        borrow.market_value
+    } else {
+        debt_value * Fraction::from_percent(liquidation_max_debt_close_factor_pct)
+    };
+    let max_value = max_value
+        .min(borrow.market_value)
+        .min(Fraction::from_num(max_liquidatable_debt_market_value_at_once));
+    borrow.borrowed_amount * (max_value / borrow.market_value)
+}
+
+/// The bonus a liquidator gets on the value repaid: how far the obligation's
+/// LTV is past its liquidation LTV, kept between the reserves' minimum and
+/// maximum bonuses and short of turning the obligation into bad debt. An
+/// obligation already in bad debt gets the bad debt bonus.
+pub fn liquidation_bonus_rate(
+    health: &ObligationHealth,
+    collateral: &Reserve,
+    debt: &Reserve,
+    elevation_group: Option<&ElevationGroup>,
+) -> Fraction {
+    let no_bf_ltv = if health.deposited_value == Fraction::ZERO {
+        Fraction::ONE
+    } else {
+        health.borrowed_assets_market_value / health.deposited_value
+    };
+    let (collateral, debt) = (&collateral.config, &debt.config);
+    if no_bf_ltv >= Fraction::ONE {
+        let bad_debt_bps =
+            collateral.bad_debt_liquidation_bonus_bps.max(debt.bad_debt_liquidation_bonus_bps);
+        return Fraction::from_bps(bad_debt_bps);
+    }
+    let min_bps = collateral.min_liquidation_bonus_bps.max(debt.min_liquidation_bonus_bps);
+    let max_bps = match elevation_group {
+        Some(group) => group.max_liquidation_bonus_bps,
+        None => collateral.max_liquidation_bonus_bps.max(debt.max_liquidation_bonus_bps),
+    };
+    let unhealthy_factor = health.loan_to_value().saturating_sub(health.liquidation_ltv());
+    unhealthy_factor
+        .max(Fraction::from_bps(min_bps))
+        .min(Fraction::from_bps(max_bps))
+        .min(Fraction::ONE - no_bf_ltv)
+}
+
+/// Quotes repaying `amount` of the obligation's debt in `repay_reserve` for
+/// its collateral in `withdraw_reserve`; `Max` repays as much as one
+/// liquidation may. Fails for a healthy obligation or a liquidation too
+/// small to take any collateral.
+pub fn quote_liquidation(
+    state: &ObligationState,
+    repay_reserve: &Pubkey,
+    withdraw_reserve: &Pubkey,
+    amount: Amount,
+) -> Result<LiquidationQuote, KaminoClientError> {
+    let health = obligation_health(&state.obligation, &state.lending_market, &state.reserves)?;
+    if !health.is_unhealthy() {
+        return Err(KaminoClientError::InvalidInput(format!(
+            "obligation {} is healthy",
+            state.address
+        )));
+    }
+    let borrow =
+        health.borrows.iter().find(|borrow| borrow.reserve == *repay_reserve).ok_or_else(|| {
+            KaminoClientError::InvalidInput(format!(
+                "obligation {} does not borrow from {repay_reserve}",
+                state.address
+            ))
+        })?;
+    let deposit =
+        health.deposits.iter().find(|deposit| deposit.reserve == *withdraw_reserve).ok_or_else(
+            || {
+                KaminoClientError::InvalidInput(format!(
+                    "obligation {} has no deposit in {withdraw_reserve}",
+                    state.address
+                ))
+            },
+        )?;
+    let (collateral, debt) = (state.reserve(withdraw_reserve)?, state.reserve(repay_reserve)?);
+    let lending_market = &state.lending_market;
+    let elevation_group = get_elevation_group(lending_market, state.obligation.elevation_group)?;
+    let bonus_rate = liquidation_bonus_rate(&health, collateral, debt, elevation_group);
+
+    let max_liquidatable = max_liquidatable_amount(
+        &health,
+        lending_market.liquidation_max_debt_close_factor_pct,
+        lending_market.min_full_liquidation_value_threshold,
+        lending_market.max_liquidatable_debt_market_value_at_once,
+        borrow,
+    );
+    let mut settle_amount = match amount {
+        Amount::Exact(amount) => Fraction::from_num(amount).min(max_liquidatable),
+        Amount::Max => max_liquidatable,
+    };
+    // The liquidator gets the value settled plus the bonus, out of the
+    // deposit; when that is more than the deposit, all of it settles less
+    // debt.
+    let settle_value = market_value(debt, settle_amount);
+    let withdraw_value = settle_value * (Fraction::ONE + bonus_rate);
+    let takes_no_collateral = |settle_amount: Fraction| {
+        KaminoClientError::InvalidInput(format!(
+            "liquidating {} of obligation {} takes no collateral",
+            settle_amount.ceil(),
+            state.address
+        ))
+    };
+    if withdraw_value == Fraction::ZERO {
+        return Err(takes_no_collateral(settle_amount));
+    }
+    let withdraw_collateral_amount = if withdraw_value >= deposit.market_value {
+        settle_amount *= deposit.market_value / withdraw_value;
+        deposit.deposited_amount
+    } else {
+        (Fraction::from_num(deposit.deposited_amount) * (withdraw_value / deposit.market_value))
+            .to_num()
+    };
+    if withdraw_collateral_amount == 0 {
+        return Err(takes_no_collateral(settle_amount));
+    }
+
+    let withdraw_liquidity: u64 =
+        collateral_to_liquidity(collateral, withdraw_collateral_amount).to_num();
+    let protocol_fee = protocol_liquidation_fee(
+        withdraw_liquidity,
+        bonus_rate,
+        collateral.config.protocol_liquidation_fee_pct,
+    );
+    let repay_amount = settle_amount.ceil().to_num();
+    let received_amount = withdraw_liquidity - protocol_fee;
+    Ok(LiquidationQuote {
+        repay_reserve: *repay_reserve,
+        withdraw_reserve: *withdraw_reserve,
+        repay_amount,
+        max_repay_amount: max_liquidatable.ceil().to_num(),
+        bonus_rate,
+        withdraw_collateral_amount,
+        protocol_fee,
+        received_amount,
+        repaid_value: market_value(debt, Fraction::from_num(repay_amount)),
+        received_value: market_value(collateral, Fraction::from_num(received_amount)),
+    })
+}
+
+/// Liquidates the obligation as `quote` has it, from `liquidator`'s
+/// liquidity ATA of the repay reserve into their ATAs of the withdraw
+/// reserve, created first if missing. klend fails the liquidation with
+/// `LiquidationSlippageError` if it would take over `slippage_bps` less
+/// collateral than quoted.
+pub fn plan_liquidate(
+    liquidator: &Pubkey,
+    state: &ObligationState,
+    quote: &LiquidationQuote,
+    slippage_bps: u16,
+) -> Result<Vec<Instruction>, KaminoClientError> {
+    let repay_accounts = state.reserve_accounts(&quote.repay_reserve)?;
+    let withdraw_accounts = state.reserve_accounts(&quote.withdraw_reserve)?;
+    let liquidate = liquidate_obligation_and_redeem_reserve_collateral(
+        liquidator,
+        &state.address,
+        &repay_accounts,
+        &withdraw_accounts,
+        quote.repay_amount,
+        quote.min_acceptable_received_collateral_amount(slippage_bps),
+        0,
+    )?;
+    let create_destinations = [withdraw_accounts.collateral_mint, withdraw_accounts.liquidity_mint]
+        .map(|mint| {
+            create_associated_token_account(
+                liquidator,
+                liquidator,
+                &mint,
+                &withdraw_accounts.token_program,
+            )
+        });
+    let action = ObligationAction::Liquidate {
+        repay_reserve: quote.repay_reserve,
+        withdraw_reserve: quote.withdraw_reserve,
+    };
+    let instructions = state.with_refreshes(liquidator, action, [liquidate])?;
+    Ok(create_destinations.into_iter().chain(instructions).collect())
+}
+
+/// Liquidates `amount` of `obligation`'s debt in `repay_reserve` for its
+/// collateral in `withdraw_reserve`, if the quote is profitable, and returns
+/// the quote.
+pub fn liquidate(
+    client: &RpcClient,
+    liquidator: &Keypair,
+    obligation: &Pubkey,
+    repay_reserve: &Pubkey,
+    withdraw_reserve: &Pubkey,
+    amount: Amount,
+    slippage_bps: u16,
+) -> Result<LiquidationQuote, KaminoClientError> {
+    let state = get_obligation_state(client, obligation, &[])?;
+    let quote = quote_liquidation(&state, repay_reserve, withdraw_reserve, amount)?;
+    if !quote.is_profitable() {
+        return Err(KaminoClientError::InvalidInput(format!(
+            "liquidating obligation {obligation} repays {} for {} received",
+            quote.repaid_value, quote.received_value
+        )));
+    }
+    let instructions = plan_liquidate(&liquidator.pubkey(), &state, &quote, slippage_bps)?;
+    send_transaction(client, &liquidator.pubkey(), &instructions, &[liquidator])?;
+    Ok(quote)
+}
+
+/// The protocol's share of the bonus in `withdraw_amount`, rounded up; klend
+/// takes at least 1, whatever the fee percentage.
+fn protocol_liquidation_fee(
+    withdraw_amount: u64,
+    bonus_rate: Fraction,
+    protocol_liquidation_fee_pct: u8,
+) -> u64 {
+    let amount = Fraction::from_num(withdraw_amount);
+    let bonus = amount - amount / (Fraction::ONE + bonus_rate);
+    let fee: u64 = (bonus * Fraction::from_percent(protocol_liquidation_fee_pct)).ceil().to_num();
+    fee.max(1).min(withdraw_amount)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use kamino_lend::state::{LendingMarket, Obligation};
+
+    use super::*;
+    use crate::test_util::zeroed;
+
+    fn reserve() -> Reserve {
+        let mut reserve: Reserve = zeroed();
+        reserve.liquidity.mint_decimals = 6;
+        reserve.liquidity.market_price_sf = Fraction::ONE.to_bits();
+        reserve.liquidity.cumulative_borrow_rate_bsf.value[0] = 1 << 60;
+        reserve.config.loan_to_value_pct = 50;
+        reserve.config.liquidation_threshold_pct = 80;
+        reserve.config.borrow_factor_pct = 100;
+        reserve.config.min_liquidation_bonus_bps = 200;
+        reserve.config.max_liquidation_bonus_bps = 500;
+        reserve.config.bad_debt_liquidation_bonus_bps = 10;
+        reserve
+    }
+
+    /// `debt_amount` of $1 debt against $100 of collateral, liquidatable at
+    /// 80% LTV.
+    fn obligation_state(collateral: Pubkey, debt: Pubkey, debt_amount: u64) -> ObligationState {
+        let mut obligation: Obligation = zeroed();
+        obligation.deposits[0].deposit_reserve = collateral;
+        obligation.deposits[0].deposited_amount = 100_000_000;
+        obligation.borrows[0].borrow_reserve = debt;
+        obligation.borrows[0].borrowed_amount_sf = Fraction::from_num(debt_amount).to_bits();
+        obligation.borrows[0].cumulative_borrow_rate_bsf.value[0] = 1 << 60;
+        let mut lending_market: LendingMarket = zeroed();
+        lending_market.liquidation_max_debt_close_factor_pct = 20;
+        lending_market.max_liquidatable_debt_market_value_at_once = 500_000;
+        ObligationState {
+            address: Pubkey::new_unique(),
+            obligation,
+            lending_market,
+            reserves: HashMap::from([(collateral, reserve()), (debt, reserve())]),
+        }
+    }
+
+    #[test]
+    fn test_quote_liquidation() {
+        let (collateral, debt) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mut state = obligation_state(collateral, debt, 85_000_000);
+        // 5% past the liquidation LTV gets the maximum bonus, and the close
+        // factor caps the repay at $17.
+        let quote = quote_liquidation(&state, &debt, &collateral, Amount::Max).unwrap();
+        assert_eq!(quote.bonus_rate, Fraction::from_bps(500u16));
+        assert_eq!((quote.repay_amount, quote.max_repay_amount), (17_000_000, 17_000_000));
+        // Fractions floor like klend's, a lamport short of 17.85.
+        assert_eq!(quote.withdraw_collateral_amount, 17_849_999);
+        assert_eq!((quote.protocol_fee, quote.received_amount), (1, 17_849_998));
+        assert!(quote.is_profitable());
+        assert_eq!(quote.min_acceptable_received_collateral_amount(100), 17_671_499);
+
+        let quote = quote_liquidation(&state, &debt, &collateral, Amount::Exact(1_000_000));
+        assert_eq!(quote.unwrap().withdraw_collateral_amount, 1_049_999);
+        assert!(quote_liquidation(&state, &collateral, &debt, Amount::Max).is_err());
+
+        // Under the full liquidation threshold the whole borrow may go.
+        state.lending_market.min_full_liquidation_value_threshold = 100;
+        let quote = quote_liquidation(&state, &debt, &collateral, Amount::Max).unwrap();
+        assert_eq!((quote.repay_amount, quote.max_repay_amount), (85_000_000, 85_000_000));
+        assert_eq!(quote.withdraw_collateral_amount, 89_249_999);
+
+        let state = obligation_state(collateral, debt, 70_000_000);
+        let error = quote_liquidation(&state, &debt, &collateral, Amount::Max).unwrap_err();
+        assert!(matches!(error, KaminoClientError::InvalidInput(_)), "{error}");
+    }
+
+    #[test]
+    fn test_quote_liquidation_of_nothing() {
+        let (collateral, debt) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mut state = obligation_state(collateral, debt, 85_000_000);
+        let error = quote_liquidation(&state, &debt, &collateral, Amount::Exact(0)).unwrap_err();
+        assert!(error.to_string().contains("takes no collateral"), "{error}");
+
+        // A worthless deposit leaves nothing to settle against either.
+        state.reserves.get_mut(&collateral).unwrap().liquidity.market_price_sf = 0;
+        let error = quote_liquidation(&state, &debt, &collateral, Amount::Exact(0)).unwrap_err();
+        assert!(error.to_string().contains("takes no collateral"), "{error}");
+    }
+
+    #[test]
+    fn test_liquidation_bonus_rate() {
+        let (collateral, debt) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let bonus = |debt_amount| {
+            let state = obligation_state(collateral, debt, debt_amount);
+            let health =
+                obligation_health(&state.obligation, &state.lending_market, &state.reserves)
+                    .unwrap();
+            let reserves = &state.reserves;
+            let rate =
+                liquidation_bonus_rate(&health, &reserves[&collateral], &reserves[&debt], None);
+            (rate * 10_000).round().to_num::<u16>()
+        };
+        assert_eq!(bonus(81_000_000), 200);
+        assert_eq!(bonus(83_000_000), 300);
+        // The bonus never takes more than the debt leaves of the deposit.
+        assert_eq!(bonus(97_000_000), 300);
+        assert_eq!(bonus(100_000_000), 10);
+    }
+}
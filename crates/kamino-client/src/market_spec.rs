@@ -0,0 +1,469 @@
+//! Declarative description of a lending market and its reserves, read from
+//! TOML or YAML and turned into the klend configs that deploy it.
+
+use std::{fs, path::Path};
+
+use anchor_lang::prelude::Pubkey;
+use kamino_lend::{
+    typedefs::{
+        AssetTier, CurvePoint, ElevationGroup, PriceHeuristic, PythConfiguration, ReserveConfig,
+        ReserveFarmKind, ReserveFees, ScopeConfiguration, SwitchboardConfiguration, TokenInfo,
+        WithdrawalCaps,
+    },
+    Fraction, FractionExtra,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{error::KaminoClientError, interest::borrow_rate_curve};
+
+/// Elevation group slots of a reserve config.
+pub const MAX_RESERVE_ELEVATION_GROUPS: usize = 20;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MarketSpec {
+    /// Currency prices are quoted in, e.g. `USD`.
+    #[serde(default = "default_quote_currency")]
+    pub quote_currency: String,
+    /// Owner the market is handed over to once deployed; defaults to the
+    /// deployer. The new owner has to accept the transfer.
+    #[serde(default, with = "option_pubkey")]
+    pub owner: Option<Pubkey>,
+    #[serde(default, with = "option_pubkey")]
+    pub risk_council: Option<Pubkey>,
+    /// Farms global config, required by reserves with farms.
+    #[serde(default, with = "option_pubkey")]
+    pub farms_global_config: Option<Pubkey>,
+    #[serde(default)]
+    pub elevation_groups: Vec<ElevationGroupSpec>,
+    #[serde(default)]
+    pub reserves: Vec<ReserveSpec>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ElevationGroupSpec {
+    pub id: u8,
+    pub ltv_pct: u8,
+    pub liquidation_threshold_pct: u8,
+    pub max_liquidation_bonus_bps: u16,
+    #[serde(default = "default_true")]
+    pub allow_new_loans: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ReserveSpec {
+    /// Token name stored in the reserve's token info; also keys the reserve
+    /// in the deployed address map.
+    pub name: String,
+    /// Existing liquidity mint; a new one with `decimals` is created when
+    /// unset.
+    #[serde(default, with = "option_pubkey")]
+    pub mint: Option<Pubkey>,
+    #[serde(default = "default_decimals")]
+    pub decimals: u8,
+    pub oracle: OracleSpec,
+    #[serde(default = "default_max_age_price_seconds")]
+    pub max_age_price_seconds: u64,
+    #[serde(default = "default_loan_to_value_pct")]
+    pub loan_to_value_pct: u8,
+    #[serde(default = "default_liquidation_threshold_pct")]
+    pub liquidation_threshold_pct: u8,
+    #[serde(default = "default_min_liquidation_bonus_bps")]
+    pub min_liquidation_bonus_bps: u16,
+    #[serde(default = "default_max_liquidation_bonus_bps")]
+    pub max_liquidation_bonus_bps: u16,
+    #[serde(default = "default_bad_debt_liquidation_bonus_bps")]
+    pub bad_debt_liquidation_bonus_bps: u16,
+    #[serde(default)]
+    pub protocol_take_rate_pct: u8,
+    #[serde(default)]
+    pub protocol_liquidation_fee_pct: u8,
+    #[serde(default = "default_borrow_factor_pct")]
+    pub borrow_factor_pct: u64,
+    #[serde(default)]
+    pub borrow_fee_bps: u16,
+    #[serde(default)]
+    pub flash_loan_fee_bps: u16,
+    /// `[utilization_bps, borrow_rate_bps]` points.
+    #[serde(default = "default_borrow_rate_curve")]
+    pub borrow_rate_curve: Vec<[u32; 2]>,
+    #[serde(default = "default_limit")]
+    pub deposit_limit: u64,
+    #[serde(default = "default_limit")]
+    pub borrow_limit: u64,
+    #[serde(default)]
+    pub deposit_withdrawal_cap: WithdrawalCapSpec,
+    #[serde(default)]
+    pub debt_withdrawal_cap: WithdrawalCapSpec,
+    #[serde(default)]
+    pub asset_tier: AssetTierSpec,
+    #[serde(default)]
+    pub elevation_groups: Vec<u8>,
+    #[serde(default)]
+    pub farms: Vec<FarmSpec>,
+    #[serde(default = "default_deleveraging_margin_call_period_secs")]
+    pub deleveraging_margin_call_period_secs: u64,
+    #[serde(default = "default_deleveraging_threshold_slots_per_bps")]
+    pub deleveraging_threshold_slots_per_bps: u64,
+}
+
+/// Price source of a reserve.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OracleSpec {
+    Pyth(#[serde(with = "pubkey")] Pubkey),
+    Switchboard {
+        #[serde(with = "pubkey")]
+        price: Pubkey,
+        #[serde(default, with = "option_pubkey")]
+        twap: Option<Pubkey>,
+    },
+    Scope {
+        #[serde(with = "pubkey")]
+        prices: Pubkey,
+        chain: Vec<u16>,
+        #[serde(default)]
+        twap_chain: Vec<u16>,
+    },
+    /// A synthetic Pyth feed at a fixed price, for in-process deployments.
+    Price(f64),
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WithdrawalCapSpec {
+    pub capacity: i64,
+    pub interval_seconds: u64,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AssetTierSpec {
+    #[default]
+    Regular,
+    IsolatedCollateral,
+    IsolatedDebt,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FarmSpec {
+    Collateral,
+    Debt,
+}
+
+impl FarmSpec {
+    pub fn kind(&self) -> ReserveFarmKind {
+        match self {
+            Self::Collateral => ReserveFarmKind::Collateral,
+            Self::Debt => ReserveFarmKind::Debt,
+        }
+    }
+}
+
+impl MarketSpec {
+    /// Reads a `.toml`, `.yaml` or `.yml` spec.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, KaminoClientError> {
+        let path = path.as_ref();
+        let content = fs::read_to_string(path)?;
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("toml") => Self::from_toml(&content),
+            Some("yaml" | "yml") => Self::from_yaml(&content),
+            _ => Err(KaminoClientError::InvalidInput(format!(
+                "{}: expected a .toml or .yaml spec",
+                path.display()
+            ))),
+        }
+    }
+
+    pub fn from_toml(spec: &str) -> Result<Self, KaminoClientError> {
+        toml::from_str(spec).map_err(|error| KaminoClientError::Deserialize(error.to_string()))
+    }
+
+    pub fn from_yaml(spec: &str) -> Result<Self, KaminoClientError> {
+        serde_yaml::from_str(spec)
+            .map_err(|error| KaminoClientError::Deserialize(error.to_string()))
+    }
+
+    pub fn quote_currency(&self) -> Result<[u8; 32], KaminoClientError> {
+        encode_name(&self.quote_currency)
+    }
+}
+
+impl ElevationGroupSpec {
+    pub fn to_elevation_group(&self) -> ElevationGroup {
+        ElevationGroup {
+            max_liquidation_bonus_bps: self.max_liquidation_bonus_bps,
+            id: self.id,
+            ltv_pct: self.ltv_pct,
+            liquidation_threshold_pct: self.liquidation_threshold_pct,
+            allow_new_loans: u8::from(self.allow_new_loans),
+            ..Default::default()
+        }
+    }
+}
+
+impl ReserveSpec {
+    /// The reserve config, with `oracle` as the address of a `Price` oracle.
+    pub fn reserve_config(&self, oracle: &Pubkey) -> Result<ReserveConfig, KaminoClientError> {
+        if self.elevation_groups.len() > MAX_RESERVE_ELEVATION_GROUPS {
+            return Err(KaminoClientError::InvalidInput(format!(
+                "{}: too many elevation groups",
+                self.name
+            )));
+        }
+        let mut elevation_groups = [0; MAX_RESERVE_ELEVATION_GROUPS];
+        elevation_groups[..self.elevation_groups.len()].copy_from_slice(&self.elevation_groups);
+        let curve: Vec<_> = self
+            .borrow_rate_curve
+            .iter()
+            .map(|&[utilization_rate_bps, borrow_rate_bps]| CurvePoint {
+                utilization_rate_bps,
+                borrow_rate_bps,
+            })
+            .collect();
+        if curve.is_empty() || curve.len() > 11 {
+            return Err(KaminoClientError::InvalidInput(format!(
+                "{}: the borrow rate curve needs 1 to 11 points",
+                self.name
+            )));
+        }
+
+        Ok(ReserveConfig {
+            asset_tier: self.asset_tier.to_asset_tier() as u8,
+            multiplier_side_boost: [1, 1],
+            multiplier_tag_boost: [1; 8],
+            protocol_take_rate_pct: self.protocol_take_rate_pct,
+            protocol_liquidation_fee_pct: self.protocol_liquidation_fee_pct,
+            loan_to_value_pct: self.loan_to_value_pct,
+            liquidation_threshold_pct: self.liquidation_threshold_pct,
+            min_liquidation_bonus_bps: self.min_liquidation_bonus_bps,
+            max_liquidation_bonus_bps: self.max_liquidation_bonus_bps,
+            bad_debt_liquidation_bonus_bps: self.bad_debt_liquidation_bonus_bps,
+            deleveraging_margin_call_period_secs: self.deleveraging_margin_call_period_secs,
+            deleveraging_threshold_slots_per_bps: self.deleveraging_threshold_slots_per_bps,
+            fees: ReserveFees {
+                borrow_fee_sf: fraction_sf(self.borrow_fee_bps),
+                flash_loan_fee_sf: fraction_sf(self.flash_loan_fee_bps),
+                padding: [0; 8],
+            },
+            borrow_rate_curve: borrow_rate_curve(&curve),
+            borrow_factor_pct: self.borrow_factor_pct,
+            deposit_limit: self.deposit_limit,
+            borrow_limit: self.borrow_limit,
+            token_info: TokenInfo {
+                name: encode_name(&self.name)?,
+                heuristic: PriceHeuristic { lower: 0, upper: 0, exp: 0 },
+                max_age_price_seconds: self.max_age_price_seconds,
+                ..self.oracle.token_info(oracle)
+            },
+            deposit_withdrawal_cap: self.deposit_withdrawal_cap.to_withdrawal_caps(),
+            debt_withdrawal_cap: self.debt_withdrawal_cap.to_withdrawal_caps(),
+            elevation_groups,
+            ..Default::default()
+        })
+    }
+}
+
+impl OracleSpec {
+    /// Token info with only the oracle fields set.
+    fn token_info(&self, synthetic: &Pubkey) -> TokenInfo {
+        let pad_chain = |chain: &[u16]| {
+            let mut padded = [u16::MAX; 4];
+            padded[..chain.len().min(4)].copy_from_slice(&chain[..chain.len().min(4)]);
+            padded
+        };
+        match self {
+            Self::Pyth(price) => TokenInfo {
+                pyth_configuration: PythConfiguration { price: *price },
+                ..Default::default()
+            },
+            Self::Price(_) => TokenInfo {
+                pyth_configuration: PythConfiguration { price: *synthetic },
+                ..Default::default()
+            },
+            Self::Switchboard { price, twap } => TokenInfo {
+                switchboard_configuration: SwitchboardConfiguration {
+                    price_aggregator: *price,
+                    twap_aggregator: twap.unwrap_or_default(),
+                },
+                ..Default::default()
+            },
+            Self::Scope { prices, chain, twap_chain } => TokenInfo {
+                scope_configuration: ScopeConfiguration {
+                    price_feed: *prices,
+                    price_chain: pad_chain(chain),
+                    twap_chain: pad_chain(twap_chain),
+                },
+                ..Default::default()
+            },
+        }
+    }
+}
+
+impl WithdrawalCapSpec {
+    fn to_withdrawal_caps(self) -> WithdrawalCaps {
+        WithdrawalCaps {
+            config_capacity: self.capacity,
+            config_interval_length_seconds: self.interval_seconds,
+            ..Default::default()
+        }
+    }
+}
+
+impl AssetTierSpec {
+    pub fn to_asset_tier(self) -> AssetTier {
+        match self {
+            Self::Regular => AssetTier::Regular,
+            Self::IsolatedCollateral => AssetTier::IsolatedCollateral,
+            Self::IsolatedDebt => AssetTier::IsolatedDebt,
+        }
+    }
+}
+
+/// Null-padded UTF-8, as klend stores token names and quote currencies.
+pub fn encode_name(name: &str) -> Result<[u8; 32], KaminoClientError> {
+    let mut encoded = [0; 32];
+    encoded
+        .get_mut(..name.len())
+        .ok_or_else(|| KaminoClientError::InvalidInput(format!("{name} is longer than 32 bytes")))?
+        .copy_from_slice(name.as_bytes());
+    Ok(encoded)
+}
+
+fn fraction_sf(bps: u16) -> u64 { Fraction::from_bps(bps).to_bits() as u64 }
+
+fn default_quote_currency() -> String { "USD".to_string() }
+
+fn default_true() -> bool { true }
+
+fn default_decimals() -> u8 { 6 }
+
+fn default_max_age_price_seconds() -> u64 { 180 }
+
+fn default_loan_to_value_pct() -> u8 { 75 }
+
+fn default_liquidation_threshold_pct() -> u8 { 85 }
+
+fn default_min_liquidation_bonus_bps() -> u16 { 200 }
+
+fn default_max_liquidation_bonus_bps() -> u16 { 500 }
+
+fn default_bad_debt_liquidation_bonus_bps() -> u16 { 10 }
+
+fn default_borrow_factor_pct() -> u64 { 100 }
+
+fn default_borrow_rate_curve() -> Vec<[u32; 2]> { vec![[0, 1], [100, 100], [10_000, 100_000]] }
+
+fn default_limit() -> u64 { 10_000_000_000_000 }
+
+/// Three days.
+fn default_deleveraging_margin_call_period_secs() -> u64 { 259_200 }
+
+/// 0.01% per hour.
+fn default_deleveraging_threshold_slots_per_bps() -> u64 { 7_200 }
+
+/// Serde of a `Pubkey` as a base58 string rather than a byte array.
+pub mod pubkey {
+    use std::str::FromStr;
+
+    use anchor_lang::prelude::Pubkey;
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(pubkey: &Pubkey, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(pubkey)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Pubkey, D::Error> {
+        Pubkey::from_str(&String::deserialize(deserializer)?).map_err(D::Error::custom)
+    }
+}
+
+/// Serde of an `Option<Pubkey>` as an optional base58 string.
+pub mod option_pubkey {
+    use std::str::FromStr;
+
+    use anchor_lang::prelude::Pubkey;
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        pubkey: &Option<Pubkey>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match pubkey {
+            Some(pubkey) => serializer.collect_str(pubkey),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Pubkey>, D::Error> {
+        Option::<String>::deserialize(deserializer)?
+            .map(|pubkey| Pubkey::from_str(&pubkey).map_err(D::Error::custom))
+            .transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SPEC: &str = r#"
+        quote_currency = "USD"
+        risk_council = "11111111111111111111111111111111"
+
+        [[elevation_groups]]
+        id = 1
+        ltv_pct = 90
+        liquidation_threshold_pct = 92
+        max_liquidation_bonus_bps = 100
+
+        [[reserves]]
+        name = "SOL"
+        decimals = 9
+        oracle = { pyth = "E4v1BBgoso9s64TQvmyownAVJbhbEPGyzA3qn4n46qj9" }
+        elevation_groups = [1]
+        farms = ["collateral"]
+
+        [[reserves]]
+        name = "USDC"
+        oracle = { scope = { prices = "3NJYftD5sjVfxSnUdZ1wVML8f3aC6mp1CXCL6L7TnU8C", chain = [0, 2] } }
+        asset_tier = "isolated_debt"
+        borrow_rate_curve = [[0, 0], [8000, 500], [10000, 3000]]
+        deposit_withdrawal_cap = { capacity = 1000, interval_seconds = 3600 }
+    "#;
+
+    #[test]
+    fn test_toml_and_yaml_agree() {
+        let toml_spec = MarketSpec::from_toml(SPEC).unwrap();
+        let yaml_spec = MarketSpec::from_yaml(&serde_yaml::to_string(&toml_spec).unwrap()).unwrap();
+        assert_eq!(yaml_spec, toml_spec);
+        assert_eq!(toml_spec.risk_council, Some(Pubkey::default()));
+        assert_eq!(toml_spec.reserves[0].farms, [FarmSpec::Collateral]);
+        assert!(MarketSpec::from_toml("quote = \"USD\"").is_err());
+    }
+
+    #[test]
+    fn test_reserve_config() {
+        let spec = MarketSpec::from_toml(SPEC).unwrap();
+        let sol = spec.reserves[0].reserve_config(&Pubkey::default()).unwrap();
+        assert_eq!(&sol.token_info.name[..4], b"SOL\0");
+        assert_eq!(sol.elevation_groups[..2], [1, 0]);
+        assert_eq!((sol.loan_to_value_pct, sol.liquidation_threshold_pct), (75, 85));
+        assert_eq!(sol.borrow_rate_curve.points[10].utilization_rate_bps, 10_000);
+
+        let usdc = spec.reserves[1].reserve_config(&Pubkey::default()).unwrap();
+        assert_eq!(usdc.asset_tier, AssetTier::IsolatedDebt as u8);
+        assert_eq!(usdc.token_info.scope_configuration.price_chain, [0, 2, u16::MAX, u16::MAX]);
+        assert_eq!(usdc.token_info.scope_configuration.twap_chain, [u16::MAX; 4]);
+        assert_eq!(usdc.deposit_withdrawal_cap.config_interval_length_seconds, 3600);
+
+        let synthetic = Pubkey::new_unique();
+        let priced = ReserveSpec { oracle: OracleSpec::Price(1.0), ..spec.reserves[0].clone() };
+        let config = priced.reserve_config(&synthetic).unwrap();
+        assert_eq!(config.token_info.pyth_configuration.price, synthetic);
+    }
+}
@@ -0,0 +1,64 @@
+use anchor_lang::prelude::Pubkey;
+use kamino_lend::typedefs::InitObligationArgs;
+
+use crate::pda;
+
+/// Kind of obligation, following the tag and seed conventions of the Kamino
+/// SDKs. An owner has at most one obligation of each kind and seeds in a
+/// market: klend rejects any obligation id but 0 with `InvalidObligationId`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ObligationType {
+    /// Tag 0, both seed accounts are the default pubkey.
+    #[default]
+    Vanilla,
+    /// Tag 1, seeded with the collateral and debt mints.
+    Multiply { coll_mint: Pubkey, debt_mint: Pubkey },
+    /// Tag 2, seeded twice with the lent mint.
+    Lending { mint: Pubkey },
+    /// Tag 3, seeded with the collateral and debt mints.
+    Leverage { coll_mint: Pubkey, debt_mint: Pubkey },
+}
+
+impl ObligationType {
+    pub fn tag(&self) -> u8 {
+        match self {
+            Self::Vanilla => 0,
+            Self::Multiply { .. } => 1,
+            Self::Lending { .. } => 2,
+            Self::Leverage { .. } => 3,
+        }
+    }
+
+    /// The `seed1_account` and `seed2_account` passed to `initObligation`.
+    pub fn seed_accounts(&self) -> (Pubkey, Pubkey) {
+        match self {
+            Self::Vanilla => (Pubkey::default(), Pubkey::default()),
+            Self::Multiply { coll_mint, debt_mint } | Self::Leverage { coll_mint, debt_mint } => {
+                (*coll_mint, *debt_mint)
+            }
+            Self::Lending { mint } => (*mint, *mint),
+        }
+    }
+
+    pub fn to_args(&self) -> InitObligationArgs { InitObligationArgs { tag: self.tag(), id: 0 } }
+
+    pub fn address(&self, lending_market: &Pubkey, owner: &Pubkey) -> Pubkey {
+        let (seed1, seed2) = self.seed_accounts();
+        pda::get_obligation(lending_market, owner, self.tag(), 0, &seed1, &seed2)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vanilla_matches_user_obligation() {
+        let lending_market = Pubkey::new_unique();
+        let owner = Pubkey::new_unique();
+        assert_eq!(
+            ObligationType::default().address(&lending_market, &owner),
+            pda::get_user_obligation(&lending_market, &owner)
+        );
+    }
+}
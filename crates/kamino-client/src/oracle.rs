@@ -0,0 +1,70 @@
+use anchor_lang::prelude::Pubkey;
+use kamino_lend::typedefs::TokenInfo;
+
+/// Placeholder klend accepts in place of an unconfigured oracle.
+pub const NULL_PUBKEY: Pubkey = Pubkey::new_from_array([
+    11, 193, 238, 216, 208, 116, 241, 195, 55, 212, 76, 22, 75, 202, 40, 216, 76, 206, 27, 169,
+    138, 64, 177, 28, 19, 90, 156, 0, 0, 0, 0, 0,
+]);
+
+/// Oracle accounts `refreshReserve` reads for a reserve, `None` when the
+/// corresponding source is not configured.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ReserveOracles {
+    pub pyth: Option<Pubkey>,
+    pub switchboard_price: Option<Pubkey>,
+    pub switchboard_twap: Option<Pubkey>,
+    pub scope_prices: Option<Pubkey>,
+}
+
+impl ReserveOracles {
+    pub fn from_token_info(token_info: &TokenInfo) -> Self {
+        Self {
+            pyth: configured(&token_info.pyth_configuration.price),
+            switchboard_price: configured(&token_info.switchboard_configuration.price_aggregator),
+            switchboard_twap: configured(&token_info.switchboard_configuration.twap_aggregator),
+            scope_prices: configured(&token_info.scope_configuration.price_feed),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool { *self == Self::default() }
+}
+
+fn configured(oracle: &Pubkey) -> Option<Pubkey> {
+    (*oracle != Pubkey::default() && *oracle != NULL_PUBKEY).then_some(*oracle)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use kamino_lend::typedefs::{PythConfiguration, ScopeConfiguration};
+
+    use super::*;
+
+    #[test]
+    fn test_null_pubkey() {
+        assert_eq!(
+            NULL_PUBKEY,
+            Pubkey::from_str("nu11111111111111111111111111111111111111111").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_from_token_info() {
+        let pyth = Pubkey::new_unique();
+        let token_info = TokenInfo {
+            pyth_configuration: PythConfiguration { price: pyth },
+            scope_configuration: ScopeConfiguration {
+                price_feed: NULL_PUBKEY,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert_eq!(
+            ReserveOracles::from_token_info(&token_info),
+            ReserveOracles { pyth: Some(pyth), ..Default::default() }
+        );
+        assert!(ReserveOracles::from_token_info(&TokenInfo::default()).is_empty());
+    }
+}
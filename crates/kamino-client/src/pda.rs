@@ -0,0 +1,114 @@
+use anchor_lang::prelude::Pubkey;
+use kamino_lend::ID as KAMINO_LENDING_ID;
+
+pub mod farms {
+    anchor_lang::declare_id!("FarmsPZpWu9i7Kky8tPN37rs2TpmMrAZrC7S7vJa91Hr");
+}
+
+pub fn get_market_authority(lending_market: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"lma", lending_market.as_ref()], &KAMINO_LENDING_ID).0
+}
+
+pub fn get_reserve_liquidity_supply(lending_market: &Pubkey, mint: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"reserve_liq_supply", lending_market.as_ref(), mint.as_ref()],
+        &KAMINO_LENDING_ID,
+    )
+    .0
+}
+
+pub fn get_reserve_collateral_mint(lending_market: &Pubkey, mint: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"reserve_coll_mint", lending_market.as_ref(), mint.as_ref()],
+        &KAMINO_LENDING_ID,
+    )
+    .0
+}
+
+pub fn get_reserve_collateral_supply(lending_market: &Pubkey, mint: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"reserve_coll_supply", lending_market.as_ref(), mint.as_ref()],
+        &KAMINO_LENDING_ID,
+    )
+    .0
+}
+
+pub fn get_reserve_fee_vault(lending_market: &Pubkey, mint: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"fee_receiver", lending_market.as_ref(), mint.as_ref()],
+        &KAMINO_LENDING_ID,
+    )
+    .0
+}
+
+pub fn get_obligation(
+    lending_market: &Pubkey,
+    owner: &Pubkey,
+    tag: u8,
+    id: u8,
+    seed1: &Pubkey,
+    seed2: &Pubkey,
+) -> Pubkey {
+    Pubkey::find_program_address(
+        &[&[tag], &[id], owner.as_ref(), lending_market.as_ref(), seed1.as_ref(), seed2.as_ref()],
+        &KAMINO_LENDING_ID,
+    )
+    .0
+}
+
+/// Address of `user`'s first vanilla obligation.
+pub fn get_user_obligation(lending_market: &Pubkey, user: &Pubkey) -> Pubkey {
+    get_obligation(lending_market, user, 0, 0, &Pubkey::default(), &Pubkey::default())
+}
+
+pub fn get_user_metadata(user: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"user_meta", user.as_ref()], &KAMINO_LENDING_ID).0
+}
+
+pub fn get_referrer_token_state(referrer: &Pubkey, reserve: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"referrer_acc", referrer.as_ref(), reserve.as_ref()],
+        &KAMINO_LENDING_ID,
+    )
+    .0
+}
+
+pub fn get_referrer_state(referrer: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"ref_state", referrer.as_ref()], &KAMINO_LENDING_ID).0
+}
+
+pub fn get_referrer_short_url(short_url: &str) -> Pubkey {
+    Pubkey::find_program_address(&[b"short_url", short_url.as_bytes()], &KAMINO_LENDING_ID).0
+}
+
+/// Authority of a farm's vaults, owned by the farms program.
+pub fn get_farm_vault_authority(farm_state: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"authority", farm_state.as_ref()], &farms::ID).0
+}
+
+/// Farm user state of an obligation staked in a reserve farm.
+pub fn get_obligation_farm_user_state(farm_state: &Pubkey, obligation: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"user", farm_state.as_ref(), obligation.as_ref()], &farms::ID).0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reserve_pdas_are_distinct() {
+        let lending_market = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let pdas = [
+            get_reserve_liquidity_supply(&lending_market, &mint),
+            get_reserve_collateral_mint(&lending_market, &mint),
+            get_reserve_collateral_supply(&lending_market, &mint),
+            get_reserve_fee_vault(&lending_market, &mint),
+        ];
+        for (i, a) in pdas.iter().enumerate() {
+            for b in &pdas[i + 1..] {
+                assert_ne!(a, b);
+            }
+        }
+    }
+}
@@ -0,0 +1,212 @@
+//! Plans the refresh instructions klend requires around obligation-touching
+//! operations.
+//!
+//! klend rejects an operation unless, in the same transaction and slot, every
+//! reserve of the obligation was refreshed, the operation's own reserve was
+//! refreshed right before `refreshObligation`, and, when the reserve has a
+//! farm, `refreshObligationFarmsForReserve` runs both before and after it.
+
+use std::collections::HashMap;
+
+use anchor_lang::{prelude::Pubkey, solana_program::instruction::Instruction};
+use kamino_lend::{
+    state::{Obligation, Reserve},
+    typedefs::ReserveFarmKind,
+};
+
+use crate::{
+    error::KaminoClientError,
+    instruction::{
+        refresh_obligation, refresh_obligation_farms_for_reserve, refresh_reserve, ReserveAccounts,
+    },
+    token::KLEND_TOKEN_PROGRAM,
+};
+
+/// The operation refreshes are planned for, with the reserves it touches.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ObligationAction {
+    Deposit {
+        reserve: Pubkey,
+    },
+    Withdraw {
+        reserve: Pubkey,
+    },
+    Borrow {
+        reserve: Pubkey,
+    },
+    Repay {
+        reserve: Pubkey,
+    },
+    Liquidate {
+        repay_reserve: Pubkey,
+        withdraw_reserve: Pubkey,
+    },
+    /// Only refreshes the obligation, e.g. before `requestElevationGroup`.
+    Refresh,
+}
+
+impl ObligationAction {
+    /// Reserves of the operation in the order klend expects their refreshes,
+    /// each with the farm kind it affects.
+    fn reserves(&self) -> Vec<(Pubkey, ReserveFarmKind)> {
+        match *self {
+            Self::Deposit { reserve } | Self::Withdraw { reserve } => {
+                vec![(reserve, ReserveFarmKind::Collateral)]
+            }
+            Self::Borrow { reserve } | Self::Repay { reserve } => {
+                vec![(reserve, ReserveFarmKind::Debt)]
+            }
+            Self::Liquidate { repay_reserve, withdraw_reserve } => vec![
+                (withdraw_reserve, ReserveFarmKind::Collateral),
+                (repay_reserve, ReserveFarmKind::Debt),
+            ],
+            Self::Refresh => vec![],
+        }
+    }
+}
+
+/// Instructions to place around an obligation-touching operation.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RefreshPlan {
+    /// Reserve refreshes, then the obligation refresh, then farm refreshes.
+    pub pre: Vec<Instruction>,
+    /// Farm refreshes that must follow the operation.
+    pub post: Vec<Instruction>,
+}
+
+impl RefreshPlan {
+    /// Surrounds `instructions` with the planned refreshes.
+    pub fn wrap(self, instructions: impl IntoIterator<Item = Instruction>) -> Vec<Instruction> {
+        self.pre.into_iter().chain(instructions).chain(self.post).collect()
+    }
+}
+
+/// Plans the refreshes for `action` on `obligation`. `reserves` must hold every
+/// reserve of the obligation and of the action; farm refreshes are signed by
+/// `crank`.
+pub fn plan_refreshes(
+    crank: &Pubkey,
+    obligation_address: &Pubkey,
+    obligation: &Obligation,
+    action: &ObligationAction,
+    reserves: &HashMap<Pubkey, Reserve>,
+) -> Result<RefreshPlan, KaminoClientError> {
+    let action_reserves = action.reserves();
+    let obligation_reserves = obligation
+        .deposits
+        .iter()
+        .map(|deposit| deposit.deposit_reserve)
+        .chain(obligation.borrows.iter().map(|borrow| borrow.borrow_reserve));
+    let mut to_refresh: Vec<Pubkey> = Vec::new();
+    for reserve in obligation_reserves {
+        let is_action_reserve = action_reserves.iter().any(|(r, _)| *r == reserve);
+        if reserve != Pubkey::default() && !is_action_reserve && !to_refresh.contains(&reserve) {
+            to_refresh.push(reserve);
+        }
+    }
+    to_refresh.extend(action_reserves.iter().map(|(reserve, _)| *reserve));
+
+    let mut plan = RefreshPlan::default();
+    for reserve in &to_refresh {
+        plan.pre.push(refresh_reserve(reserve, get_reserve(reserves, reserve)?)?);
+    }
+    plan.pre.push(refresh_obligation(obligation_address, obligation)?);
+
+    for (reserve, kind) in &action_reserves {
+        let state = get_reserve(reserves, reserve)?;
+        let farm_state = match kind {
+            ReserveFarmKind::Collateral => state.farm_collateral,
+            ReserveFarmKind::Debt => state.farm_debt,
+        };
+        if farm_state == Pubkey::default() {
+            continue;
+        }
+        // refreshObligationFarmsForReserve always runs under the classic
+        // token program.
+        let reserve_accounts = ReserveAccounts::from_state(reserve, state, &KLEND_TOKEN_PROGRAM);
+        let ix = refresh_obligation_farms_for_reserve(
+            crank,
+            obligation_address,
+            &reserve_accounts,
+            &farm_state,
+            *kind,
+        )?;
+        plan.pre.push(ix.clone());
+        plan.post.push(ix);
+    }
+    Ok(plan)
+}
+
+fn get_reserve<'a>(
+    reserves: &'a HashMap<Pubkey, Reserve>,
+    reserve: &Pubkey,
+) -> Result<&'a Reserve, KaminoClientError> {
+    reserves.get(reserve).ok_or(KaminoClientError::AccountNotFound(*reserve))
+}
+
+#[cfg(test)]
+mod tests {
+    use kamino_lend::ID as KAMINO_LENDING_ID;
+
+    use super::*;
+    use crate::test_util::zeroed;
+
+    fn reserve_state(lending_market: &Pubkey) -> Reserve {
+        let mut reserve: Reserve = zeroed();
+        reserve.lending_market = *lending_market;
+        reserve
+    }
+
+    #[test]
+    fn test_plan_orders_action_reserve_last() {
+        let lending_market = Pubkey::new_unique();
+        let crank = Pubkey::new_unique();
+        let obligation_address = Pubkey::new_unique();
+        let [deposit_reserve, borrow_reserve, new_reserve] =
+            [Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique()];
+        let mut obligation: Obligation = zeroed();
+        obligation.lending_market = lending_market;
+        obligation.deposits[0].deposit_reserve = deposit_reserve;
+        obligation.borrows[0].borrow_reserve = borrow_reserve;
+        obligation.borrows[1].borrow_reserve = deposit_reserve;
+        let mut reserves: HashMap<Pubkey, Reserve> = [deposit_reserve, borrow_reserve, new_reserve]
+            .into_iter()
+            .map(|reserve| (reserve, reserve_state(&lending_market)))
+            .collect();
+        reserves.get_mut(&new_reserve).unwrap().farm_debt = Pubkey::new_unique();
+
+        let plan = plan_refreshes(
+            &crank,
+            &obligation_address,
+            &obligation,
+            &ObligationAction::Borrow { reserve: new_reserve },
+            &reserves,
+        )
+        .unwrap();
+
+        let refreshed: Vec<Pubkey> = plan.pre[..3].iter().map(|ix| ix.accounts[0].pubkey).collect();
+        assert_eq!(refreshed, vec![deposit_reserve, borrow_reserve, new_reserve]);
+        let obligation_refresh = &plan.pre[3];
+        assert_eq!(obligation_refresh.accounts[1].pubkey, obligation_address);
+        let remaining: Vec<Pubkey> =
+            obligation_refresh.accounts[2..].iter().map(|meta| meta.pubkey).collect();
+        assert_eq!(remaining, vec![deposit_reserve, borrow_reserve, deposit_reserve]);
+        assert_eq!(plan.pre.len(), 5);
+        assert_eq!(plan.post, vec![plan.pre[4].clone()]);
+        assert!(plan.pre.iter().all(|ix| ix.program_id == KAMINO_LENDING_ID));
+    }
+
+    #[test]
+    fn test_plan_requires_reserve_states() {
+        let mut obligation: Obligation = zeroed();
+        obligation.deposits[0].deposit_reserve = Pubkey::new_unique();
+        let result = plan_refreshes(
+            &Pubkey::new_unique(),
+            &Pubkey::new_unique(),
+            &obligation,
+            &ObligationAction::Refresh,
+            &HashMap::new(),
+        );
+        assert!(result.is_err());
+    }
+}
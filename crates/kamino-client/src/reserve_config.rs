@@ -0,0 +1,953 @@
+//! Typed `updateSingleReserveConfig` and `updateEntireReserveConfig` values,
+//! the diff between two configs and the checks klend runs on them.
+//!
+//! klend reads every value as the raw little-endian encoding of the mode's
+//! type, zero-padded to the instruction's array: 32 bytes for a single field,
+//! the Borsh size of [`ReserveConfig`] for the entire config.
+
+use std::fmt;
+
+use anchor_lang::{prelude::Pubkey, AnchorSerialize};
+use kamino_lend::{
+    typedefs::{BorrowRateCurve, ReserveConfig, UpdateConfigMode, WithdrawalCaps},
+    Fraction, FractionExtra, LendingError,
+};
+use rust_decimal::Decimal;
+
+use crate::{error::KaminoClientError, flash_loan::FLASH_LOANS_DISABLED, oracle::ReserveOracles};
+
+const FULL_BPS: u32 = 10_000;
+
+/// Value length of `updateSingleReserveConfig`.
+pub const SINGLE_VALUE_LEN: usize = 32;
+
+/// Value length of `updateEntireReserveConfig`.
+pub const ENTIRE_VALUE_LEN: usize = 648;
+
+/// One `UpdateConfigMode` with its value.
+#[derive(Clone, Debug)]
+pub enum ReserveConfigUpdate {
+    LoanToValuePct(u8),
+    MaxLiquidationBonusBps(u16),
+    LiquidationThresholdPct(u8),
+    ProtocolLiquidationFeePct(u8),
+    ProtocolTakeRatePct(u8),
+    BorrowFeeSf(u64),
+    FlashLoanFeeSf(u64),
+    /// Kept for the mode numbering; the config has no such field anymore.
+    ReferralFeeBps(u16),
+    DepositLimit(u64),
+    BorrowLimit(u64),
+    LowerHeuristic(u64),
+    UpperHeuristic(u64),
+    ExpHeuristic(u64),
+    MaxTwapDivergenceBps(u64),
+    ScopeTwapChain([u16; 4]),
+    ScopePriceChain([u16; 4]),
+    Name([u8; 32]),
+    MaxAgePriceSeconds(u64),
+    MaxAgeTwapSeconds(u64),
+    ScopePriceFeed(Pubkey),
+    PythPrice(Pubkey),
+    SwitchboardPrice(Pubkey),
+    SwitchboardTwap(Pubkey),
+    /// Too large for `updateSingleReserveConfig`; goes through the entire
+    /// config.
+    BorrowRateCurve(BorrowRateCurve),
+    EntireConfig(Box<ReserveConfig>),
+    DebtWithdrawalCap {
+        capacity: i64,
+        interval_length_seconds: u64,
+    },
+    DepositWithdrawalCap {
+        capacity: i64,
+        interval_length_seconds: u64,
+    },
+    DebtWithdrawalCapCurrentTotal(i64),
+    DepositWithdrawalCapCurrentTotal(i64),
+    BadDebtLiquidationBonusBps(u16),
+    MinLiquidationBonusBps(u16),
+    DeleveragingMarginCallPeriodSecs(u64),
+    BorrowFactorPct(u64),
+    AssetTier(u8),
+    ElevationGroups([u8; 20]),
+    DeleveragingThresholdSlotsPerBps(u64),
+    MultiplierSideBoost([u8; 2]),
+    MultiplierTagBoost([u8; 8]),
+    Status(u8),
+    FarmCollateral(Pubkey),
+    FarmDebt(Pubkey),
+}
+
+impl ReserveConfigUpdate {
+    pub fn mode(&self) -> UpdateConfigMode {
+        use UpdateConfigMode as Mode;
+        match self {
+            Self::LoanToValuePct(_) => Mode::UpdateLoanToValuePct,
+            Self::MaxLiquidationBonusBps(_) => Mode::UpdateMaxLiquidationBonusBps,
+            Self::LiquidationThresholdPct(_) => Mode::UpdateLiquidationThresholdPct,
+            Self::ProtocolLiquidationFeePct(_) => Mode::UpdateProtocolLiquidationFee,
+            Self::ProtocolTakeRatePct(_) => Mode::UpdateProtocolTakeRate,
+            Self::BorrowFeeSf(_) => Mode::UpdateFeesBorrowFee,
+            Self::FlashLoanFeeSf(_) => Mode::UpdateFeesFlashLoanFee,
+            Self::ReferralFeeBps(_) => Mode::UpdateFeesReferralFeeBps,
+            Self::DepositLimit(_) => Mode::UpdateDepositLimit,
+            Self::BorrowLimit(_) => Mode::UpdateBorrowLimit,
+            Self::LowerHeuristic(_) => Mode::UpdateTokenInfoLowerHeuristic,
+            Self::UpperHeuristic(_) => Mode::UpdateTokenInfoUpperHeuristic,
+            Self::ExpHeuristic(_) => Mode::UpdateTokenInfoExpHeuristic,
+            Self::MaxTwapDivergenceBps(_) => Mode::UpdateTokenInfoTwapDivergence,
+            Self::ScopeTwapChain(_) => Mode::UpdateTokenInfoScopeTwap,
+            Self::ScopePriceChain(_) => Mode::UpdateTokenInfoScopeChain,
+            Self::Name(_) => Mode::UpdateTokenInfoName,
+            Self::MaxAgePriceSeconds(_) => Mode::UpdateTokenInfoPriceMaxAge,
+            Self::MaxAgeTwapSeconds(_) => Mode::UpdateTokenInfoTwapMaxAge,
+            Self::ScopePriceFeed(_) => Mode::UpdateScopePriceFeed,
+            Self::PythPrice(_) => Mode::UpdatePythPrice,
+            Self::SwitchboardPrice(_) => Mode::UpdateSwitchboardFeed,
+            Self::SwitchboardTwap(_) => Mode::UpdateSwitchboardTwapFeed,
+            Self::BorrowRateCurve(_) => Mode::UpdateBorrowRateCurve,
+            Self::EntireConfig(_) => Mode::UpdateEntireReserveConfig,
+            Self::DebtWithdrawalCap { .. } => Mode::UpdateDebtWithdrawalCap,
+            Self::DepositWithdrawalCap { .. } => Mode::UpdateDepositWithdrawalCap,
+            Self::DebtWithdrawalCapCurrentTotal(_) => Mode::UpdateDebtWithdrawalCapCurrentTotal,
+            Self::DepositWithdrawalCapCurrentTotal(_) => {
+                Mode::UpdateDepositWithdrawalCapCurrentTotal
+            }
+            Self::BadDebtLiquidationBonusBps(_) => Mode::UpdateBadDebtLiquidationBonusBps,
+            Self::MinLiquidationBonusBps(_) => Mode::UpdateMinLiquidationBonusBps,
+            Self::DeleveragingMarginCallPeriodSecs(_) => Mode::DeleveragingMarginCallPeriod,
+            Self::BorrowFactorPct(_) => Mode::UpdateBorrowFactor,
+            Self::AssetTier(_) => Mode::UpdateAssetTier,
+            Self::ElevationGroups(_) => Mode::UpdateElevationGroup,
+            Self::DeleveragingThresholdSlotsPerBps(_) => Mode::DeleveragingThresholdSlotsPerBps,
+            Self::MultiplierSideBoost(_) => Mode::UpdateMultiplierSideBoost,
+            Self::MultiplierTagBoost(_) => Mode::UpdateMultiplierTagBoost,
+            Self::Status(_) => Mode::UpdateReserveStatus,
+            Self::FarmCollateral(_) => Mode::UpdateFarmCollateral,
+            Self::FarmDebt(_) => Mode::UpdateFarmDebt,
+        }
+    }
+
+    /// The `mode` argument. klend numbers its modes from 1, one more than
+    /// the position of the variant in the IDL.
+    pub fn mode_arg(&self) -> u64 { self.mode() as u64 + 1 }
+
+    /// The unpadded value.
+    pub fn value(&self) -> Result<Vec<u8>, KaminoClientError> {
+        Ok(match self {
+            Self::LoanToValuePct(value)
+            | Self::LiquidationThresholdPct(value)
+            | Self::ProtocolLiquidationFeePct(value)
+            | Self::ProtocolTakeRatePct(value)
+            | Self::AssetTier(value)
+            | Self::Status(value) => vec![*value],
+            Self::MaxLiquidationBonusBps(value)
+            | Self::ReferralFeeBps(value)
+            | Self::BadDebtLiquidationBonusBps(value)
+            | Self::MinLiquidationBonusBps(value) => value.to_le_bytes().to_vec(),
+            Self::BorrowFeeSf(value)
+            | Self::FlashLoanFeeSf(value)
+            | Self::DepositLimit(value)
+            | Self::BorrowLimit(value)
+            | Self::LowerHeuristic(value)
+            | Self::UpperHeuristic(value)
+            | Self::ExpHeuristic(value)
+            | Self::MaxTwapDivergenceBps(value)
+            | Self::MaxAgePriceSeconds(value)
+            | Self::MaxAgeTwapSeconds(value)
+            | Self::DeleveragingMarginCallPeriodSecs(value)
+            | Self::BorrowFactorPct(value)
+            | Self::DeleveragingThresholdSlotsPerBps(value) => value.to_le_bytes().to_vec(),
+            Self::DebtWithdrawalCapCurrentTotal(value)
+            | Self::DepositWithdrawalCapCurrentTotal(value) => value.to_le_bytes().to_vec(),
+            Self::ScopeTwapChain(chain) | Self::ScopePriceChain(chain) => {
+                chain.iter().flat_map(|index| index.to_le_bytes()).collect()
+            }
+            Self::Name(name) => name.to_vec(),
+            Self::ScopePriceFeed(pubkey)
+            | Self::PythPrice(pubkey)
+            | Self::SwitchboardPrice(pubkey)
+            | Self::SwitchboardTwap(pubkey)
+            | Self::FarmCollateral(pubkey)
+            | Self::FarmDebt(pubkey) => pubkey.to_bytes().to_vec(),
+            Self::BorrowRateCurve(curve) => curve.try_to_vec()?,
+            Self::EntireConfig(config) => config.try_to_vec()?,
+            Self::DebtWithdrawalCap { capacity, interval_length_seconds }
+            | Self::DepositWithdrawalCap { capacity, interval_length_seconds } => {
+                [capacity.to_le_bytes(), interval_length_seconds.to_le_bytes()].concat()
+            }
+            Self::ElevationGroups(groups) => groups.to_vec(),
+            Self::MultiplierSideBoost(boost) => boost.to_vec(),
+            Self::MultiplierTagBoost(boost) => boost.to_vec(),
+        })
+    }
+
+    /// The mode and value arguments, the value zero-padded to `N` bytes.
+    /// Fails if the value is longer than `N`.
+    pub fn encode<const N: usize>(&self) -> Result<(u64, [u8; N]), KaminoClientError> {
+        let bytes = self.value()?;
+        let mut value = [0; N];
+        value
+            .get_mut(..bytes.len())
+            .ok_or_else(|| {
+                KaminoClientError::InvalidInput(format!(
+                    "{:?} takes {} bytes, more than {N}",
+                    self.mode(),
+                    bytes.len()
+                ))
+            })?
+            .copy_from_slice(&bytes);
+        Ok((self.mode_arg(), value))
+    }
+}
+
+/// One field that differs between two configs, with the update setting it.
+#[derive(Clone, Debug)]
+pub struct ConfigChange {
+    pub field: &'static str,
+    pub current: String,
+    pub desired: String,
+    pub update: ReserveConfigUpdate,
+}
+
+impl fmt::Display for ConfigChange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {} -> {}", self.field, self.current, self.desired)
+    }
+}
+
+/// The fields that differ between `current` and `desired`, in config order.
+///
+/// The withdrawal caps' `current_total` and `last_interval_start_timestamp`
+/// are tracked by klend and never reported, nor are the reserved fields.
+pub fn diff_reserve_config(
+    current: &ReserveConfig,
+    desired: &ReserveConfig,
+) -> Result<Vec<ConfigChange>, KaminoClientError> {
+    use ReserveConfigUpdate as Update;
+    let mut changes = vec![];
+    let mut push = |field, current: String, desired: String, update| {
+        if current != desired {
+            changes.push(ConfigChange { field, current, desired, update });
+        }
+    };
+    push("status", show(current.status), show(desired.status), Update::Status(desired.status));
+    push(
+        "asset_tier",
+        show(current.asset_tier),
+        show(desired.asset_tier),
+        Update::AssetTier(desired.asset_tier),
+    );
+    push(
+        "multiplier_side_boost",
+        show(current.multiplier_side_boost),
+        show(desired.multiplier_side_boost),
+        Update::MultiplierSideBoost(desired.multiplier_side_boost),
+    );
+    push(
+        "multiplier_tag_boost",
+        show(current.multiplier_tag_boost),
+        show(desired.multiplier_tag_boost),
+        Update::MultiplierTagBoost(desired.multiplier_tag_boost),
+    );
+    push(
+        "protocol_take_rate_pct",
+        show(current.protocol_take_rate_pct),
+        show(desired.protocol_take_rate_pct),
+        Update::ProtocolTakeRatePct(desired.protocol_take_rate_pct),
+    );
+    push(
+        "protocol_liquidation_fee_pct",
+        show(current.protocol_liquidation_fee_pct),
+        show(desired.protocol_liquidation_fee_pct),
+        Update::ProtocolLiquidationFeePct(desired.protocol_liquidation_fee_pct),
+    );
+    push(
+        "loan_to_value_pct",
+        show(current.loan_to_value_pct),
+        show(desired.loan_to_value_pct),
+        Update::LoanToValuePct(desired.loan_to_value_pct),
+    );
+    push(
+        "liquidation_threshold_pct",
+        show(current.liquidation_threshold_pct),
+        show(desired.liquidation_threshold_pct),
+        Update::LiquidationThresholdPct(desired.liquidation_threshold_pct),
+    );
+    push(
+        "min_liquidation_bonus_bps",
+        show(current.min_liquidation_bonus_bps),
+        show(desired.min_liquidation_bonus_bps),
+        Update::MinLiquidationBonusBps(desired.min_liquidation_bonus_bps),
+    );
+    push(
+        "max_liquidation_bonus_bps",
+        show(current.max_liquidation_bonus_bps),
+        show(desired.max_liquidation_bonus_bps),
+        Update::MaxLiquidationBonusBps(desired.max_liquidation_bonus_bps),
+    );
+    push(
+        "bad_debt_liquidation_bonus_bps",
+        show(current.bad_debt_liquidation_bonus_bps),
+        show(desired.bad_debt_liquidation_bonus_bps),
+        Update::BadDebtLiquidationBonusBps(desired.bad_debt_liquidation_bonus_bps),
+    );
+    push(
+        "deleveraging_margin_call_period_secs",
+        show(current.deleveraging_margin_call_period_secs),
+        show(desired.deleveraging_margin_call_period_secs),
+        Update::DeleveragingMarginCallPeriodSecs(desired.deleveraging_margin_call_period_secs),
+    );
+    push(
+        "deleveraging_threshold_slots_per_bps",
+        show(current.deleveraging_threshold_slots_per_bps),
+        show(desired.deleveraging_threshold_slots_per_bps),
+        Update::DeleveragingThresholdSlotsPerBps(desired.deleveraging_threshold_slots_per_bps),
+    );
+    push(
+        "fees.borrow_fee_sf",
+        show_fee(current.fees.borrow_fee_sf),
+        show_fee(desired.fees.borrow_fee_sf),
+        Update::BorrowFeeSf(desired.fees.borrow_fee_sf),
+    );
+    push(
+        "fees.flash_loan_fee_sf",
+        show_flash_loan_fee(current.fees.flash_loan_fee_sf),
+        show_flash_loan_fee(desired.fees.flash_loan_fee_sf),
+        Update::FlashLoanFeeSf(desired.fees.flash_loan_fee_sf),
+    );
+    push(
+        "borrow_rate_curve",
+        show_curve(&current.borrow_rate_curve),
+        show_curve(&desired.borrow_rate_curve),
+        Update::BorrowRateCurve(desired.borrow_rate_curve),
+    );
+    push(
+        "borrow_factor_pct",
+        show(current.borrow_factor_pct),
+        show(desired.borrow_factor_pct),
+        Update::BorrowFactorPct(desired.borrow_factor_pct),
+    );
+    push(
+        "deposit_limit",
+        show(current.deposit_limit),
+        show(desired.deposit_limit),
+        Update::DepositLimit(desired.deposit_limit),
+    );
+    push(
+        "borrow_limit",
+        show(current.borrow_limit),
+        show(desired.borrow_limit),
+        Update::BorrowLimit(desired.borrow_limit),
+    );
+
+    let (current_info, desired_info) = (&current.token_info, &desired.token_info);
+    push(
+        "token_info.name",
+        show_name(&current_info.name),
+        show_name(&desired_info.name),
+        Update::Name(desired_info.name),
+    );
+    push(
+        "token_info.heuristic.lower",
+        show(current_info.heuristic.lower),
+        show(desired_info.heuristic.lower),
+        Update::LowerHeuristic(desired_info.heuristic.lower),
+    );
+    push(
+        "token_info.heuristic.upper",
+        show(current_info.heuristic.upper),
+        show(desired_info.heuristic.upper),
+        Update::UpperHeuristic(desired_info.heuristic.upper),
+    );
+    push(
+        "token_info.heuristic.exp",
+        show(current_info.heuristic.exp),
+        show(desired_info.heuristic.exp),
+        Update::ExpHeuristic(desired_info.heuristic.exp),
+    );
+    push(
+        "token_info.max_twap_divergence_bps",
+        show(current_info.max_twap_divergence_bps),
+        show(desired_info.max_twap_divergence_bps),
+        Update::MaxTwapDivergenceBps(desired_info.max_twap_divergence_bps),
+    );
+    push(
+        "token_info.max_age_price_seconds",
+        show(current_info.max_age_price_seconds),
+        show(desired_info.max_age_price_seconds),
+        Update::MaxAgePriceSeconds(desired_info.max_age_price_seconds),
+    );
+    push(
+        "token_info.max_age_twap_seconds",
+        show(current_info.max_age_twap_seconds),
+        show(desired_info.max_age_twap_seconds),
+        Update::MaxAgeTwapSeconds(desired_info.max_age_twap_seconds),
+    );
+    let (current_scope, desired_scope) =
+        (&current_info.scope_configuration, &desired_info.scope_configuration);
+    push(
+        "token_info.scope_configuration.price_feed",
+        show(current_scope.price_feed),
+        show(desired_scope.price_feed),
+        Update::ScopePriceFeed(desired_scope.price_feed),
+    );
+    push(
+        "token_info.scope_configuration.price_chain",
+        show(current_scope.price_chain),
+        show(desired_scope.price_chain),
+        Update::ScopePriceChain(desired_scope.price_chain),
+    );
+    push(
+        "token_info.scope_configuration.twap_chain",
+        show(current_scope.twap_chain),
+        show(desired_scope.twap_chain),
+        Update::ScopeTwapChain(desired_scope.twap_chain),
+    );
+    let (current_switchboard, desired_switchboard) =
+        (&current_info.switchboard_configuration, &desired_info.switchboard_configuration);
+    push(
+        "token_info.switchboard_configuration.price_aggregator",
+        show(current_switchboard.price_aggregator),
+        show(desired_switchboard.price_aggregator),
+        Update::SwitchboardPrice(desired_switchboard.price_aggregator),
+    );
+    push(
+        "token_info.switchboard_configuration.twap_aggregator",
+        show(current_switchboard.twap_aggregator),
+        show(desired_switchboard.twap_aggregator),
+        Update::SwitchboardTwap(desired_switchboard.twap_aggregator),
+    );
+    push(
+        "token_info.pyth_configuration.price",
+        show(current_info.pyth_configuration.price),
+        show(desired_info.pyth_configuration.price),
+        Update::PythPrice(desired_info.pyth_configuration.price),
+    );
+
+    push(
+        "deposit_withdrawal_cap",
+        show_cap(&current.deposit_withdrawal_cap),
+        show_cap(&desired.deposit_withdrawal_cap),
+        Update::DepositWithdrawalCap {
+            capacity: desired.deposit_withdrawal_cap.config_capacity,
+            interval_length_seconds: desired.deposit_withdrawal_cap.config_interval_length_seconds,
+        },
+    );
+    push(
+        "debt_withdrawal_cap",
+        show_cap(&current.debt_withdrawal_cap),
+        show_cap(&desired.debt_withdrawal_cap),
+        Update::DebtWithdrawalCap {
+            capacity: desired.debt_withdrawal_cap.config_capacity,
+            interval_length_seconds: desired.debt_withdrawal_cap.config_interval_length_seconds,
+        },
+    );
+    push(
+        "elevation_groups",
+        show(current.elevation_groups),
+        show(desired.elevation_groups),
+        Update::ElevationGroups(desired.elevation_groups),
+    );
+    Ok(changes)
+}
+
+/// The updates turning `current` into `desired`: one
+/// `updateSingleReserveConfig` per changed field, or the entire config when
+/// a field does not fit a single update or the single updates would carry
+/// more data.
+///
+/// klend validates the config after every single update, so a threshold or
+/// bonus that is raised goes before the value it bounds.
+pub fn plan_reserve_config_update(
+    current: &ReserveConfig,
+    desired: &ReserveConfig,
+) -> Result<Vec<ReserveConfigUpdate>, KaminoClientError> {
+    let mut updates: Vec<_> =
+        diff_reserve_config(current, desired)?.into_iter().map(|change| change.update).collect();
+    let mut all_fit = true;
+    for update in &updates {
+        all_fit &= update.value()?.len() <= SINGLE_VALUE_LEN;
+    }
+    // Discriminator, mode and value: the bulk of what each instruction adds
+    // to a transaction.
+    let single_len = updates.len() * (8 + 8 + SINGLE_VALUE_LEN);
+    if !all_fit || single_len >= 8 + 8 + ENTIRE_VALUE_LEN {
+        let mut entire = *desired;
+        for (entire, current) in [
+            (&mut entire.deposit_withdrawal_cap, &current.deposit_withdrawal_cap),
+            (&mut entire.debt_withdrawal_cap, &current.debt_withdrawal_cap),
+        ] {
+            entire.current_total = current.current_total;
+            entire.last_interval_start_timestamp = current.last_interval_start_timestamp;
+        }
+        return Ok(vec![ReserveConfigUpdate::EntireConfig(Box::new(entire))]);
+    }
+
+    let position = |updates: &[ReserveConfigUpdate], mode: UpdateConfigMode| {
+        updates.iter().position(|update| update.mode() as u64 == mode as u64)
+    };
+    let raised_first = [
+        (
+            UpdateConfigMode::UpdateLiquidationThresholdPct,
+            UpdateConfigMode::UpdateLoanToValuePct,
+            desired.liquidation_threshold_pct > current.liquidation_threshold_pct,
+        ),
+        (
+            UpdateConfigMode::UpdateMaxLiquidationBonusBps,
+            UpdateConfigMode::UpdateMinLiquidationBonusBps,
+            desired.max_liquidation_bonus_bps > current.max_liquidation_bonus_bps,
+        ),
+    ];
+    for (bound, bounded, raised) in raised_first {
+        if let (Some(bound), Some(bounded)) =
+            (position(&updates, bound), position(&updates, bounded))
+        {
+            if raised == (bound > bounded) {
+                updates.swap(bound, bounded);
+            }
+        }
+    }
+    Ok(updates)
+}
+
+/// A config klend would reject, with the error it would reject it with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConfigError {
+    LoanToValueTooHigh {
+        ltv_pct: u8,
+    },
+    LiquidationThresholdTooHigh {
+        liquidation_threshold_pct: u8,
+    },
+    LoanToValueAboveLiquidationThreshold {
+        ltv_pct: u8,
+        liquidation_threshold_pct: u8,
+    },
+    MaxLiquidationBonusTooHigh {
+        max_bps: u16,
+    },
+    MinLiquidationBonusAboveMax {
+        min_bps: u16,
+        max_bps: u16,
+    },
+    BadDebtLiquidationBonusTooHigh {
+        bad_debt_bps: u16,
+    },
+    ProtocolTakeRateTooHigh {
+        pct: u8,
+    },
+    ProtocolLiquidationFeeTooHigh {
+        pct: u8,
+    },
+    BorrowFactorBelow100 {
+        borrow_factor_pct: u64,
+    },
+    ZeroDeleveragingThreshold,
+    CurveDoesNotStartAtZero {
+        utilization_rate_bps: u32,
+    },
+    /// Utilization must increase until 100%, then stay there.
+    CurveUtilizationNotAscending {
+        index: usize,
+    },
+    CurveRateDecreasing {
+        index: usize,
+    },
+    CurveDoesNotEndAtFull {
+        utilization_rate_bps: u32,
+    },
+    DuplicateElevationGroup {
+        id: u8,
+    },
+    NoOracle,
+    /// A scope feed without a price chain.
+    EmptyScopeChain,
+    /// TWAP divergence is checked but an oracle has no TWAP source.
+    MissingTwap {
+        oracle: &'static str,
+    },
+    NegativeWithdrawalCap {
+        cap: &'static str,
+    },
+    /// A capacity with no interval to reset it.
+    WithdrawalCapWithoutInterval {
+        cap: &'static str,
+    },
+}
+
+impl ConfigError {
+    /// The error klend fails setting this config with.
+    pub fn lending_error(&self) -> LendingError {
+        match self {
+            Self::CurveDoesNotStartAtZero { .. }
+            | Self::CurveUtilizationNotAscending { .. }
+            | Self::CurveRateDecreasing { .. } => LendingError::InvalidBorrowRateCurvePoint,
+            Self::CurveDoesNotEndAtFull { .. } => LendingError::InvalidUtilizationRate,
+            Self::NoOracle | Self::EmptyScopeChain => LendingError::InvalidOracleConfig,
+            Self::MissingTwap { .. } => LendingError::InvalidTwapConfig,
+            _ => LendingError::InvalidConfig,
+        }
+    }
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::LoanToValueTooHigh { ltv_pct } => write!(f, "ltv {ltv_pct}% is not below 100%"),
+            Self::LiquidationThresholdTooHigh { liquidation_threshold_pct } => {
+                write!(f, "liquidation threshold {liquidation_threshold_pct}% is above 100%")
+            }
+            Self::LoanToValueAboveLiquidationThreshold { ltv_pct, liquidation_threshold_pct } => {
+                write!(
+                    f,
+                    "ltv {ltv_pct}% is above the liquidation threshold \
+                     {liquidation_threshold_pct}%"
+                )
+            }
+            Self::MaxLiquidationBonusTooHigh { max_bps } => {
+                write!(f, "max liquidation bonus {max_bps} bps is above 100%")
+            }
+            Self::MinLiquidationBonusAboveMax { min_bps, max_bps } => {
+                write!(f, "min liquidation bonus {min_bps} bps is above the max {max_bps} bps")
+            }
+            Self::BadDebtLiquidationBonusTooHigh { bad_debt_bps } => {
+                write!(f, "bad debt liquidation bonus {bad_debt_bps} bps is not below 1%")
+            }
+            Self::ProtocolTakeRateTooHigh { pct } => {
+                write!(f, "protocol take rate {pct}% is above 100%")
+            }
+            Self::ProtocolLiquidationFeeTooHigh { pct } => {
+                write!(f, "protocol liquidation fee {pct}% is above 100%")
+            }
+            Self::BorrowFactorBelow100 { borrow_factor_pct } => {
+                write!(f, "borrow factor {borrow_factor_pct}% is below 100%")
+            }
+            Self::ZeroDeleveragingThreshold => {
+                write!(f, "deleveraging threshold slots per bps is zero")
+            }
+            Self::CurveDoesNotStartAtZero { utilization_rate_bps } => {
+                write!(f, "borrow rate curve starts at {utilization_rate_bps} bps, not 0")
+            }
+            Self::CurveUtilizationNotAscending { index } => {
+                write!(f, "borrow rate curve point {index} does not increase utilization")
+            }
+            Self::CurveRateDecreasing { index } => {
+                write!(f, "borrow rate curve point {index} decreases the rate")
+            }
+            Self::CurveDoesNotEndAtFull { utilization_rate_bps } => {
+                write!(f, "borrow rate curve ends at {utilization_rate_bps} bps, not 10000")
+            }
+            Self::DuplicateElevationGroup { id } => {
+                write!(f, "elevation group {id} is listed more than once")
+            }
+            Self::NoOracle => write!(f, "no oracle is configured"),
+            Self::EmptyScopeChain => write!(f, "the scope feed has no price chain"),
+            Self::MissingTwap { oracle } => write!(f, "{oracle} has no twap source"),
+            Self::NegativeWithdrawalCap { cap } => write!(f, "{cap} capacity is negative"),
+            Self::WithdrawalCapWithoutInterval { cap } => {
+                write!(f, "{cap} has a capacity but no interval")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// Checks `config` against the invariants klend enforces when it is set,
+/// returning every violation. The withdrawal cap and duplicate elevation
+/// group checks are stricter than klend, which accepts such configs but
+/// never applies them as intended.
+pub fn validate(config: &ReserveConfig) -> Result<(), Vec<ConfigError>> {
+    use ConfigError as E;
+    let mut errors = vec![];
+    if config.loan_to_value_pct >= 100 {
+        errors.push(E::LoanToValueTooHigh { ltv_pct: config.loan_to_value_pct });
+    }
+    if config.liquidation_threshold_pct > 100 {
+        errors.push(E::LiquidationThresholdTooHigh {
+            liquidation_threshold_pct: config.liquidation_threshold_pct,
+        });
+    }
+    if config.loan_to_value_pct > config.liquidation_threshold_pct {
+        errors.push(E::LoanToValueAboveLiquidationThreshold {
+            ltv_pct: config.loan_to_value_pct,
+            liquidation_threshold_pct: config.liquidation_threshold_pct,
+        });
+    }
+    if config.max_liquidation_bonus_bps > FULL_BPS as u16 {
+        errors.push(E::MaxLiquidationBonusTooHigh { max_bps: config.max_liquidation_bonus_bps });
+    }
+    if config.min_liquidation_bonus_bps > config.max_liquidation_bonus_bps {
+        errors.push(E::MinLiquidationBonusAboveMax {
+            min_bps: config.min_liquidation_bonus_bps,
+            max_bps: config.max_liquidation_bonus_bps,
+        });
+    }
+    if config.bad_debt_liquidation_bonus_bps >= 100 {
+        errors.push(E::BadDebtLiquidationBonusTooHigh {
+            bad_debt_bps: config.bad_debt_liquidation_bonus_bps,
+        });
+    }
+    if config.protocol_take_rate_pct > 100 {
+        errors.push(E::ProtocolTakeRateTooHigh { pct: config.protocol_take_rate_pct });
+    }
+    if config.protocol_liquidation_fee_pct > 100 {
+        errors.push(E::ProtocolLiquidationFeeTooHigh { pct: config.protocol_liquidation_fee_pct });
+    }
+    if config.borrow_factor_pct < 100 {
+        errors.push(E::BorrowFactorBelow100 { borrow_factor_pct: config.borrow_factor_pct });
+    }
+    if config.deleveraging_threshold_slots_per_bps == 0 {
+        errors.push(E::ZeroDeleveragingThreshold);
+    }
+
+    let points = &config.borrow_rate_curve.points;
+    if points[0].utilization_rate_bps != 0 {
+        errors.push(E::CurveDoesNotStartAtZero {
+            utilization_rate_bps: points[0].utilization_rate_bps,
+        });
+    }
+    for (index, pair) in points.windows(2).enumerate() {
+        let (last, point) = (pair[0], pair[1]);
+        // Once at 100% the curve is padded by repeating the last point.
+        let ascending = if last.utilization_rate_bps == FULL_BPS {
+            point.utilization_rate_bps == FULL_BPS
+        } else {
+            point.utilization_rate_bps > last.utilization_rate_bps
+        };
+        if !ascending {
+            errors.push(E::CurveUtilizationNotAscending { index: index + 1 });
+        }
+        if point.borrow_rate_bps < last.borrow_rate_bps {
+            errors.push(E::CurveRateDecreasing { index: index + 1 });
+        }
+    }
+    let last = points[points.len() - 1].utilization_rate_bps;
+    if last != FULL_BPS {
+        errors.push(E::CurveDoesNotEndAtFull { utilization_rate_bps: last });
+    }
+
+    for (i, &id) in config.elevation_groups.iter().enumerate() {
+        if id != 0 && config.elevation_groups[..i].contains(&id) {
+            errors.push(E::DuplicateElevationGroup { id });
+        }
+    }
+
+    let token_info = &config.token_info;
+    let oracles = ReserveOracles::from_token_info(token_info);
+    if oracles.is_empty() {
+        errors.push(E::NoOracle);
+    }
+    let scope = &token_info.scope_configuration;
+    if oracles.scope_prices.is_some() && scope.price_chain[0] == u16::MAX {
+        errors.push(E::EmptyScopeChain);
+    }
+    if token_info.max_twap_divergence_bps > 0 {
+        if oracles.switchboard_price.is_some() && oracles.switchboard_twap.is_none() {
+            errors.push(E::MissingTwap { oracle: "switchboard" });
+        }
+        if oracles.scope_prices.is_some() && scope.twap_chain[0] == u16::MAX {
+            errors.push(E::MissingTwap { oracle: "scope" });
+        }
+    }
+
+    for (cap, caps) in [
+        ("deposit_withdrawal_cap", &config.deposit_withdrawal_cap),
+        ("debt_withdrawal_cap", &config.debt_withdrawal_cap),
+    ] {
+        if caps.config_capacity < 0 {
+            errors.push(E::NegativeWithdrawalCap { cap });
+        }
+        if caps.config_capacity > 0 && caps.config_interval_length_seconds == 0 {
+            errors.push(E::WithdrawalCapWithoutInterval { cap });
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+fn show(value: impl fmt::Debug) -> String { format!("{value:?}") }
+
+fn show_fee(fee_sf: u64) -> String {
+    let fee = Fraction::from_bits(fee_sf.into()).to_decimal() * Decimal::ONE_HUNDRED;
+    format!("{}%", fee.normalize())
+}
+
+fn show_flash_loan_fee(fee_sf: u64) -> String {
+    if fee_sf == FLASH_LOANS_DISABLED {
+        return "disabled".to_string();
+    }
+    show_fee(fee_sf)
+}
+
+fn show_name(name: &[u8; 32]) -> String {
+    format!("{:?}", String::from_utf8_lossy(name).trim_end_matches('\0'))
+}
+
+fn show_curve(curve: &BorrowRateCurve) -> String {
+    let points: Vec<_> = curve
+        .points
+        .iter()
+        .map(|point| format!("{}:{}", point.utilization_rate_bps, point.borrow_rate_bps))
+        .collect();
+    format!("[{}]", points.join(", "))
+}
+
+fn show_cap(cap: &WithdrawalCaps) -> String {
+    format!("{} per {}s", cap.config_capacity, cap.config_interval_length_seconds)
+}
+
+#[cfg(test)]
+mod tests {
+    use kamino_lend::typedefs::CurvePoint;
+
+    use super::*;
+    use crate::interest::borrow_rate_curve;
+
+    #[test]
+    fn test_encode() {
+        let (mode, value) =
+            ReserveConfigUpdate::LoanToValuePct(75).encode::<SINGLE_VALUE_LEN>().unwrap();
+        assert_eq!((mode, value[0]), (1, 75));
+        assert_eq!(value[1..], [0; 31]);
+
+        let update =
+            ReserveConfigUpdate::DepositWithdrawalCap { capacity: 5, interval_length_seconds: 60 };
+        let (mode, value) = update.encode::<SINGLE_VALUE_LEN>().unwrap();
+        assert_eq!(mode, 27);
+        assert_eq!(value[..16], [[5, 0, 0, 0, 0, 0, 0, 0], [60, 0, 0, 0, 0, 0, 0, 0]].concat());
+
+        let (mode, value) =
+            ReserveConfigUpdate::ScopePriceChain([1, 2, u16::MAX, u16::MAX]).encode::<8>().unwrap();
+        assert_eq!((mode, value), (16, [1, 0, 2, 0, 255, 255, 255, 255]));
+
+        let (mode, _) = ReserveConfigUpdate::FarmDebt(Pubkey::new_unique())
+            .encode::<SINGLE_VALUE_LEN>()
+            .unwrap();
+        assert_eq!(mode, 41);
+    }
+
+    #[test]
+    fn test_encode_too_long() {
+        let curve = ReserveConfigUpdate::BorrowRateCurve(BorrowRateCurve::default());
+        assert!(curve.encode::<SINGLE_VALUE_LEN>().is_err());
+
+        let config = ReserveConfigUpdate::EntireConfig(Box::default());
+        assert!(config.encode::<SINGLE_VALUE_LEN>().is_err());
+        let (mode, _) = config.encode::<ENTIRE_VALUE_LEN>().unwrap();
+        assert_eq!(mode, 25);
+    }
+
+    fn config() -> ReserveConfig {
+        ReserveConfig {
+            loan_to_value_pct: 75,
+            liquidation_threshold_pct: 85,
+            min_liquidation_bonus_bps: 200,
+            max_liquidation_bonus_bps: 500,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_diff_reserve_config() {
+        let current = config();
+        assert!(diff_reserve_config(&current, &current).unwrap().is_empty());
+
+        let mut desired = current;
+        desired.loan_to_value_pct = 70;
+        desired.token_info.name = *b"SOL\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0";
+        desired.deposit_withdrawal_cap.current_total = 10;
+        desired.fees.borrow_fee_sf = Fraction::from_bps(30u16).to_bits() as u64;
+        desired.fees.flash_loan_fee_sf = FLASH_LOANS_DISABLED;
+        let report: Vec<_> = diff_reserve_config(&current, &desired)
+            .unwrap()
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(
+            report,
+            [
+                "loan_to_value_pct: 75 -> 70",
+                "fees.borrow_fee_sf: 0% -> 0.3%",
+                "fees.flash_loan_fee_sf: 0% -> disabled",
+                r#"token_info.name: "" -> "SOL""#,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_plan_reserve_config_update() {
+        let current = config();
+        let mut desired = current;
+        desired.loan_to_value_pct = 80;
+        desired.liquidation_threshold_pct = 90;
+        desired.min_liquidation_bonus_bps = 100;
+        desired.max_liquidation_bonus_bps = 150;
+        let modes: Vec<_> = plan_reserve_config_update(&current, &desired)
+            .unwrap()
+            .iter()
+            .map(ReserveConfigUpdate::mode_arg)
+            .collect();
+        let expected = [
+            UpdateConfigMode::UpdateLiquidationThresholdPct,
+            UpdateConfigMode::UpdateLoanToValuePct,
+            UpdateConfigMode::UpdateMinLiquidationBonusBps,
+            UpdateConfigMode::UpdateMaxLiquidationBonusBps,
+        ]
+        .map(|mode| mode as u64 + 1);
+        assert_eq!(modes, expected);
+
+        desired.borrow_rate_curve.points[0].borrow_rate_bps = 1;
+        desired.debt_withdrawal_cap.current_total = 10;
+        let plan = plan_reserve_config_update(&current, &desired).unwrap();
+        let [ReserveConfigUpdate::EntireConfig(entire)] = plan.as_slice() else {
+            panic!("expected the entire config, got {plan:?}");
+        };
+        assert_eq!(entire.borrow_rate_curve.points[0].borrow_rate_bps, 1);
+        assert_eq!(entire.debt_withdrawal_cap.current_total, 0);
+    }
+
+    #[test]
+    fn test_validate() {
+        let mut config = ReserveConfig {
+            borrow_factor_pct: 100,
+            deleveraging_threshold_slots_per_bps: 7200,
+            borrow_rate_curve: borrow_rate_curve(&[
+                CurvePoint { utilization_rate_bps: 0, borrow_rate_bps: 1 },
+                CurvePoint { utilization_rate_bps: 10_000, borrow_rate_bps: 100 },
+            ]),
+            ..config()
+        };
+        config.token_info.pyth_configuration.price = Pubkey::new_unique();
+        assert_eq!(validate(&config), Ok(()));
+
+        config.loan_to_value_pct = 90;
+        config.borrow_rate_curve.points[2].utilization_rate_bps = 9_000;
+        config.elevation_groups[..2].copy_from_slice(&[1, 1]);
+        config.token_info.pyth_configuration.price = Pubkey::default();
+        config.debt_withdrawal_cap.config_capacity = 10;
+        let errors = validate(&config).unwrap_err();
+        assert_eq!(
+            errors,
+            [
+                ConfigError::LoanToValueAboveLiquidationThreshold {
+                    ltv_pct: 90,
+                    liquidation_threshold_pct: 85
+                },
+                ConfigError::CurveUtilizationNotAscending { index: 2 },
+                ConfigError::DuplicateElevationGroup { id: 1 },
+                ConfigError::NoOracle,
+                ConfigError::WithdrawalCapWithoutInterval { cap: "debt_withdrawal_cap" },
+            ]
+        );
+        let lending_errors: Vec<_> = errors.iter().map(ConfigError::lending_error).collect();
+        assert_eq!(lending_errors[1], LendingError::InvalidBorrowRateCurvePoint);
+        assert_eq!(lending_errors[3], LendingError::InvalidOracleConfig);
+    }
+}
@@ -0,0 +1,100 @@
+//! Blocking RPC calls reporting failures as [`KaminoClientError`], with the
+//! failing instruction's program error decoded.
+
+use std::collections::HashMap;
+
+use anchor_lang::{prelude::Pubkey, AccountDeserialize};
+use kamino_lend::state::Reserve;
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::{
+    account::{from_account, Account},
+    clock::Clock,
+    instruction::Instruction,
+    signature::{Keypair, Signature},
+    sysvar,
+    transaction::Transaction,
+};
+use spl_token_2022::extension::StateWithExtensions;
+
+use crate::{
+    error::{check_transaction_size, KaminoClientError},
+    token::MintInfo,
+};
+
+pub fn get_account(client: &RpcClient, address: &Pubkey) -> Result<Account, KaminoClientError> {
+    client
+        .get_account_with_commitment(address, client.commitment())?
+        .value
+        .ok_or(KaminoClientError::AccountNotFound(*address))
+}
+
+pub fn get_anchor_account<T: AccountDeserialize>(
+    client: &RpcClient,
+    address: &Pubkey,
+) -> Result<T, KaminoClientError> {
+    let account = get_account(client, address)?;
+    T::try_deserialize(&mut account.data.as_slice())
+        .map_err(|error| KaminoClientError::Deserialize(format!("account {address}: {error}")))
+}
+
+/// Fetches and decodes the reserves at `addresses`.
+pub fn get_reserves(
+    client: &RpcClient,
+    addresses: &[Pubkey],
+) -> Result<HashMap<Pubkey, Reserve>, KaminoClientError> {
+    let accounts = client.get_multiple_accounts_with_commitment(addresses, client.commitment())?;
+    addresses
+        .iter()
+        .zip(accounts.value)
+        .map(|(address, account)| {
+            let account = account.ok_or(KaminoClientError::AccountNotFound(*address))?;
+            let reserve =
+                Reserve::try_deserialize(&mut account.data.as_slice()).map_err(|error| {
+                    KaminoClientError::Deserialize(format!("account {address}: {error}"))
+                })?;
+            Ok((*address, reserve))
+        })
+        .collect()
+}
+
+/// The cluster's clock, as instructions sent now would read it.
+pub fn get_clock(client: &RpcClient) -> Result<Clock, KaminoClientError> {
+    from_account(&get_account(client, &sysvar::clock::ID)?)
+        .ok_or_else(|| KaminoClientError::Deserialize("clock sysvar".to_string()))
+}
+
+/// Fetches a mint of either token program.
+pub fn get_mint(client: &RpcClient, address: &Pubkey) -> Result<MintInfo, KaminoClientError> {
+    MintInfo::unpack(address, &get_account(client, address)?)
+}
+
+/// Balance of a token account of either token program.
+pub fn get_token_balance(
+    client: &RpcClient,
+    token_account: &Pubkey,
+) -> Result<u64, KaminoClientError> {
+    let account = get_account(client, token_account)?;
+    StateWithExtensions::<spl_token_2022::state::Account>::unpack(&account.data)
+        .map(|state| state.base.amount)
+        .map_err(|error| {
+            KaminoClientError::Deserialize(format!("account {token_account}: {error}"))
+        })
+}
+
+/// Sends `instructions` in one transaction paid by `payer`, which must be one
+/// of `signers`, and waits for its confirmation.
+pub fn send_transaction(
+    client: &RpcClient,
+    payer: &Pubkey,
+    instructions: &[Instruction],
+    signers: &[&Keypair],
+) -> Result<Signature, KaminoClientError> {
+    let mut transaction = Transaction::new_with_payer(instructions, Some(payer));
+    transaction
+        .try_sign(&signers.to_vec(), client.get_latest_blockhash()?)
+        .map_err(|error| KaminoClientError::InvalidInput(error.to_string()))?;
+    check_transaction_size(&transaction)?;
+    client
+        .send_and_confirm_transaction(&transaction)
+        .map_err(|error| KaminoClientError::from_client_error(error, instructions))
+}
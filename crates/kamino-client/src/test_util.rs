@@ -0,0 +1,5 @@
+use anchor_lang::AnchorDeserialize;
+
+/// Decodes an all-zero account, standing in for `Default` on generated
+/// accounts whose arrays are too long to derive it.
+pub fn zeroed<T: AnchorDeserialize>() -> T { T::deserialize(&mut &[0u8; 10_240][..]).unwrap() }
@@ -0,0 +1,346 @@
+//! Mints and token accounts of either token program.
+//!
+//! Token-2022 reserves are not supported: the klend program in
+//! `deps/programs` takes its liquidity mint as an Anchor `token::Mint`, which
+//! must be owned by SPL Token, so `initReserve` rejects a Token-2022 mint with
+//! `AccountOwnedByWrongProgram` (3007). Listing one needs a klend build with
+//! Token-2022 support. Until then [`check_reserve_mint`] reports it before
+//! sending, together with the extensions klend's unchecked vault transfers
+//! cannot move.
+
+use std::fmt;
+
+use anchor_lang::prelude::Pubkey;
+use solana_sdk::{
+    account::Account, instruction::Instruction, program_pack::Pack, rent::Rent, system_instruction,
+};
+use spl_associated_token_account::get_associated_token_address;
+use spl_token::native_mint;
+use spl_token_2022::{
+    extension::{
+        transfer_fee::TransferFeeConfig, BaseState, BaseStateWithExtensions, ExtensionType,
+        StateWithExtensions,
+    },
+    state::{Account as TokenAccount, Mint},
+};
+
+use crate::error::KaminoClientError;
+
+/// The token program of every reserve's liquidity. The bundled klend only
+/// takes SPL Token mints, as the module docs explain; supporting Token-2022
+/// reserves starts here.
+pub const KLEND_TOKEN_PROGRAM: Pubkey = spl_token::ID;
+
+pub fn is_token_program(program_id: &Pubkey) -> bool {
+    *program_id == spl_token::ID || *program_id == spl_token_2022::ID
+}
+
+/// Size of a mint with `extensions`, which SPL Token mints cannot have.
+pub fn mint_len(
+    token_program: &Pubkey,
+    extensions: &[ExtensionType],
+) -> Result<usize, KaminoClientError> {
+    account_len::<Mint>(token_program, extensions)
+}
+
+/// Size of a token account of a mint with `mint_extensions`, including the
+/// account extensions those require.
+pub fn token_account_len(
+    token_program: &Pubkey,
+    mint_extensions: &[ExtensionType],
+) -> Result<usize, KaminoClientError> {
+    let extensions = ExtensionType::get_required_init_account_extensions(mint_extensions);
+    account_len::<TokenAccount>(token_program, &extensions)
+}
+
+fn account_len<S: BaseState + Pack>(
+    token_program: &Pubkey,
+    extensions: &[ExtensionType],
+) -> Result<usize, KaminoClientError> {
+    if *token_program == spl_token::ID && extensions.is_empty() {
+        return Ok(S::LEN);
+    }
+    if *token_program != spl_token_2022::ID {
+        return Err(KaminoClientError::InvalidInput(format!(
+            "{token_program} cannot hold {extensions:?}"
+        )));
+    }
+    ExtensionType::try_calculate_account_len::<S>(extensions)
+        .map_err(|error| KaminoClientError::InvalidInput(error.to_string()))
+}
+
+/// Creates and initializes `mint`, which signs, with rent paid by `payer`.
+pub fn create_mint(
+    payer: &Pubkey,
+    mint: &Pubkey,
+    mint_authority: &Pubkey,
+    freeze_authority: Option<&Pubkey>,
+    decimals: u8,
+    token_program: &Pubkey,
+) -> Result<Vec<Instruction>, KaminoClientError> {
+    let space = mint_len(token_program, &[])?;
+    Ok(vec![
+        system_instruction::create_account(
+            payer,
+            mint,
+            Rent::default().minimum_balance(space),
+            space as u64,
+            token_program,
+        ),
+        spl_token_2022::instruction::initialize_mint(
+            token_program,
+            mint,
+            mint_authority,
+            freeze_authority,
+            decimals,
+        )
+        .map_err(|error| KaminoClientError::InvalidInput(error.to_string()))?,
+    ])
+}
+
+/// Creates the associated token account of `owner` unless it exists.
+pub fn create_associated_token_account(
+    payer: &Pubkey,
+    owner: &Pubkey,
+    mint: &Pubkey,
+    token_program: &Pubkey,
+) -> Instruction {
+    spl_associated_token_account::instruction::create_associated_token_account_idempotent(
+        payer,
+        owner,
+        mint,
+        token_program,
+    )
+}
+
+/// Moves `lamports` of `payer` into the wrapped SOL account of `owner`,
+/// creating it if needed.
+pub fn wrap_sol(
+    payer: &Pubkey,
+    owner: &Pubkey,
+    lamports: u64,
+) -> Result<Vec<Instruction>, KaminoClientError> {
+    let account = get_associated_token_address(owner, &native_mint::ID);
+    Ok(vec![
+        create_associated_token_account(payer, owner, &native_mint::ID, &spl_token::ID),
+        system_instruction::transfer(payer, &account, lamports),
+        spl_token::instruction::sync_native(&spl_token::ID, &account)
+            .map_err(|error| KaminoClientError::InvalidInput(error.to_string()))?,
+    ])
+}
+
+/// Closes the wrapped SOL account of `owner`, who signs, returning all its
+/// lamports to them.
+pub fn unwrap_sol(owner: &Pubkey) -> Result<Instruction, KaminoClientError> {
+    let account = get_associated_token_address(owner, &native_mint::ID);
+    spl_token::instruction::close_account(&spl_token::ID, &account, owner, owner, &[])
+        .map_err(|error| KaminoClientError::InvalidInput(error.to_string()))
+}
+
+/// `transferChecked`, which Token-2022 requires for mints with a transfer
+/// fee or hook and which works for any mint.
+pub fn transfer_checked(
+    source: &Pubkey,
+    mint: &MintInfo,
+    destination: &Pubkey,
+    authority: &Pubkey,
+    amount: u64,
+) -> Result<Instruction, KaminoClientError> {
+    spl_token_2022::instruction::transfer_checked(
+        &mint.token_program,
+        source,
+        &mint.address,
+        destination,
+        authority,
+        &[],
+        amount,
+        mint.decimals,
+    )
+    .map_err(|error| KaminoClientError::InvalidInput(error.to_string()))
+}
+
+/// A decoded mint of either token program.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MintInfo {
+    pub address: Pubkey,
+    pub token_program: Pubkey,
+    pub decimals: u8,
+    pub supply: u64,
+    pub mint_authority: Option<Pubkey>,
+    pub freeze_authority: Option<Pubkey>,
+    pub extensions: Vec<ExtensionType>,
+    /// Larger of the current and scheduled transfer fee, in bps.
+    pub transfer_fee_bps: Option<u16>,
+}
+
+impl MintInfo {
+    pub fn unpack(address: &Pubkey, account: &Account) -> Result<Self, KaminoClientError> {
+        let not_a_mint = |error| KaminoClientError::Deserialize(format!("mint {address}: {error}"));
+        if !is_token_program(&account.owner) {
+            return Err(not_a_mint(format!("owned by {}", account.owner)));
+        }
+        let mint = StateWithExtensions::<Mint>::unpack(&account.data)
+            .map_err(|error| not_a_mint(error.to_string()))?;
+        let extensions =
+            mint.get_extension_types().map_err(|error| not_a_mint(error.to_string()))?;
+        let transfer_fee_bps = mint.get_extension::<TransferFeeConfig>().ok().map(|config| {
+            u16::from(config.older_transfer_fee.transfer_fee_basis_points)
+                .max(config.newer_transfer_fee.transfer_fee_basis_points.into())
+        });
+        Ok(Self {
+            address: *address,
+            token_program: account.owner,
+            decimals: mint.base.decimals,
+            supply: mint.base.supply,
+            mint_authority: mint.base.mint_authority.into(),
+            freeze_authority: mint.base.freeze_authority.into(),
+            extensions,
+            transfer_fee_bps,
+        })
+    }
+
+    /// Associated token account of `owner`, derived for the mint's program.
+    pub fn associated_token_address(&self, owner: &Pubkey) -> Pubkey {
+        spl_associated_token_account::get_associated_token_address_with_program_id(
+            owner,
+            &self.address,
+            &self.token_program,
+        )
+    }
+
+    /// What keeps klend from taking the mint as reserve liquidity.
+    pub fn unsupported_by_klend(&self) -> Vec<UnsupportedMint> {
+        let mut reasons = vec![];
+        if self.token_program != spl_token::ID {
+            reasons.push(UnsupportedMint::TokenProgram(self.token_program));
+        }
+        if let Some(bps) = self.transfer_fee_bps {
+            reasons.push(UnsupportedMint::TransferFee { bps });
+        }
+        reasons.extend(
+            self.extensions
+                .iter()
+                .filter(|extension| UNSUPPORTED_EXTENSIONS.contains(extension))
+                .map(|extension| UnsupportedMint::Extension(*extension)),
+        );
+        reasons
+    }
+}
+
+/// Mint extensions under which vault transfers fail without the mint, or
+/// which let a third party freeze or move the vaults' tokens.
+const UNSUPPORTED_EXTENSIONS: &[ExtensionType] = &[
+    ExtensionType::TransferHook,
+    ExtensionType::NonTransferable,
+    ExtensionType::PermanentDelegate,
+    ExtensionType::DefaultAccountState,
+];
+
+/// Why klend cannot list a mint.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum UnsupportedMint {
+    /// klend only takes SPL Token mints.
+    TokenProgram(Pubkey),
+    /// Token-2022 rejects transfers of a mint with a transfer fee, even a
+    /// zero one, unless they are checked.
+    TransferFee {
+        bps: u16,
+    },
+    Extension(ExtensionType),
+}
+
+impl fmt::Display for UnsupportedMint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::TokenProgram(program) => write!(f, "owned by {program}, not SPL Token"),
+            Self::TransferFee { bps } => write!(f, "transfer fee of {bps} bps"),
+            Self::Extension(extension) => write!(f, "{extension:?} extension"),
+        }
+    }
+}
+
+/// Decodes `account` as a mint klend can take as reserve liquidity, which
+/// with the bundled klend excludes every Token-2022 mint.
+pub fn check_reserve_mint(
+    address: &Pubkey,
+    account: &Account,
+) -> Result<MintInfo, KaminoClientError> {
+    let mint = MintInfo::unpack(address, account)?;
+    let reasons = mint.unsupported_by_klend();
+    if !reasons.is_empty() {
+        return Err(KaminoClientError::UnsupportedMint { mint: *address, reasons });
+    }
+    Ok(mint)
+}
+
+#[cfg(test)]
+mod tests {
+    use spl_token_2022::extension::{
+        transfer_fee::TransferFee, BaseStateWithExtensionsMut, StateWithExtensionsMut,
+    };
+
+    use super::*;
+
+    fn mint_account(token_program: Pubkey, extensions: &[ExtensionType], bps: u16) -> Account {
+        let mut data = vec![0; mint_len(&token_program, extensions).unwrap()];
+        let mut mint = StateWithExtensionsMut::<Mint>::unpack_uninitialized(&mut data).unwrap();
+        if extensions.contains(&ExtensionType::TransferFeeConfig) {
+            let config = mint.init_extension::<TransferFeeConfig>(true).unwrap();
+            config.newer_transfer_fee =
+                TransferFee { transfer_fee_basis_points: bps.into(), ..Default::default() };
+        }
+        mint.base = Mint { decimals: 9, supply: 5, is_initialized: true, ..Default::default() };
+        mint.pack_base();
+        if !extensions.is_empty() {
+            mint.init_account_type().unwrap();
+        }
+        Account { owner: token_program, data, ..Default::default() }
+    }
+
+    #[test]
+    fn test_account_len() {
+        assert_eq!(mint_len(&spl_token::ID, &[]).unwrap(), Mint::LEN);
+        assert_eq!(token_account_len(&spl_token_2022::ID, &[]).unwrap(), TokenAccount::LEN);
+        let fee = [ExtensionType::TransferFeeConfig];
+        assert!(mint_len(&spl_token::ID, &fee).is_err());
+        assert!(mint_len(&spl_token_2022::ID, &fee).unwrap() > Mint::LEN);
+        assert!(
+            token_account_len(&spl_token_2022::ID, &fee).unwrap()
+                > token_account_len(&spl_token_2022::ID, &[]).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_check_reserve_mint() {
+        let address = Pubkey::new_unique();
+        let mint = check_reserve_mint(&address, &mint_account(spl_token::ID, &[], 0)).unwrap();
+        assert_eq!((mint.decimals, mint.supply, mint.transfer_fee_bps), (9, 5, None));
+        assert_eq!(
+            mint.associated_token_address(&address),
+            spl_associated_token_account::get_associated_token_address(&address, &address)
+        );
+
+        let account = mint_account(spl_token_2022::ID, &[], 0);
+        assert_eq!(
+            MintInfo::unpack(&address, &account).unwrap().unsupported_by_klend(),
+            [UnsupportedMint::TokenProgram(spl_token_2022::ID)]
+        );
+
+        let account = mint_account(spl_token_2022::ID, &[ExtensionType::TransferFeeConfig], 25);
+        let error = check_reserve_mint(&address, &account).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "mint {address} cannot be listed on klend: owned by {}, not SPL Token, transfer \
+                 fee of 25 bps",
+                spl_token_2022::ID
+            )
+        );
+
+        let account = Account { owner: Pubkey::new_unique(), ..Default::default() };
+        assert!(matches!(
+            MintInfo::unpack(&address, &account),
+            Err(KaminoClientError::Deserialize(_))
+        ));
+    }
+}
@@ -0,0 +1,79 @@
+//! Typed views of the `_sf` and `_bsf` fields of the generated accounts.
+
+use crate::{
+    fraction::{BigFraction, Fraction},
+    state::{Obligation, Reserve},
+    typedefs::{ObligationCollateral, ObligationLiquidity, ReserveFees},
+};
+
+impl Reserve {
+    pub fn market_price(&self) -> Fraction { Fraction::from_bits(self.liquidity.market_price_sf) }
+
+    pub fn borrowed_amount(&self) -> Fraction {
+        Fraction::from_bits(self.liquidity.borrowed_amount_sf)
+    }
+
+    pub fn cumulative_borrow_rate(&self) -> BigFraction {
+        BigFraction::from(&self.liquidity.cumulative_borrow_rate_bsf)
+    }
+
+    pub fn accumulated_protocol_fees(&self) -> Fraction {
+        Fraction::from_bits(self.liquidity.accumulated_protocol_fees_sf)
+    }
+
+    pub fn accumulated_referrer_fees(&self) -> Fraction {
+        Fraction::from_bits(self.liquidity.accumulated_referrer_fees_sf)
+    }
+
+    pub fn pending_referrer_fees(&self) -> Fraction {
+        Fraction::from_bits(self.liquidity.pending_referrer_fees_sf)
+    }
+
+    pub fn absolute_referral_rate(&self) -> Fraction {
+        Fraction::from_bits(self.liquidity.absolute_referral_rate_sf)
+    }
+}
+
+impl ReserveFees {
+    pub fn borrow_fee(&self) -> Fraction { Fraction::from_bits(self.borrow_fee_sf.into()) }
+
+    pub fn flash_loan_fee(&self) -> Fraction { Fraction::from_bits(self.flash_loan_fee_sf.into()) }
+}
+
+impl Obligation {
+    pub fn deposited_value(&self) -> Fraction { Fraction::from_bits(self.deposited_value_sf) }
+
+    pub fn borrow_factor_adjusted_debt_value(&self) -> Fraction {
+        Fraction::from_bits(self.borrow_factor_adjusted_debt_value_sf)
+    }
+
+    pub fn borrowed_assets_market_value(&self) -> Fraction {
+        Fraction::from_bits(self.borrowed_assets_market_value_sf)
+    }
+
+    pub fn allowed_borrow_value(&self) -> Fraction {
+        Fraction::from_bits(self.allowed_borrow_value_sf)
+    }
+
+    pub fn unhealthy_borrow_value(&self) -> Fraction {
+        Fraction::from_bits(self.unhealthy_borrow_value_sf)
+    }
+}
+
+impl ObligationCollateral {
+    pub fn market_value(&self) -> Fraction { Fraction::from_bits(self.market_value_sf) }
+}
+
+impl ObligationLiquidity {
+    pub fn cumulative_borrow_rate(&self) -> BigFraction {
+        BigFraction::from(&self.cumulative_borrow_rate_bsf)
+    }
+
+    pub fn borrowed_amount(&self) -> Fraction { Fraction::from_bits(self.borrowed_amount_sf) }
+
+    pub fn market_value(&self) -> Fraction { Fraction::from_bits(self.market_value_sf) }
+
+    pub fn borrow_factor_adjusted_market_value(&self) -> Fraction {
+        Fraction::from_bits(self.borrow_factor_adjusted_market_value_sf)
+    }
+}
@@ -0,0 +1,374 @@
+//! klend's custom error codes, from the `errors` list of the IDL, which
+//! anchor-gen does not generate.
+
+use std::fmt;
+
+use num_derive::FromPrimitive;
+use num_traits::FromPrimitive;
+
+/// A klend error, numbered by its custom program error code.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, FromPrimitive)]
+#[repr(u32)]
+pub enum LendingError {
+    InvalidMarketAuthority = 6000,
+    InvalidMarketOwner = 6001,
+    InvalidAccountOwner = 6002,
+    InvalidAmount = 6003,
+    InvalidConfig = 6004,
+    InvalidSigner = 6005,
+    InvalidAccountInput = 6006,
+    MathOverflow = 6007,
+    InsufficientLiquidity = 6008,
+    ReserveStale = 6009,
+    WithdrawTooSmall = 6010,
+    WithdrawTooLarge = 6011,
+    BorrowTooSmall = 6012,
+    BorrowTooLarge = 6013,
+    RepayTooSmall = 6014,
+    LiquidationTooSmall = 6015,
+    ObligationHealthy = 6016,
+    ObligationStale = 6017,
+    ObligationReserveLimit = 6018,
+    InvalidObligationOwner = 6019,
+    ObligationDepositsEmpty = 6020,
+    ObligationBorrowsEmpty = 6021,
+    ObligationDepositsZero = 6022,
+    ObligationBorrowsZero = 6023,
+    InvalidObligationCollateral = 6024,
+    InvalidObligationLiquidity = 6025,
+    ObligationCollateralEmpty = 6026,
+    ObligationLiquidityEmpty = 6027,
+    NegativeInterestRate = 6028,
+    InvalidOracleConfig = 6029,
+    InsufficientProtocolFeesToRedeem = 6030,
+    FlashBorrowCpi = 6031,
+    NoFlashRepayFound = 6032,
+    InvalidFlashRepay = 6033,
+    FlashRepayCpi = 6034,
+    MultipleFlashBorrows = 6035,
+    FlashLoansDisabled = 6036,
+    SwitchboardV2Error = 6037,
+    CouldNotDeserializeScope = 6038,
+    PriceTooOld = 6039,
+    PriceTooDivergentFromTwap = 6040,
+    InvalidTwapPrice = 6041,
+    GlobalEmergencyMode = 6042,
+    InvalidFlag = 6043,
+    PriceNotValid = 6044,
+    PriceIsBiggerThanHeuristic = 6045,
+    PriceIsLowerThanHeuristic = 6046,
+    PriceIsZero = 6047,
+    PriceConfidenceTooWide = 6048,
+    IntegerOverflow = 6049,
+    NoFarmForReserve = 6050,
+    IncorrectInstructionInPosition = 6051,
+    NoPriceFound = 6052,
+    InvalidTwapConfig = 6053,
+    InvalidPythPriceAccount = 6054,
+    InvalidSwitchboardAccount = 6055,
+    InvalidScopePriceAccount = 6056,
+    ObligationCollateralLtvZero = 6057,
+    InvalidObligationSeedsValue = 6058,
+    InvalidObligationId = 6059,
+    InvalidBorrowRateCurvePoint = 6060,
+    InvalidUtilizationRate = 6061,
+    CannotSocializeObligationWithCollateral = 6062,
+    ObligationEmpty = 6063,
+    WithdrawalCapReached = 6064,
+    LastTimestampGreaterThanCurrent = 6065,
+    LiquidationSlippageError = 6066,
+    IsolatedAssetTierViolation = 6067,
+    InconsistentElevationGroup = 6068,
+    InvalidElevationGroup = 6069,
+    InvalidElevationGroupConfig = 6070,
+    UnhealthyElevationGroupLtv = 6071,
+    ElevationGroupNewLoansDisabled = 6072,
+    ReserveDeprecated = 6073,
+    ReferrerAccountNotInitialized = 6074,
+    ReferrerAccountMintMissmatch = 6075,
+    ReferrerAccountWrongAddress = 6076,
+    ReferrerAccountReferrerMissmatch = 6077,
+    ReferrerAccountMissing = 6078,
+    InsufficientReferralFeesToRedeem = 6079,
+    CpiDisabled = 6080,
+    ShortUrlNotAsciiAlphanumeric = 6081,
+    ReserveObsolete = 6082,
+    ElevationGroupAlreadyActivated = 6083,
+    ObligationInDeprecatedReserve = 6084,
+    ReferrerStateOwnerMismatch = 6085,
+    UserMetadataOwnerAlreadySet = 6086,
+    CollateralNonLiquidatable = 6087,
+    BorrowingDisabled = 6088,
+    BorrowLimitExceeded = 6089,
+    DepositLimitExceeded = 6090,
+}
+
+impl LendingError {
+    /// The error for a `custom program error` code, if klend defines it.
+    pub fn from_code(code: u32) -> Option<Self> { Self::from_u32(code) }
+
+    pub fn code(&self) -> u32 { *self as u32 }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::InvalidMarketAuthority => "InvalidMarketAuthority",
+            Self::InvalidMarketOwner => "InvalidMarketOwner",
+            Self::InvalidAccountOwner => "InvalidAccountOwner",
+            Self::InvalidAmount => "InvalidAmount",
+            Self::InvalidConfig => "InvalidConfig",
+            Self::InvalidSigner => "InvalidSigner",
+            Self::InvalidAccountInput => "InvalidAccountInput",
+            Self::MathOverflow => "MathOverflow",
+            Self::InsufficientLiquidity => "InsufficientLiquidity",
+            Self::ReserveStale => "ReserveStale",
+            Self::WithdrawTooSmall => "WithdrawTooSmall",
+            Self::WithdrawTooLarge => "WithdrawTooLarge",
+            Self::BorrowTooSmall => "BorrowTooSmall",
+            Self::BorrowTooLarge => "BorrowTooLarge",
+            Self::RepayTooSmall => "RepayTooSmall",
+            Self::LiquidationTooSmall => "LiquidationTooSmall",
+            Self::ObligationHealthy => "ObligationHealthy",
+            Self::ObligationStale => "ObligationStale",
+            Self::ObligationReserveLimit => "ObligationReserveLimit",
+            Self::InvalidObligationOwner => "InvalidObligationOwner",
+            Self::ObligationDepositsEmpty => "ObligationDepositsEmpty",
+            Self::ObligationBorrowsEmpty => "ObligationBorrowsEmpty",
+            Self::ObligationDepositsZero => "ObligationDepositsZero",
+            Self::ObligationBorrowsZero => "ObligationBorrowsZero",
+            Self::InvalidObligationCollateral => "InvalidObligationCollateral",
+            Self::InvalidObligationLiquidity => "InvalidObligationLiquidity",
+            Self::ObligationCollateralEmpty => "ObligationCollateralEmpty",
+            Self::ObligationLiquidityEmpty => "ObligationLiquidityEmpty",
+            Self::NegativeInterestRate => "NegativeInterestRate",
+            Self::InvalidOracleConfig => "InvalidOracleConfig",
+            Self::InsufficientProtocolFeesToRedeem => "InsufficientProtocolFeesToRedeem",
+            Self::FlashBorrowCpi => "FlashBorrowCpi",
+            Self::NoFlashRepayFound => "NoFlashRepayFound",
+            Self::InvalidFlashRepay => "InvalidFlashRepay",
+            Self::FlashRepayCpi => "FlashRepayCpi",
+            Self::MultipleFlashBorrows => "MultipleFlashBorrows",
+            Self::FlashLoansDisabled => "FlashLoansDisabled",
+            Self::SwitchboardV2Error => "SwitchboardV2Error",
+            Self::CouldNotDeserializeScope => "CouldNotDeserializeScope",
+            Self::PriceTooOld => "PriceTooOld",
+            Self::PriceTooDivergentFromTwap => "PriceTooDivergentFromTwap",
+            Self::InvalidTwapPrice => "InvalidTwapPrice",
+            Self::GlobalEmergencyMode => "GlobalEmergencyMode",
+            Self::InvalidFlag => "InvalidFlag",
+            Self::PriceNotValid => "PriceNotValid",
+            Self::PriceIsBiggerThanHeuristic => "PriceIsBiggerThanHeuristic",
+            Self::PriceIsLowerThanHeuristic => "PriceIsLowerThanHeuristic",
+            Self::PriceIsZero => "PriceIsZero",
+            Self::PriceConfidenceTooWide => "PriceConfidenceTooWide",
+            Self::IntegerOverflow => "IntegerOverflow",
+            Self::NoFarmForReserve => "NoFarmForReserve",
+            Self::IncorrectInstructionInPosition => "IncorrectInstructionInPosition",
+            Self::NoPriceFound => "NoPriceFound",
+            Self::InvalidTwapConfig => "InvalidTwapConfig",
+            Self::InvalidPythPriceAccount => "InvalidPythPriceAccount",
+            Self::InvalidSwitchboardAccount => "InvalidSwitchboardAccount",
+            Self::InvalidScopePriceAccount => "InvalidScopePriceAccount",
+            Self::ObligationCollateralLtvZero => "ObligationCollateralLtvZero",
+            Self::InvalidObligationSeedsValue => "InvalidObligationSeedsValue",
+            Self::InvalidObligationId => "InvalidObligationId",
+            Self::InvalidBorrowRateCurvePoint => "InvalidBorrowRateCurvePoint",
+            Self::InvalidUtilizationRate => "InvalidUtilizationRate",
+            Self::CannotSocializeObligationWithCollateral => {
+                "CannotSocializeObligationWithCollateral"
+            }
+            Self::ObligationEmpty => "ObligationEmpty",
+            Self::WithdrawalCapReached => "WithdrawalCapReached",
+            Self::LastTimestampGreaterThanCurrent => "LastTimestampGreaterThanCurrent",
+            Self::LiquidationSlippageError => "LiquidationSlippageError",
+            Self::IsolatedAssetTierViolation => "IsolatedAssetTierViolation",
+            Self::InconsistentElevationGroup => "InconsistentElevationGroup",
+            Self::InvalidElevationGroup => "InvalidElevationGroup",
+            Self::InvalidElevationGroupConfig => "InvalidElevationGroupConfig",
+            Self::UnhealthyElevationGroupLtv => "UnhealthyElevationGroupLtv",
+            Self::ElevationGroupNewLoansDisabled => "ElevationGroupNewLoansDisabled",
+            Self::ReserveDeprecated => "ReserveDeprecated",
+            Self::ReferrerAccountNotInitialized => "ReferrerAccountNotInitialized",
+            Self::ReferrerAccountMintMissmatch => "ReferrerAccountMintMissmatch",
+            Self::ReferrerAccountWrongAddress => "ReferrerAccountWrongAddress",
+            Self::ReferrerAccountReferrerMissmatch => "ReferrerAccountReferrerMissmatch",
+            Self::ReferrerAccountMissing => "ReferrerAccountMissing",
+            Self::InsufficientReferralFeesToRedeem => "InsufficientReferralFeesToRedeem",
+            Self::CpiDisabled => "CpiDisabled",
+            Self::ShortUrlNotAsciiAlphanumeric => "ShortUrlNotAsciiAlphanumeric",
+            Self::ReserveObsolete => "ReserveObsolete",
+            Self::ElevationGroupAlreadyActivated => "ElevationGroupAlreadyActivated",
+            Self::ObligationInDeprecatedReserve => "ObligationInDeprecatedReserve",
+            Self::ReferrerStateOwnerMismatch => "ReferrerStateOwnerMismatch",
+            Self::UserMetadataOwnerAlreadySet => "UserMetadataOwnerAlreadySet",
+            Self::CollateralNonLiquidatable => "CollateralNonLiquidatable",
+            Self::BorrowingDisabled => "BorrowingDisabled",
+            Self::BorrowLimitExceeded => "BorrowLimitExceeded",
+            Self::DepositLimitExceeded => "DepositLimitExceeded",
+        }
+    }
+
+    pub fn message(&self) -> &'static str {
+        match self {
+            Self::InvalidMarketAuthority => "Market authority is invalid",
+            Self::InvalidMarketOwner => "Market owner is invalid",
+            Self::InvalidAccountOwner => "Input account owner is not the program address",
+            Self::InvalidAmount => "Input amount is invalid",
+            Self::InvalidConfig => "Input config value is invalid",
+            Self::InvalidSigner => "Input account must be a signer",
+            Self::InvalidAccountInput => "Invalid account input",
+            Self::MathOverflow => "Math operation overflow",
+            Self::InsufficientLiquidity => "Insufficient liquidity available",
+            Self::ReserveStale => "Reserve state needs to be refreshed",
+            Self::WithdrawTooSmall => "Withdraw amount too small",
+            Self::WithdrawTooLarge => "Withdraw amount too large",
+            Self::BorrowTooSmall => "Borrow amount too small to receive liquidity after fees",
+            Self::BorrowTooLarge => "Borrow amount too large for deposited collateral",
+            Self::RepayTooSmall => "Repay amount too small to transfer liquidity",
+            Self::LiquidationTooSmall => "Liquidation amount too small to receive collateral",
+            Self::ObligationHealthy => "Cannot liquidate healthy obligations",
+            Self::ObligationStale => "Obligation state needs to be refreshed",
+            Self::ObligationReserveLimit => "Obligation reserve limit exceeded",
+            Self::InvalidObligationOwner => "Obligation owner is invalid",
+            Self::ObligationDepositsEmpty => "Obligation deposits are empty",
+            Self::ObligationBorrowsEmpty => "Obligation borrows are empty",
+            Self::ObligationDepositsZero => "Obligation deposits have zero value",
+            Self::ObligationBorrowsZero => "Obligation borrows have zero value",
+            Self::InvalidObligationCollateral => "Invalid obligation collateral",
+            Self::InvalidObligationLiquidity => "Invalid obligation liquidity",
+            Self::ObligationCollateralEmpty => "Obligation collateral is empty",
+            Self::ObligationLiquidityEmpty => "Obligation liquidity is empty",
+            Self::NegativeInterestRate => "Interest rate is negative",
+            Self::InvalidOracleConfig => "Input oracle config is invalid",
+            Self::InsufficientProtocolFeesToRedeem => {
+                "Insufficient protocol fees to claim or no liquidity available"
+            }
+            Self::FlashBorrowCpi => "No cpi flash borrows allowed",
+            Self::NoFlashRepayFound => "No corresponding repay found for flash borrow",
+            Self::InvalidFlashRepay => "Invalid repay found",
+            Self::FlashRepayCpi => "No cpi flash repays allowed",
+            Self::MultipleFlashBorrows => {
+                "Multiple flash borrows not allowed in the same transaction"
+            }
+            Self::FlashLoansDisabled => "Flash loans are disabled for this reserve",
+            Self::SwitchboardV2Error => "Switchboard error",
+            Self::CouldNotDeserializeScope => "Cannot deserialize the scope price account",
+            Self::PriceTooOld => "Price too old",
+            Self::PriceTooDivergentFromTwap => "Price too divergent from twap",
+            Self::InvalidTwapPrice => "Invalid twap price",
+            Self::GlobalEmergencyMode => "Emergency mode is enabled",
+            Self::InvalidFlag => "Invalid lending market config",
+            Self::PriceNotValid => "Price is not valid",
+            Self::PriceIsBiggerThanHeuristic => "Price is bigger than allowed by heuristic",
+            Self::PriceIsLowerThanHeuristic => "Price lower than allowed by heuristic",
+            Self::PriceIsZero => "Price is zero",
+            Self::PriceConfidenceTooWide => "Price confidence too wide",
+            Self::IntegerOverflow => "Conversion between integers failed",
+            Self::NoFarmForReserve => "This reserve does not have a farm",
+            Self::IncorrectInstructionInPosition => "Wrong instruction at expected position",
+            Self::NoPriceFound => "No price found",
+            Self::InvalidTwapConfig => {
+                "Invalid Twap configuration: Twap is enabled but one of the enabled price doesn't \
+                 have a twap"
+            }
+            Self::InvalidPythPriceAccount => "Pyth price account does not match configuration",
+            Self::InvalidSwitchboardAccount => "Switchboard account(s) do not match configuration",
+            Self::InvalidScopePriceAccount => "Scope price account does not match configuration",
+            Self::ObligationCollateralLtvZero => {
+                "The obligation has one collateral with an LTV set to 0. Withdraw it before \
+                 withdrawing other collaterals"
+            }
+            Self::InvalidObligationSeedsValue => {
+                "Seeds must be default pubkeys for tag 0, and mint addresses for tag 1 or 2"
+            }
+            Self::InvalidObligationId => "Obligation id must be 0",
+            Self::InvalidBorrowRateCurvePoint => "Invalid borrow rate curve point",
+            Self::InvalidUtilizationRate => "Invalid utilization rate",
+            Self::CannotSocializeObligationWithCollateral => {
+                "Obligation hasn't been fully liquidated and debt cannot be socialized."
+            }
+            Self::ObligationEmpty => "Obligation has no borrows or deposits.",
+            Self::WithdrawalCapReached => "Withdrawal cap is reached",
+            Self::LastTimestampGreaterThanCurrent => {
+                "The last interval start timestamp is greater than the current timestamp"
+            }
+            Self::LiquidationSlippageError => {
+                "The reward amount is less than the minimum acceptable received collateral"
+            }
+            Self::IsolatedAssetTierViolation => "Isolated Asset Tier Violation",
+            Self::InconsistentElevationGroup => {
+                "The obligation's elevation group and the reserve's are not the same"
+            }
+            Self::InvalidElevationGroup => {
+                "The elevation group chosen for the reserve does not exist in the lending market"
+            }
+            Self::InvalidElevationGroupConfig => {
+                "The elevation group updated has wrong parameters set"
+            }
+            Self::UnhealthyElevationGroupLtv => {
+                "The current obligation must have most or all its debt repaid before changing the \
+                 elevation group"
+            }
+            Self::ElevationGroupNewLoansDisabled => {
+                "Elevation group does not accept any new loans or any new borrows/withdrawals"
+            }
+            Self::ReserveDeprecated => "Reserve was deprecated, no longer usable",
+            Self::ReferrerAccountNotInitialized => "Referrer account not initialized",
+            Self::ReferrerAccountMintMissmatch => {
+                "Referrer account mint does not match the operation reserve mint"
+            }
+            Self::ReferrerAccountWrongAddress => {
+                "Referrer account address is not a valid program address"
+            }
+            Self::ReferrerAccountReferrerMissmatch => {
+                "Referrer account referrer does not match the owner referrer"
+            }
+            Self::ReferrerAccountMissing => "Referrer account missing for obligation with referrer",
+            Self::InsufficientReferralFeesToRedeem => {
+                "Insufficient referral fees to claim or no liquidity available"
+            }
+            Self::CpiDisabled => "CPI disabled for this instruction",
+            Self::ShortUrlNotAsciiAlphanumeric => "Referrer short_url is not ascii alphanumeric",
+            Self::ReserveObsolete => "Reserve is marked as obsolete",
+            Self::ElevationGroupAlreadyActivated => {
+                "Obligation already part of the same elevation group"
+            }
+            Self::ObligationInDeprecatedReserve => {
+                "Obligation has a deposit in a deprecated reserve"
+            }
+            Self::ReferrerStateOwnerMismatch => {
+                "Referrer state owner does not match the given signer"
+            }
+            Self::UserMetadataOwnerAlreadySet => "User metadata owner is already set",
+            Self::CollateralNonLiquidatable => {
+                "This collateral cannot be liquidated (LTV set to 0)"
+            }
+            Self::BorrowingDisabled => "Borrowing is disabled",
+            Self::BorrowLimitExceeded => "Cannot borrow above borrow limit",
+            Self::DepositLimitExceeded => "Cannot deposit above deposit limit",
+        }
+    }
+}
+
+/// `klend::ObligationStale (6017)`.
+impl fmt::Display for LendingError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "klend::{} ({})", self.name(), self.code())
+    }
+}
+
+impl std::error::Error for LendingError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_code() {
+        assert_eq!(LendingError::from_code(6017), Some(LendingError::ObligationStale));
+        assert_eq!(LendingError::from_code(6090), Some(LendingError::DepositLimitExceeded));
+        assert_eq!(LendingError::from_code(6091), None);
+        assert_eq!(LendingError::from_code(17), None);
+        assert_eq!(LendingError::ObligationStale.to_string(), "klend::ObligationStale (6017)");
+        assert_eq!(LendingError::InvalidConfig.message(), "Input config value is invalid");
+    }
+}
@@ -0,0 +1,230 @@
+//! klend's fixed-point numbers.
+//!
+//! Fields suffixed `_sf` hold the bits of a [`Fraction`], fields suffixed
+//! `_bsf` hold a [`BigFraction`] as [`BigFractionBytes`]. Both use 60
+//! fractional bits.
+
+use std::{
+    fmt,
+    ops::{Add, Div, Mul, Sub},
+};
+
+pub use fixed::types::U68F60 as Fraction;
+use rust_decimal::Decimal;
+
+pub use self::uint_types::U256;
+use crate::typedefs::BigFractionBytes;
+
+#[allow(clippy::assign_op_pattern, clippy::manual_div_ceil)]
+mod uint_types {
+    uint::construct_uint! {
+        pub struct U256(4);
+    }
+}
+
+pub const FRACTION_BITS: u32 = 60;
+const FRACTION_DECIMALS: u32 = 17;
+
+pub trait FractionExtra: Sized {
+    fn from_percent<T: Into<u128>>(percent: T) -> Self;
+    fn from_bps<T: Into<u128>>(bps: T) -> Self;
+    /// Rounds to 17 fractional digits, so a decimal truncated into 60
+    /// fractional bits comes back exact, or fewer when the integer part needs
+    /// the room.
+    fn to_decimal(&self) -> Decimal;
+}
+
+impl FractionExtra for Fraction {
+    fn from_percent<T: Into<u128>>(percent: T) -> Self { Fraction::from_num(percent.into()) / 100 }
+
+    fn from_bps<T: Into<u128>>(bps: T) -> Self { Fraction::from_num(bps.into()) / 10_000 }
+
+    fn to_decimal(&self) -> Decimal {
+        bits_to_decimal(U256::from(self.to_bits())).expect("fractions have at most 68 integer bits")
+    }
+}
+
+/// The decimal closest to `bits` with as many of 17 fractional digits as
+/// its 96-bit mantissa has room for.
+fn bits_to_decimal(bits: U256) -> Option<Decimal> {
+    let half = U256::one() << (FRACTION_BITS - 1);
+    (0..=FRACTION_DECIMALS).rev().find_map(|scale| {
+        let scaled = bits.checked_mul(U256::exp10(scale as usize))?.checked_add(half)?;
+        let mantissa = scaled >> FRACTION_BITS;
+        (mantissa.bits() <= 96)
+            .then(|| Decimal::from_i128_with_scale(mantissa.as_u128() as i128, scale).normalize())
+    })
+}
+
+/// Fixed-point number with 196 integer bits and 60 fractional bits, used for
+/// cumulative borrow rates.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct BigFraction(pub U256);
+
+impl BigFraction {
+    pub const ONE: Self = Self(U256([1 << FRACTION_BITS, 0, 0, 0]));
+    pub const ZERO: Self = Self(U256([0; 4]));
+
+    pub fn from_bits(bits: U256) -> Self { Self(bits) }
+
+    pub fn to_bits(self) -> U256 { self.0 }
+
+    /// Rounds like [`FractionExtra::to_decimal`]; `None` when the integer
+    /// part is past a decimal's 96 bits.
+    pub fn to_decimal(&self) -> Option<Decimal> { bits_to_decimal(self.0) }
+
+    pub fn checked_add(self, rhs: Self) -> Option<Self> { self.0.checked_add(rhs.0).map(Self) }
+
+    pub fn checked_sub(self, rhs: Self) -> Option<Self> { self.0.checked_sub(rhs.0).map(Self) }
+
+    pub fn checked_mul(self, rhs: Self) -> Option<Self> {
+        self.0.checked_mul(rhs.0).map(|product| Self(product >> FRACTION_BITS))
+    }
+
+    pub fn checked_div(self, rhs: Self) -> Option<Self> {
+        self.0.checked_mul(Self::ONE.0)?.checked_div(rhs.0).map(Self)
+    }
+}
+
+impl Add for BigFraction {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self { self.checked_add(rhs).expect("big fraction overflow") }
+}
+
+impl Sub for BigFraction {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self { self.checked_sub(rhs).expect("big fraction underflow") }
+}
+
+impl Mul for BigFraction {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self { self.checked_mul(rhs).expect("big fraction overflow") }
+}
+
+impl Div for BigFraction {
+    type Output = Self;
+
+    fn div(self, rhs: Self) -> Self { self.checked_div(rhs).expect("big fraction division") }
+}
+
+impl From<Fraction> for BigFraction {
+    fn from(fraction: Fraction) -> Self { Self(U256::from(fraction.to_bits())) }
+}
+
+impl TryFrom<BigFraction> for Fraction {
+    type Error = &'static str;
+
+    fn try_from(big: BigFraction) -> Result<Self, Self::Error> {
+        if big.0.bits() > 128 {
+            return Err("big fraction does not fit in a fraction");
+        }
+        Ok(Fraction::from_bits(big.0.as_u128()))
+    }
+}
+
+/// Truncates to 60 fractional bits.
+impl TryFrom<Decimal> for BigFraction {
+    type Error = &'static str;
+
+    fn try_from(decimal: Decimal) -> Result<Self, Self::Error> {
+        if decimal.is_sign_negative() && !decimal.is_zero() {
+            return Err("big fractions are not negative");
+        }
+        let mantissa = U256::from(decimal.mantissa().unsigned_abs());
+        Ok(Self((mantissa << FRACTION_BITS) / U256::exp10(decimal.scale() as usize)))
+    }
+}
+
+impl From<BigFractionBytes> for BigFraction {
+    fn from(bytes: BigFractionBytes) -> Self { Self(U256(bytes.value)) }
+}
+
+impl From<&BigFractionBytes> for BigFraction {
+    fn from(bytes: &BigFractionBytes) -> Self { Self(U256(bytes.value)) }
+}
+
+impl From<BigFraction> for BigFractionBytes {
+    fn from(big: BigFraction) -> Self { BigFractionBytes { value: big.0 .0, padding: [0; 2] } }
+}
+
+impl fmt::Display for BigFraction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mask = (U256::one() << FRACTION_BITS) - 1;
+        write!(f, "{}", self.0 >> FRACTION_BITS)?;
+        let mut remainder = self.0 & mask;
+        if remainder.is_zero() {
+            return Ok(());
+        }
+        write!(f, ".")?;
+        while !remainder.is_zero() {
+            remainder *= 10;
+            write!(f, "{}", (remainder >> FRACTION_BITS).low_u32())?;
+            remainder &= mask;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    #[test]
+    fn test_big_fraction_bytes_round_trip() {
+        let bytes = BigFractionBytes { value: [7, 1 << 63, 3, 1], padding: [0; 2] };
+        let big = BigFraction::from(bytes);
+        assert_eq!(BigFractionBytes::from(big).value, bytes.value);
+        assert!(Fraction::try_from(big).is_err());
+
+        let fraction = Fraction::from_num(1.25);
+        assert_eq!(Fraction::try_from(BigFraction::from(fraction)).unwrap(), fraction);
+    }
+
+    #[test]
+    fn test_big_fraction_arithmetic() {
+        let three = BigFraction::from(Fraction::from_num(3));
+        let two = BigFraction::from(Fraction::from_num(2));
+        assert_eq!(three / two, BigFraction::from(Fraction::from_num(1.5)));
+        assert_eq!(three * two, BigFraction::from(Fraction::from_num(6)));
+        assert_eq!(three - two, BigFraction::ONE);
+        assert_eq!(two.checked_sub(three), None);
+        assert_eq!(two.checked_div(BigFraction::ZERO), None);
+        assert_eq!(BigFraction(U256::MAX).checked_add(BigFraction::ONE), None);
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(BigFraction::from(Fraction::from_num(2.375)).to_string(), "2.375");
+        assert_eq!(BigFraction::ONE.to_string(), "1");
+        assert_eq!(Fraction::from_num(2.375).to_string(), "2.375");
+    }
+
+    #[test]
+    fn test_to_decimal() {
+        assert_eq!(Fraction::from_percent(75u8).to_decimal(), Decimal::from_str("0.75").unwrap());
+        assert_eq!(Fraction::from_bps(1u16).to_decimal(), Decimal::from_str("0.0001").unwrap());
+        let large = Fraction::from_num(u64::MAX) + Fraction::from_num(0.5);
+        assert_eq!(large.to_decimal(), Decimal::from(u64::MAX) + Decimal::from_str("0.5").unwrap());
+        assert!(Fraction::MAX.to_decimal() > Decimal::from(u128::MAX >> 61));
+    }
+
+    #[test]
+    fn test_big_fraction_decimal_round_trip() {
+        for value in
+            ["0", "1", "1.0000000000012345", "123456789.00000000001", "0.00000000000000001"]
+        {
+            let decimal = Decimal::from_str(value).unwrap();
+            let big = BigFraction::try_from(decimal).unwrap();
+            assert_eq!(big.to_decimal(), Some(decimal.normalize()), "{value}");
+        }
+        let rate = BigFraction::from(Fraction::from_num(1.25));
+        assert_eq!(BigFraction::try_from(rate.to_decimal().unwrap()), Ok(rate));
+        assert_eq!(BigFraction(U256::one() << 200).to_decimal(), None);
+        assert!(BigFraction::try_from(Decimal::from_str("-0.5").unwrap()).is_err());
+    }
+}
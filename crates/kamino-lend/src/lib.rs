@@ -1,3 +1,10 @@
 anchor_gen::generate_cpi_crate!("../../idl/kamino_lending.json");
 
+mod accessors;
+pub mod errors;
+pub mod fraction;
+
+pub use errors::LendingError;
+pub use fraction::{BigFraction, Fraction, FractionExtra};
+
 declare_id!("KLend2g3cP87fffoy8q1mQqGKjrxjC8boSyAYavgmjD");
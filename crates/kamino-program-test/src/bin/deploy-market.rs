@@ -0,0 +1,59 @@
+//! Deploys a market spec and prints the address map.
+//!
+//! `cargo run -p kamino-program-test --bin deploy-market -- <spec> [--url URL]
+//! [--keypair PATH] [--addresses PATH]`
+//!
+//! Without `--url` the market is deployed to a fresh in-process bank, which
+//! checks the spec. `--addresses` reads the map of a previous deployment to
+//! resume from and writes the updated map back.
+
+use std::{error::Error, fs, path::PathBuf};
+
+use kamino_client::market_spec::MarketSpec;
+use kamino_program_test::{
+    deploy::{deploy_market, MarketAddresses, RpcTarget},
+    KaminoTestContext,
+};
+use solana_sdk::signature::read_keypair_file;
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn Error>> {
+    let usage = "usage: deploy-market <spec> [--url URL] [--keypair PATH] [--addresses PATH]";
+    let mut args = std::env::args().skip(1);
+    let spec = MarketSpec::load(args.next().ok_or(usage)?)?;
+    let (mut url, mut keypair, mut addresses_path) = (None, None, None);
+    while let Some(flag) = args.next() {
+        let value = args.next().ok_or(usage)?;
+        match flag.as_str() {
+            "--url" => url = Some(value),
+            "--keypair" => keypair = Some(PathBuf::from(value)),
+            "--addresses" => addresses_path = Some(PathBuf::from(value)),
+            _ => return Err(usage.into()),
+        }
+    }
+
+    let mut addresses = match &addresses_path {
+        Some(path) if path.exists() => serde_json::from_str(&fs::read_to_string(path)?)?,
+        _ => MarketAddresses::default(),
+    };
+    let result = match url {
+        Some(url) => {
+            let keypair = keypair.or_else(default_keypair).ok_or("no --keypair given")?;
+            let payer =
+                read_keypair_file(&keypair).map_err(|e| format!("{}: {e}", keypair.display()))?;
+            deploy_market(&mut RpcTarget::new(url, payer), &spec, &mut addresses).await
+        }
+        None => deploy_market(&mut KaminoTestContext::start().await?, &spec, &mut addresses).await,
+    };
+
+    let json = serde_json::to_string_pretty(&addresses)? + "\n";
+    if let Some(path) = addresses_path {
+        fs::write(path, &json)?;
+    }
+    print!("{json}");
+    result
+}
+
+fn default_keypair() -> Option<PathBuf> {
+    std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config/solana/id.json"))
+}
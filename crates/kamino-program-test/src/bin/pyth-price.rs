@@ -0,0 +1,14 @@
+//! Prints a synthetic Pyth price account in the `deps/prices` JSON format.
+//!
+//! `cargo run -p kamino-program-test --bin pyth-price -- <address> <price>
+//! [flags] > deps/prices/x.json`
+
+use std::error::Error;
+
+use kamino_program_test::pyth::parse_args;
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let (address, price) = parse_args(std::env::args().skip(1))?;
+    print!("{}", price.to_fixture(&address).to_json()?);
+    Ok(())
+}
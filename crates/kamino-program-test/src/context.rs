@@ -0,0 +1,147 @@
+use std::error::Error;
+
+use anchor_lang::AccountDeserialize;
+use kamino_client::instruction::{init_lending_market, init_reserve};
+use kamino_lend::{
+    state::{LendingMarket, Reserve},
+    ID as KAMINO_LENDING_ID,
+};
+use solana_program_test::{BanksClientError, ProgramTestContext};
+use solana_sdk::{
+    account::{Account, AccountSharedData},
+    instruction::Instruction,
+    pubkey::Pubkey,
+    rent::Rent,
+    signature::{Keypair, Signer},
+    system_instruction,
+    transaction::Transaction,
+};
+
+use crate::{program_test, pyth::PythPrice};
+
+/// A started bank with the test validator's programs and fixtures, and a
+/// funded payer.
+pub struct KaminoTestContext {
+    pub context: ProgramTestContext,
+}
+
+impl KaminoTestContext {
+    pub async fn start() -> Result<Self, Box<dyn Error>> {
+        Ok(Self { context: program_test()?.start_with_context().await })
+    }
+
+    pub fn payer(&self) -> &Keypair { &self.context.payer }
+
+    /// Sends `instructions` in one transaction paid by the payer, who signs
+    /// in addition to `signers`.
+    pub async fn process_instructions(
+        &mut self,
+        instructions: &[Instruction],
+        signers: &[&Keypair],
+    ) -> Result<(), BanksClientError> {
+        // A fresh blockhash keeps identical transactions from being deduped.
+        let blockhash = self.context.get_new_latest_blockhash().await?;
+        let mut all_signers = vec![&self.context.payer];
+        all_signers.extend_from_slice(signers);
+        let transaction = Transaction::new_signed_with_payer(
+            instructions,
+            Some(&self.context.payer.pubkey()),
+            &all_signers,
+            blockhash,
+        );
+        self.context.banks_client.process_transaction(transaction).await
+    }
+
+    pub async fn get_account(&mut self, address: &Pubkey) -> Result<Account, Box<dyn Error>> {
+        self.context
+            .banks_client
+            .get_account(*address)
+            .await?
+            .ok_or_else(|| format!("account {address} does not exist").into())
+    }
+
+    pub async fn get_anchor_account<T: AccountDeserialize>(
+        &mut self,
+        address: &Pubkey,
+    ) -> Result<T, Box<dyn Error>> {
+        let account = self.get_account(address).await?;
+        Ok(T::try_deserialize(&mut account.data.as_slice())?)
+    }
+
+    /// Replaces the Pyth price account at `address`, e.g. to move a price
+    /// mid-test.
+    pub async fn set_pyth_price(
+        &mut self,
+        address: &Pubkey,
+        price: &PythPrice,
+    ) -> Result<(), Box<dyn Error>> {
+        self.overwrite_account(address, price.to_account()).await
+    }
+
+    /// Replaces the account at `address`. The payer funds any lamports the
+    /// new account adds, as the bank's capitalization is checked when
+    /// warping.
+    pub async fn overwrite_account(
+        &mut self,
+        address: &Pubkey,
+        account: Account,
+    ) -> Result<(), Box<dyn Error>> {
+        let previous = self.context.banks_client.get_account(*address).await?;
+        let previous_lamports = previous.map_or(0, |account| account.lamports);
+        let payer = self.payer().pubkey();
+        let mut payer_account = self.get_account(&payer).await?;
+        payer_account.lamports = (payer_account.lamports + previous_lamports)
+            .checked_sub(account.lamports)
+            .ok_or("payer cannot fund the account")?;
+        self.context.set_account(&payer, &AccountSharedData::from(payer_account));
+        self.context.set_account(address, &AccountSharedData::from(account));
+        Ok(())
+    }
+
+    /// Creates a rent-exempt account of `space` bytes owned by `owner`, e.g.
+    /// a lending market or reserve before its init instruction.
+    pub async fn create_account(
+        &mut self,
+        account: &Keypair,
+        space: usize,
+        owner: &Pubkey,
+    ) -> Result<(), Box<dyn Error>> {
+        let instruction = system_instruction::create_account(
+            &self.context.payer.pubkey(),
+            &account.pubkey(),
+            Rent::default().minimum_balance(space),
+            space as u64,
+            owner,
+        );
+        self.process_instructions(&[instruction], &[account]).await?;
+        Ok(())
+    }
+
+    /// Creates a lending market owned by the payer.
+    pub async fn create_lending_market(&mut self) -> Result<Pubkey, Box<dyn Error>> {
+        let market = Keypair::new();
+        let space = 8 + std::mem::size_of::<LendingMarket>();
+        self.create_account(&market, space, &KAMINO_LENDING_ID).await?;
+        let instruction = init_lending_market(&self.payer().pubkey(), &market.pubkey(), [0; 32])?;
+        self.process_instructions(&[instruction], &[]).await?;
+        Ok(market.pubkey())
+    }
+
+    /// Creates a reserve of `mint`, owned by `token_program`, in the payer's
+    /// `lending_market`. It has klend's default config until one is set.
+    pub async fn create_reserve(
+        &mut self,
+        lending_market: &Pubkey,
+        mint: &Pubkey,
+        token_program: &Pubkey,
+    ) -> Result<Pubkey, Box<dyn Error>> {
+        let reserve = Keypair::new();
+        let space = 8 + std::mem::size_of::<Reserve>();
+        self.create_account(&reserve, space, &KAMINO_LENDING_ID).await?;
+        let owner = self.payer().pubkey();
+        let instruction =
+            init_reserve(lending_market, &reserve.pubkey(), mint, &owner, token_program)?;
+        self.process_instructions(&[instruction], &[]).await?;
+        Ok(reserve.pubkey())
+    }
+}
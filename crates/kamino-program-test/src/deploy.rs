@@ -0,0 +1,432 @@
+//! Deploys a [`MarketSpec`] to the in-process harness or to a cluster.
+//!
+//! Deploying is idempotent given the address map of the previous run:
+//! accounts that already exist are reused, and configs are only updated when
+//! they differ from the spec.
+
+use std::{collections::BTreeMap, error::Error};
+
+use anchor_lang::AccountDeserialize;
+use kamino_client::{
+    instruction::{
+        init_farms_for_reserve, init_lending_market, init_reserve, update_lending_market_to,
+        update_reserve_config_to, ReserveAccounts,
+    },
+    market_spec::{option_pubkey, pubkey, MarketSpec, OracleSpec, ReserveSpec},
+    pda::farms::ID as FARMS_PROGRAM_ID,
+    reserve_config::validate,
+    token::{check_reserve_mint, create_mint},
+};
+use kamino_lend::{
+    state::{LendingMarket, Reserve},
+    typedefs::ReserveFarmKind,
+    ID as KAMINO_LENDING_ID,
+};
+use serde::{Deserialize, Serialize};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::{
+    account::Account,
+    commitment_config::CommitmentConfig,
+    instruction::Instruction,
+    pubkey::Pubkey,
+    rent::Rent,
+    signature::{Keypair, Signer},
+    system_instruction,
+    transaction::Transaction,
+};
+
+use crate::{deps_dir, pyth::PythPrice, KaminoTestContext};
+
+/// Size of a farms program `FarmState`, discriminator included.
+const FARM_STATE_LEN: usize = 8 + 8328;
+
+/// Addresses created by a deployment, keyed by reserve name.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MarketAddresses {
+    #[serde(default, with = "option_pubkey")]
+    pub lending_market: Option<Pubkey>,
+    #[serde(default)]
+    pub reserves: BTreeMap<String, ReserveAddresses>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReserveAddresses {
+    #[serde(with = "pubkey")]
+    pub reserve: Pubkey,
+    #[serde(with = "pubkey")]
+    pub mint: Pubkey,
+    /// Synthetic price feed of a `price` oracle.
+    #[serde(default, with = "option_pubkey", skip_serializing_if = "Option::is_none")]
+    pub oracle: Option<Pubkey>,
+    #[serde(default, with = "option_pubkey", skip_serializing_if = "Option::is_none")]
+    pub collateral_farm: Option<Pubkey>,
+    #[serde(default, with = "option_pubkey", skip_serializing_if = "Option::is_none")]
+    pub debt_farm: Option<Pubkey>,
+}
+
+/// Where a market is deployed; the payer owns the market while deploying.
+#[allow(async_fn_in_trait)]
+pub trait DeployTarget {
+    fn payer(&self) -> &Keypair;
+
+    async fn fetch_account(&mut self, address: &Pubkey) -> Result<Option<Account>, Box<dyn Error>>;
+
+    async fn send(
+        &mut self,
+        instructions: &[Instruction],
+        signers: &[&Keypair],
+    ) -> Result<(), Box<dyn Error>>;
+
+    /// Publishes a synthetic Pyth price at `address`.
+    async fn set_price(&mut self, address: &Pubkey, price: f64) -> Result<(), Box<dyn Error>>;
+}
+
+impl DeployTarget for KaminoTestContext {
+    fn payer(&self) -> &Keypair { &self.context.payer }
+
+    async fn fetch_account(&mut self, address: &Pubkey) -> Result<Option<Account>, Box<dyn Error>> {
+        Ok(self.context.banks_client.get_account(*address).await?)
+    }
+
+    async fn send(
+        &mut self,
+        instructions: &[Instruction],
+        signers: &[&Keypair],
+    ) -> Result<(), Box<dyn Error>> {
+        Ok(self.process_instructions(instructions, signers).await?)
+    }
+
+    async fn set_price(&mut self, address: &Pubkey, price: f64) -> Result<(), Box<dyn Error>> {
+        let clock = self.clock().await?;
+        let price = PythPrice {
+            publish_time: clock.unix_timestamp,
+            slot: clock.slot,
+            ..PythPrice::from_usd(price)
+        };
+        self.set_pyth_price(address, &price).await
+    }
+}
+
+/// The spec of `deps/markets/local.toml`, the market the harness's tests run
+/// against.
+pub fn local_market_spec() -> Result<MarketSpec, Box<dyn Error>> {
+    Ok(MarketSpec::load(deps_dir().join("markets/local.toml"))?)
+}
+
+impl KaminoTestContext {
+    /// Deploys the [`local_market_spec`] from scratch.
+    pub async fn deploy_local_market(&mut self) -> Result<MarketAddresses, Box<dyn Error>> {
+        let mut addresses = MarketAddresses::default();
+        deploy_market(self, &local_market_spec()?, &mut addresses).await?;
+        Ok(addresses)
+    }
+}
+
+/// A cluster reached over RPC, e.g. a local test validator.
+pub struct RpcTarget {
+    pub client: RpcClient,
+    pub payer: Keypair,
+}
+
+impl RpcTarget {
+    pub fn new(url: String, payer: Keypair) -> Self {
+        Self { client: RpcClient::new_with_commitment(url, CommitmentConfig::confirmed()), payer }
+    }
+}
+
+impl DeployTarget for RpcTarget {
+    fn payer(&self) -> &Keypair { &self.payer }
+
+    async fn fetch_account(&mut self, address: &Pubkey) -> Result<Option<Account>, Box<dyn Error>> {
+        let commitment = self.client.commitment();
+        Ok(self.client.get_account_with_commitment(address, commitment).await?.value)
+    }
+
+    async fn send(
+        &mut self,
+        instructions: &[Instruction],
+        signers: &[&Keypair],
+    ) -> Result<(), Box<dyn Error>> {
+        let blockhash = self.client.get_latest_blockhash().await?;
+        let mut all_signers = vec![&self.payer];
+        all_signers.extend_from_slice(signers);
+        let transaction = Transaction::new_signed_with_payer(
+            instructions,
+            Some(&self.payer.pubkey()),
+            &all_signers,
+            blockhash,
+        );
+        self.client.send_and_confirm_transaction(&transaction).await?;
+        Ok(())
+    }
+
+    async fn set_price(&mut self, _address: &Pubkey, _price: f64) -> Result<(), Box<dyn Error>> {
+        Err("synthetic prices can only be deployed in-process; use a pyth, switchboard or scope \
+             oracle"
+            .into())
+    }
+}
+
+/// Deploys `spec`, reusing and completing the accounts in `addresses`, which
+/// is kept up to date as the deployment progresses.
+pub async fn deploy_market(
+    target: &mut impl DeployTarget,
+    spec: &MarketSpec,
+    addresses: &mut MarketAddresses,
+) -> Result<(), Box<dyn Error>> {
+    let payer = target.payer().pubkey();
+    let lending_market = match existing(target, addresses.lending_market).await? {
+        Some(lending_market) => lending_market,
+        None => {
+            let lending_market = Keypair::new();
+            let instructions = [
+                create_account_instruction(
+                    &payer,
+                    &lending_market.pubkey(),
+                    8 + std::mem::size_of::<LendingMarket>(),
+                    &KAMINO_LENDING_ID,
+                ),
+                init_lending_market(&payer, &lending_market.pubkey(), spec.quote_currency()?)?,
+            ];
+            target.send(&instructions, &[&lending_market]).await?;
+            addresses.lending_market = Some(lending_market.pubkey());
+            lending_market.pubkey()
+        }
+    };
+    let market: LendingMarket = fetch_anchor(target, &lending_market).await?;
+    if market.quote_currency != spec.quote_currency()? {
+        return Err(format!("market {lending_market} is quoted in another currency").into());
+    }
+
+    let mut desired = market;
+    for group in &spec.elevation_groups {
+        let groups = &desired.elevation_groups;
+        let index = groups
+            .iter()
+            .position(|slot| slot.id == group.id)
+            .or_else(|| groups.iter().position(|slot| slot.id == 0))
+            .ok_or("the market has no free elevation group")?;
+        desired.elevation_groups[index] = group.to_elevation_group();
+    }
+    desired.risk_council = spec.risk_council.unwrap_or(market.risk_council);
+    for instruction in update_lending_market_to(&payer, &lending_market, &market, &desired)? {
+        target.send(&[instruction], &[]).await?;
+    }
+
+    for reserve in &spec.reserves {
+        deploy_reserve(target, spec, &lending_market, reserve, addresses).await?;
+    }
+
+    // Last, as the payer has to stay the owner until everything else is set.
+    if let Some(owner) = spec.owner {
+        let transfer = LendingMarket { lending_market_owner: owner, ..desired };
+        for instruction in update_lending_market_to(&payer, &lending_market, &desired, &transfer)? {
+            target.send(&[instruction], &[]).await?;
+        }
+    }
+    Ok(())
+}
+
+async fn deploy_reserve(
+    target: &mut impl DeployTarget,
+    spec: &MarketSpec,
+    lending_market: &Pubkey,
+    reserve_spec: &ReserveSpec,
+    addresses: &mut MarketAddresses,
+) -> Result<(), Box<dyn Error>> {
+    let payer = target.payer().pubkey();
+    let previous = addresses.reserves.get(&reserve_spec.name).cloned().unwrap_or_default();
+
+    let (mint, token_program) = match reserve_spec.mint {
+        Some(mint) => {
+            let account = target
+                .fetch_account(&mint)
+                .await?
+                .ok_or_else(|| format!("{mint} does not exist"))?;
+            (mint, check_reserve_mint(&mint, &account)?.token_program)
+        }
+        None => match previous_account(target, previous.mint).await? {
+            Some(account) => (previous.mint, account.owner),
+            None => {
+                let mint = Keypair::new();
+                let instructions = create_mint(
+                    &payer,
+                    &mint.pubkey(),
+                    &payer,
+                    None,
+                    reserve_spec.decimals,
+                    &spl_token::ID,
+                )?;
+                target.send(&instructions, &[&mint]).await?;
+                (mint.pubkey(), spl_token::ID)
+            }
+        },
+    };
+
+    // A deployed reserve keeps its price; only a new one gets the synthetic
+    // price its config update checks.
+    let existing_reserve = existing(target, Some(previous.reserve)).await?;
+    let oracle = match reserve_spec.oracle {
+        OracleSpec::Price(price) => {
+            let oracle = previous.oracle.unwrap_or_else(|| Keypair::new().pubkey());
+            if existing_reserve.is_none() {
+                target.set_price(&oracle, price).await?;
+            }
+            Some(oracle)
+        }
+        _ => None,
+    };
+
+    let reserve = match existing_reserve {
+        Some(reserve) => reserve,
+        None => {
+            let reserve = Keypair::new();
+            let instructions = [
+                create_account_instruction(
+                    &payer,
+                    &reserve.pubkey(),
+                    8 + std::mem::size_of::<Reserve>(),
+                    &KAMINO_LENDING_ID,
+                ),
+                init_reserve(lending_market, &reserve.pubkey(), &mint, &payer, &token_program)?,
+            ];
+            target.send(&instructions, &[&reserve]).await?;
+            reserve.pubkey()
+        }
+    };
+    let mut addresses_entry = ReserveAddresses { reserve, mint, oracle, ..previous };
+    addresses.reserves.insert(reserve_spec.name.clone(), addresses_entry.clone());
+
+    let state: Reserve = fetch_anchor(target, &reserve).await?;
+    let config = reserve_spec.reserve_config(&oracle.unwrap_or_default())?;
+    validate(&config).map_err(|errors| {
+        let errors: Vec<_> = errors.iter().map(ToString::to_string).collect();
+        format!("reserve {} is invalid: {}", reserve_spec.name, errors.join(", "))
+    })?;
+    let instructions =
+        update_reserve_config_to(&payer, lending_market, &reserve, &state.config, &config)?;
+    for instruction in instructions {
+        target.send(&[instruction], &[]).await?;
+    }
+
+    for farm in &reserve_spec.farms {
+        let current = match farm.kind() {
+            ReserveFarmKind::Collateral => state.farm_collateral,
+            ReserveFarmKind::Debt => state.farm_debt,
+        };
+        if current != Pubkey::default() {
+            continue;
+        }
+        let farms_global_config = spec
+            .farms_global_config
+            .ok_or_else(|| format!("{}: farms need farms_global_config", reserve_spec.name))?;
+        let farm_state = Keypair::new();
+        let reserve_accounts =
+            ReserveAccounts::new(lending_market, &reserve, &mint, &token_program);
+        let instructions = [
+            create_account_instruction(
+                &payer,
+                &farm_state.pubkey(),
+                FARM_STATE_LEN,
+                &FARMS_PROGRAM_ID,
+            ),
+            init_farms_for_reserve(
+                &payer,
+                &reserve_accounts,
+                &farms_global_config,
+                &farm_state.pubkey(),
+                farm.kind(),
+            )?,
+        ];
+        target.send(&instructions, &[&farm_state]).await?;
+        match farm.kind() {
+            ReserveFarmKind::Collateral => {
+                addresses_entry.collateral_farm = Some(farm_state.pubkey())
+            }
+            ReserveFarmKind::Debt => addresses_entry.debt_farm = Some(farm_state.pubkey()),
+        }
+        addresses.reserves.insert(reserve_spec.name.clone(), addresses_entry.clone());
+    }
+    Ok(())
+}
+
+/// The account at `address`, unless it is unset or does not exist.
+async fn previous_account(
+    target: &mut impl DeployTarget,
+    address: Pubkey,
+) -> Result<Option<Account>, Box<dyn Error>> {
+    if address == Pubkey::default() {
+        return Ok(None);
+    }
+    target.fetch_account(&address).await
+}
+
+/// `address` if it is set and the account exists.
+async fn existing(
+    target: &mut impl DeployTarget,
+    address: Option<Pubkey>,
+) -> Result<Option<Pubkey>, Box<dyn Error>> {
+    match address.filter(|address| *address != Pubkey::default()) {
+        Some(address) => Ok(target.fetch_account(&address).await?.map(|_| address)),
+        None => Ok(None),
+    }
+}
+
+async fn fetch_anchor<T: AccountDeserialize>(
+    target: &mut impl DeployTarget,
+    address: &Pubkey,
+) -> Result<T, Box<dyn Error>> {
+    let account =
+        target.fetch_account(address).await?.ok_or_else(|| format!("{address} does not exist"))?;
+    Ok(T::try_deserialize(&mut account.data.as_slice())?)
+}
+
+fn create_account_instruction(
+    payer: &Pubkey,
+    address: &Pubkey,
+    space: usize,
+    owner: &Pubkey,
+) -> Instruction {
+    let lamports = Rent::default().minimum_balance(space);
+    system_instruction::create_account(payer, address, lamports, space as u64, owner)
+}
+
+#[cfg(test)]
+mod tests {
+    use kamino_lend::Fraction;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_deploy_is_idempotent() {
+        let spec = local_market_spec().unwrap();
+        let mut ctx = KaminoTestContext::start().await.unwrap();
+        let mut addresses = ctx.deploy_local_market().await.unwrap();
+
+        let market: LendingMarket =
+            ctx.get_anchor_account(&addresses.lending_market.unwrap()).await.unwrap();
+        assert_eq!(Some(market.risk_council), spec.risk_council);
+        let group = market.elevation_groups.iter().find(|group| group.id == 1).unwrap();
+        assert_eq!(group.ltv_pct, spec.elevation_groups[0].ltv_pct);
+        assert_eq!(addresses.reserves.len(), spec.reserves.len());
+        for reserve in &spec.reserves {
+            let mint = ctx.get_mint(&addresses.reserves[&reserve.name].mint).await.unwrap();
+            assert_eq!(mint.decimals, reserve.decimals);
+        }
+        let sol = addresses.reserves["SOL"].clone();
+        let reserve: Reserve = ctx.get_anchor_account(&sol.reserve).await.unwrap();
+        assert_eq!(reserve.config.elevation_groups[0], 1);
+        assert_eq!(Some(reserve.farm_collateral), sol.collateral_farm);
+
+        let deployed = addresses.clone();
+        ctx.set_reserve_price(&sol.reserve, 31.0).await.unwrap();
+        let slot = ctx.refresh_reserve(&sol.reserve).await.unwrap().last_update.slot;
+        deploy_market(&mut ctx, &spec, &mut addresses).await.unwrap();
+        assert_eq!(addresses, deployed);
+        let reserve: Reserve = ctx.get_anchor_account(&sol.reserve).await.unwrap();
+        assert_eq!(reserve.last_update.slot, slot);
+        // The price set since is not reset to the spec's.
+        let reserve = ctx.refresh_reserve(&sol.reserve).await.unwrap();
+        assert_eq!(reserve.market_price(), Fraction::from_num(31));
+    }
+}
@@ -0,0 +1,255 @@
+//! Account fixtures in the JSON format written by
+//! `solana account --output json`, as found under `deps`.
+
+use std::{borrow::Cow, error::Error, fs, path::Path, str::FromStr};
+
+use anchor_lang::{AccountDeserialize, AccountSerialize};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use kamino_lend::{
+    state::{LendingMarket, Obligation, Reserve},
+    ID as KAMINO_LENDING_ID,
+};
+use pyth_sdk_solana::state::SolanaPriceAccount;
+use serde::{Deserialize, Serialize};
+use solana_sdk::{
+    account::Account,
+    address_lookup_table::{
+        self,
+        state::{AddressLookupTable, LookupTableMeta},
+    },
+    pubkey,
+    pubkey::Pubkey,
+};
+
+use crate::switchboard::{SwitchboardAggregator, SWITCHBOARD_V2_ID};
+
+pub const PYTH_ORACLE_ID: Pubkey = pubkey!("FsJ3A3u2vn5cTVofAjvy6y5kwABJAqYWpe4975bi2epH");
+
+/// Decimals of the prices under `deps/prices`, which the synthetic oracle
+/// prices use too.
+pub const PRICE_DECIMALS: u32 = 8;
+
+/// A USD `price` in units of `10^-PRICE_DECIMALS`.
+pub fn scale_price(price: f64) -> i64 { (price * 10f64.powi(PRICE_DECIMALS as i32)).round() as i64 }
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AccountFixture {
+    pub pubkey: Pubkey,
+    pub account: Account,
+    /// Written by newer CLIs only; kept so fixtures round-trip.
+    pub space: Option<u64>,
+}
+
+/// A fixture's data decoded according to its owner.
+#[derive(Clone)]
+pub enum DecodedAccount {
+    LendingMarket(Box<LendingMarket>),
+    Reserve(Box<Reserve>),
+    Obligation(Box<Obligation>),
+    PythPrice(Box<SolanaPriceAccount>),
+    SwitchboardAggregator(SwitchboardAggregator),
+    AddressLookupTable {
+        meta: LookupTableMeta,
+        addresses: Vec<Pubkey>,
+    },
+    /// Accounts of other owners and other account types, e.g. IDLs.
+    Other,
+}
+
+impl AccountFixture {
+    pub fn new(pubkey: Pubkey, account: Account) -> Self { Self { pubkey, account, space: None } }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, Box<dyn Error>> {
+        let path = path.as_ref();
+        Self::from_json(&fs::read_to_string(path)?)
+            .map_err(|e| format!("{}: {e}", path.display()).into())
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), Box<dyn Error>> {
+        Ok(fs::write(path, self.to_json()?)?)
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, Box<dyn Error>> {
+        let fixture: FixtureJson = serde_json::from_str(json)?;
+        let (data, encoding) = fixture.account.data;
+        if encoding != "base64" {
+            return Err(format!("unsupported data encoding {encoding}").into());
+        }
+        Ok(Self {
+            pubkey: Pubkey::from_str(&fixture.pubkey)?,
+            account: Account {
+                lamports: fixture.account.lamports,
+                data: STANDARD.decode(data)?,
+                owner: Pubkey::from_str(&fixture.account.owner)?,
+                executable: fixture.account.executable,
+                rent_epoch: fixture.account.rent_epoch,
+            },
+            space: fixture.account.space,
+        })
+    }
+
+    /// Pretty-printed like the CLI output, including the trailing newline.
+    pub fn to_json(&self) -> Result<String, Box<dyn Error>> {
+        let fixture = FixtureJson {
+            pubkey: self.pubkey.to_string(),
+            account: FixtureAccountJson {
+                lamports: self.account.lamports,
+                data: (STANDARD.encode(&self.account.data), "base64".to_string()),
+                owner: self.account.owner.to_string(),
+                executable: self.account.executable,
+                rent_epoch: self.account.rent_epoch,
+                space: self.space,
+            },
+        };
+        Ok(serde_json::to_string_pretty(&fixture)? + "\n")
+    }
+
+    pub fn decode(&self) -> Result<DecodedAccount, Box<dyn Error>> {
+        let data = self.account.data.as_slice();
+        let owner = self.account.owner;
+        let decoded = if owner == KAMINO_LENDING_ID {
+            if let Ok(market) = LendingMarket::try_deserialize(&mut &data[..]) {
+                DecodedAccount::LendingMarket(Box::new(market))
+            } else if let Ok(reserve) = Reserve::try_deserialize(&mut &data[..]) {
+                DecodedAccount::Reserve(Box::new(reserve))
+            } else if let Ok(obligation) = Obligation::try_deserialize(&mut &data[..]) {
+                DecodedAccount::Obligation(Box::new(obligation))
+            } else {
+                DecodedAccount::Other
+            }
+        } else if owner == PYTH_ORACLE_ID {
+            pyth_sdk_solana::state::load_price_account::<32, ()>(data)?;
+            DecodedAccount::PythPrice(Box::new(bytemuck::pod_read_unaligned(
+                &data[..std::mem::size_of::<SolanaPriceAccount>()],
+            )))
+        } else if owner == SWITCHBOARD_V2_ID {
+            match SwitchboardAggregator::decode(data) {
+                Ok(aggregator) => DecodedAccount::SwitchboardAggregator(aggregator),
+                Err(_) => DecodedAccount::Other,
+            }
+        } else if owner == address_lookup_table::program::ID {
+            let table = AddressLookupTable::deserialize(data)?;
+            DecodedAccount::AddressLookupTable {
+                meta: table.meta,
+                addresses: table.addresses.into_owned(),
+            }
+        } else {
+            DecodedAccount::Other
+        };
+        Ok(decoded)
+    }
+
+    /// Writes `decoded` back into the account data, e.g. after repricing a
+    /// feed. `Other` leaves the data unchanged.
+    pub fn encode(&mut self, decoded: &DecodedAccount) -> Result<(), Box<dyn Error>> {
+        let data = &mut self.account.data;
+        match decoded {
+            DecodedAccount::LendingMarket(market) => serialize_anchor(market.as_ref(), data)?,
+            DecodedAccount::Reserve(reserve) => serialize_anchor(reserve.as_ref(), data)?,
+            DecodedAccount::Obligation(obligation) => serialize_anchor(obligation.as_ref(), data)?,
+            DecodedAccount::PythPrice(price) => {
+                let bytes = bytemuck::bytes_of(price.as_ref());
+                data.get_mut(..bytes.len())
+                    .ok_or("account is too small for a pyth price")?
+                    .copy_from_slice(bytes);
+            }
+            DecodedAccount::SwitchboardAggregator(aggregator) => aggregator.encode_into(data)?,
+            DecodedAccount::AddressLookupTable { meta, addresses } => {
+                let table =
+                    AddressLookupTable { meta: meta.clone(), addresses: Cow::Borrowed(addresses) };
+                *data = table.serialize_for_tests()?;
+            }
+            DecodedAccount::Other => {}
+        }
+        Ok(())
+    }
+}
+
+fn serialize_anchor<T: AccountSerialize>(
+    account: &T,
+    data: &mut [u8],
+) -> Result<(), Box<dyn Error>> {
+    let mut encoded = Vec::with_capacity(data.len());
+    account.try_serialize(&mut encoded)?;
+    if encoded.len() > data.len() {
+        return Err("encoded account does not fit the fixture".into());
+    }
+    data[..encoded.len()].copy_from_slice(&encoded);
+    Ok(())
+}
+
+#[derive(Serialize, Deserialize)]
+struct FixtureJson {
+    pubkey: String,
+    account: FixtureAccountJson,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FixtureAccountJson {
+    lamports: u64,
+    data: (String, String),
+    owner: String,
+    executable: bool,
+    rent_epoch: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    space: Option<u64>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::deps_dir;
+
+    #[test]
+    fn test_fixtures_round_trip() {
+        let dirs = ["farms", "kamino", "klend", "lookup", "prices", "pyth", "scope", "switchboard"];
+        for path in dirs.iter().flat_map(|dir| fs::read_dir(deps_dir().join(dir)).unwrap()) {
+            let path = path.unwrap().path();
+            let json = fs::read_to_string(&path).unwrap();
+            let mut fixture = AccountFixture::from_json(&json).unwrap();
+            assert_eq!(fixture.to_json().unwrap(), json, "{}", path.display());
+
+            let account = fixture.account.clone();
+            let decoded = fixture.decode().unwrap();
+            fixture.encode(&decoded).unwrap();
+            assert_eq!(fixture.account, account, "{}", path.display());
+        }
+    }
+
+    #[test]
+    fn test_decode_by_owner() {
+        let decode = |fixture: &str| {
+            AccountFixture::load(deps_dir().join(fixture)).unwrap().decode().unwrap()
+        };
+        assert!(matches!(decode("klend/market.json"), DecodedAccount::LendingMarket(_)));
+        assert!(matches!(decode("klend/idl-mainnet.json"), DecodedAccount::Other));
+        assert!(matches!(decode("lookup/33EucPaS4a588jJJn1Ld3Ka9ye15VpgRLvjVTEPtZLCa.json"),
+            DecodedAccount::AddressLookupTable { addresses, .. } if !addresses.is_empty()));
+        let DecodedAccount::SwitchboardAggregator(aggregator) =
+            decode("switchboard/kUSDH-USDC_orca.json")
+        else {
+            panic!("not an aggregator");
+        };
+        assert!(aggregator.name.starts_with(b"kUSDH-USDC_Orca/USD"));
+        assert_eq!(aggregator.latest_confirmed_round.result.scale, 8);
+    }
+
+    #[test]
+    fn test_reprice_pyth_fixture() {
+        let path = deps_dir().join("prices/sol-20usd.json");
+        let mut fixture = AccountFixture::load(path).unwrap();
+        let DecodedAccount::PythPrice(mut price) = fixture.decode().unwrap() else {
+            panic!("not a pyth price");
+        };
+        assert_eq!(price.agg.price, 20 * 10i64.pow(8));
+        price.agg.price = 35 * 10i64.pow(8);
+        fixture.encode(&DecodedAccount::PythPrice(price)).unwrap();
+
+        let reloaded = AccountFixture::from_json(&fixture.to_json().unwrap()).unwrap();
+        let DecodedAccount::PythPrice(price) = reloaded.decode().unwrap() else {
+            panic!("not a pyth price");
+        };
+        assert_eq!(price.agg.price, 35 * 10i64.pow(8));
+    }
+}
@@ -0,0 +1,105 @@
+//! Flash loan fees of reserves in a deployed market.
+
+use std::error::Error;
+
+use kamino_client::{
+    instruction::update_single_reserve_config, reserve_config::ReserveConfigUpdate,
+};
+use kamino_lend::{state::Reserve, Fraction, FractionExtra};
+use solana_sdk::{pubkey::Pubkey, signature::Signer};
+
+use crate::KaminoTestContext;
+
+impl KaminoTestContext {
+    /// Sets the flash loan fee of `reserve`, in a market owned by the payer,
+    /// to `fee_bps`.
+    pub async fn set_flash_loan_fee(
+        &mut self,
+        reserve: &Pubkey,
+        fee_bps: u16,
+    ) -> Result<(), Box<dyn Error>> {
+        let state: Reserve = self.get_anchor_account(reserve).await?;
+        let fee_sf = Fraction::from_bps(fee_bps).to_bits() as u64;
+        let update = update_single_reserve_config(
+            &self.payer().pubkey(),
+            &state.lending_market,
+            reserve,
+            &ReserveConfigUpdate::FlashLoanFeeSf(fee_sf),
+        )?;
+        self.process_instructions(&[update], &[]).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use kamino_client::{
+        error::{InstructionFailure, KaminoClientError},
+        flash_loan::{compose_flash_loan, validate_flash_loans, ComputeBudget},
+        instruction::{flash_repay_reserve_liquidity, ReserveAccounts},
+        token::KLEND_TOKEN_PROGRAM,
+    };
+    use kamino_lend::LendingError;
+    use solana_sdk::native_token::LAMPORTS_PER_SOL;
+    use spl_token::instruction::mint_to;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_flash_loan() {
+        let mut ctx = KaminoTestContext::start().await.unwrap();
+        let addresses = ctx.deploy_local_market().await.unwrap();
+        let sol = addresses.reserves["SOL"].clone();
+        ctx.supply_liquidity(&sol.reserve, 100 * LAMPORTS_PER_SOL).await.unwrap();
+        ctx.set_flash_loan_fee(&sol.reserve, 30).await.unwrap();
+        let payer = ctx.payer().pubkey();
+
+        // The payer, as mint authority, covers the fee between the borrow and
+        // the repay.
+        let reserve: Reserve = ctx.get_anchor_account(&sol.reserve).await.unwrap();
+        let accounts = ReserveAccounts::from_state(&sol.reserve, &reserve, &KLEND_TOKEN_PROGRAM);
+        let liquidity_account = accounts.user_liquidity_ata(&payer);
+        let amount = 10 * LAMPORTS_PER_SOL;
+        let cover_fee =
+            mint_to(&spl_token::ID, &sol.mint, &liquidity_account, &payer, &[], 30_000_000)
+                .unwrap();
+        let compute_budget =
+            ComputeBudget { unit_limit: Some(400_000), unit_price_micro_lamports: Some(1) };
+        let loan = compose_flash_loan(
+            &payer,
+            &sol.reserve,
+            &reserve,
+            None,
+            amount,
+            &compute_budget,
+            [cover_fee],
+        )
+        .unwrap();
+        assert_eq!((loan.borrow_index, loan.repay_index, loan.fee), (3, 5, 30_000_000));
+
+        // klend agrees with the offline check on a repay pointing elsewhere.
+        let mut misindexed = loan.instructions.clone();
+        misindexed[5] = flash_repay_reserve_liquidity(&payer, &accounts, None, amount, 2).unwrap();
+        let KaminoClientError::Program(expected) = validate_flash_loans(&misindexed).unwrap_err()
+        else {
+            panic!("not a program failure");
+        };
+        assert_eq!(expected.lending_error(), Some(LendingError::InvalidFlashRepay));
+        let error = ctx.process_instructions(&misindexed, &[]).await.unwrap_err();
+        let failure = InstructionFailure::from_transaction_error(&error.unwrap()).unwrap();
+        assert_eq!(
+            (failure.index, failure.lending_error()),
+            (expected.index, expected.lending_error())
+        );
+
+        let fee_vault = ctx.get_token_balance(&accounts.fee_vault).await.unwrap();
+        ctx.process_instructions(&loan.instructions, &[]).await.unwrap();
+        assert_eq!(ctx.get_token_balance(&liquidity_account).await.unwrap(), 0);
+        let collected = ctx.get_token_balance(&accounts.fee_vault).await.unwrap() - fee_vault;
+        let reserve: Reserve = ctx.get_anchor_account(&sol.reserve).await.unwrap();
+        assert_eq!(
+            reserve.liquidity.available_amount + collected,
+            100 * LAMPORTS_PER_SOL + loan.fee
+        );
+    }
+}
@@ -0,0 +1,145 @@
+//! In-process counterpart of `deps/test-validator-params.sh`: boots the
+//! programs under `deps/programs` and the account fixtures the script hands to
+//! `solana-test-validator` in a `solana-program-test` bank.
+//!
+//! The scope prices account the script clones from mainnet is not loaded;
+//! reserves priced through scope need their prices set with
+//! [`KaminoTestContext::set_scope_prices`] or
+//! [`KaminoTestContext::set_reserve_price`].
+
+pub mod context;
+pub mod deploy;
+pub mod fixtures;
+pub mod flash_loan;
+pub mod liquidation;
+pub mod obligation;
+pub mod pyth;
+pub mod scenario;
+pub mod scope;
+pub mod switchboard;
+#[cfg(test)]
+mod test_util;
+pub mod token;
+
+use std::{
+    error::Error,
+    fs,
+    path::{Path, PathBuf},
+};
+
+pub use context::KaminoTestContext;
+pub use fixtures::{AccountFixture, DecodedAccount};
+use solana_program_test::ProgramTest;
+use solana_sdk::{account::Account, bpf_loader, pubkey, pubkey::Pubkey, rent::Rent};
+
+/// Programs deployed by the test validator, with their file under
+/// `deps/programs`.
+pub const PROGRAMS: &[(Pubkey, &str)] = &[
+    (pubkey!("E6qbhrt4pFmCotNUSSEh6E5cRQCEJpMcd79Z56EG9KY"), "kamino.so"),
+    (pubkey!("HFn8GnPADiny6XqUoWE8uRPPxb29ikn4yTuPa9MF2fWJ"), "scope.so"),
+    (pubkey!("KLend2g3cP87fffoy8q1mQqGKjrxjC8boSyAYavgmjD"), "kamino_lending.so"),
+    (pubkey!("metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s"), "metaplex.so"),
+    (pubkey!("devi51mZmdwUJGU9hjN27vEz64Gps7uUefqxg27EAtH"), "raydium.so"),
+    (pubkey!("whirLbMiicVdio4qvUfM5KAg6Ct8VwpYzGff3uctyCc"), "whirlpool.so"),
+    (pubkey!("FarmsPZpWu9i7Kky8tPN37rs2TpmMrAZrC7S7vJa91Hr"), "farms.so"),
+];
+
+/// Account fixtures loaded by the test validator, relative to `deps`.
+pub const ACCOUNT_FIXTURES: &[&str] = &[
+    "kamino/global-config.json",
+    "klend/idl-mainnet.json",
+    "klend/market.json",
+    "pyth/Gnt27xtC473ZT2Mw5u8wZ68Z3gULkSTb5DuxJy7eJotD.json",
+    "lookup/33EucPaS4a588jJJn1Ld3Ka9ye15VpgRLvjVTEPtZLCa.json",
+    "switchboard/idl.json",
+    "switchboard/2bpwkRWDEXHWYNBDddKssz6te82zCqwLR8qhR2acUtep.json",
+    "switchboard/kUSDH-USDC_orca.json",
+    "prices/H6ARHf6YXhGYeQfUzQNGk6rDNnLBQKrenN712K4AQJEG.json",
+    "prices/E4v1BBgoso9s64TQvmyownAVJbhbEPGyzA3qn4n46qj9.json",
+    "prices/Bt1hEbY62aMriY1SyQqbeZbm8VmSbQVGBFzSzMuVNWzN.json",
+    "prices/sol-20usd.json",
+    "prices/sol-2usd.json",
+    "prices/sol-1usd.json",
+    "prices/stsol-20usd.json",
+    "prices/usdc-1usd.json",
+    "farms/6UodrBjL2ZreDy7QdR4YV1oxqMBjVYSEyrFpctqqwGwL.json",
+    "farms/idl-mainnet.json",
+    "scope/idl-mainnet.json",
+];
+
+/// Compute limit of every transaction; klend's refreshes do not fit the
+/// default.
+const COMPUTE_MAX_UNITS: u64 = 1_400_000;
+
+pub fn deps_dir() -> PathBuf { Path::new(env!("CARGO_MANIFEST_DIR")).join("../../deps") }
+
+/// A `ProgramTest` with every program and fixture of the test validator.
+/// Further programs and accounts can be added before starting it.
+pub fn program_test() -> Result<ProgramTest, Box<dyn Error>> {
+    let deps = deps_dir();
+    let mut program_test = ProgramTest::default();
+    program_test.set_compute_max_units(COMPUTE_MAX_UNITS);
+    for (program_id, file) in PROGRAMS {
+        let path = deps.join("programs").join(file);
+        let data = fs::read(&path).map_err(|e| {
+            format!("{}: {e}; run deps/dump-from-mainnet.sh to fetch it", path.display())
+        })?;
+        program_test.add_account(*program_id, program_account(data));
+    }
+    for fixture in ACCOUNT_FIXTURES {
+        let fixture = AccountFixture::load(deps.join(fixture))?;
+        program_test.add_account(fixture.pubkey, fixture.account);
+    }
+    Ok(program_test)
+}
+
+fn program_account(data: Vec<u8>) -> Account {
+    Account {
+        lamports: Rent::default().minimum_balance(data.len()).max(1),
+        data,
+        owner: bpf_loader::id(),
+        executable: true,
+        rent_epoch: 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use kamino_lend::state::LendingMarket;
+    use solana_sdk::signature::Signer;
+
+    use super::*;
+    use crate::pyth::PythPrice;
+
+    #[tokio::test]
+    async fn test_fixtures_are_loaded() {
+        let mut ctx = KaminoTestContext::start().await.unwrap();
+        let market = pubkey!("7u3HeHxYDLhnCoErrtycNokbQYbWGzLs6JSDqGAv5PfF");
+        let market: LendingMarket = ctx.get_anchor_account(&market).await.unwrap();
+        assert_ne!(market.lending_market_owner, Pubkey::default());
+        for (program_id, _) in PROGRAMS {
+            assert!(ctx.get_account(program_id).await.unwrap().executable);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_set_pyth_price_overwrites_fixture() {
+        let mut ctx = KaminoTestContext::start().await.unwrap();
+        let feed = pubkey!("E4v1BBgoso9s64TQvmyownAVJbhbEPGyzA3qn4n46qj9");
+        ctx.set_pyth_price(&feed, &PythPrice::from_usd(35.0)).await.unwrap();
+        let fixture = AccountFixture::new(feed, ctx.get_account(&feed).await.unwrap());
+        let DecodedAccount::PythPrice(price) = fixture.decode().unwrap() else {
+            panic!("not a pyth price");
+        };
+        assert_eq!(price.agg.price, 3_500_000_000);
+    }
+
+    #[tokio::test]
+    async fn test_klend_runs_in_process() {
+        let mut ctx = KaminoTestContext::start().await.unwrap();
+        let owner = ctx.payer().pubkey();
+        let lending_market = ctx.create_lending_market().await.unwrap();
+        let market: LendingMarket = ctx.get_anchor_account(&lending_market).await.unwrap();
+        assert_eq!(market.lending_market_owner, owner);
+    }
+}
@@ -0,0 +1,103 @@
+//! Liquidators of obligations in a deployed market.
+
+use std::error::Error;
+
+use solana_sdk::{
+    native_token::LAMPORTS_PER_SOL,
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    system_instruction,
+};
+
+use crate::KaminoTestContext;
+
+impl KaminoTestContext {
+    /// A liquidator funded with 1 SOL for rent and `amount` of `mint` to
+    /// repay with, and its token account of `mint`.
+    pub async fn create_liquidator(
+        &mut self,
+        mint: &Pubkey,
+        amount: u64,
+    ) -> Result<(Keypair, Pubkey), Box<dyn Error>> {
+        let liquidator = Keypair::new();
+        let fund = system_instruction::transfer(
+            &self.payer().pubkey(),
+            &liquidator.pubkey(),
+            LAMPORTS_PER_SOL,
+        );
+        self.process_instructions(&[fund], &[]).await?;
+        let repay_account = self.mint_to(mint, &liquidator.pubkey(), amount).await?;
+        Ok((liquidator, repay_account))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use kamino_client::{
+        actions::{plan_borrow, Amount},
+        error::InstructionFailure,
+        liquidation::{plan_liquidate, quote_liquidation, LiquidationQuote},
+    };
+    use kamino_lend::{state::Obligation, Fraction, FractionExtra, LendingError};
+
+    use super::*;
+    use crate::test_util::usdc_depositor;
+
+    #[tokio::test]
+    async fn test_liquidate() {
+        // $600 of SOL borrowed against $1,000 of USDC.
+        let mut ctx = KaminoTestContext::start().await.unwrap();
+        let owner = Keypair::new();
+        let (addresses, obligation) = usdc_depositor(&mut ctx, &owner, None).await;
+        let (sol, usdc) = (addresses.reserves["SOL"].clone(), addresses.reserves["USDC"].clone());
+        let state = ctx.get_obligation_state(&obligation, &[sol.reserve]).await.unwrap();
+        let borrow =
+            plan_borrow(&owner.pubkey(), &state, &sol.reserve, None, 30 * LAMPORTS_PER_SOL)
+                .unwrap();
+        ctx.process_instructions(&borrow, &[&owner]).await.unwrap();
+        let state = ctx.get_obligation_state(&obligation, &[]).await.unwrap();
+        assert!(quote_liquidation(&state, &sol.reserve, &usdc.reserve, Amount::Max).is_err());
+
+        // At $31 the debt is over the 90% liquidation threshold.
+        ctx.set_reserve_price(&sol.reserve, 31.0).await.unwrap();
+        ctx.refresh_reserve(&sol.reserve).await.unwrap();
+        ctx.refresh_reserve(&usdc.reserve).await.unwrap();
+        let state = ctx.get_obligation_state(&obligation, &[]).await.unwrap();
+        let quote = quote_liquidation(&state, &sol.reserve, &usdc.reserve, Amount::Max).unwrap();
+        // LTV 93% against a 90% threshold, and a close factor of 20% of $930.
+        assert_eq!(quote.bonus_rate, Fraction::from_percent(3u8));
+        assert_eq!(quote.repay_amount, 6 * LAMPORTS_PER_SOL);
+        assert!(quote.is_profitable());
+
+        let (liquidator, repay_account) =
+            ctx.create_liquidator(&sol.mint, quote.repay_amount).await.unwrap();
+        let accounts = state.reserve_accounts(&usdc.reserve).unwrap();
+
+        // klend refuses to take less collateral than the plan accepts.
+        let greedy = LiquidationQuote {
+            withdraw_collateral_amount: quote.withdraw_collateral_amount + 1,
+            ..quote.clone()
+        };
+        let instructions = plan_liquidate(&liquidator.pubkey(), &state, &greedy, 0).unwrap();
+        let error = ctx.process_instructions(&instructions, &[&liquidator]).await.unwrap_err();
+        let failure = InstructionFailure::from_transaction_error(&error.unwrap()).unwrap();
+        assert_eq!(failure.lending_error(), Some(LendingError::LiquidationSlippageError));
+
+        let fee_vault = ctx.get_token_balance(&accounts.fee_vault).await.unwrap();
+        let instructions = plan_liquidate(&liquidator.pubkey(), &state, &quote, 50).unwrap();
+        ctx.process_instructions(&instructions, &[&liquidator]).await.unwrap();
+        assert_eq!(ctx.get_token_balance(&repay_account).await.unwrap(), 0);
+        let received = ctx
+            .get_token_balance(&accounts.user_liquidity_ata(&liquidator.pubkey()))
+            .await
+            .unwrap();
+        let fee = ctx.get_token_balance(&accounts.fee_vault).await.unwrap() - fee_vault;
+        assert_eq!((received, fee), (quote.received_amount, quote.protocol_fee));
+        let liquidated: Obligation = ctx.get_anchor_account(&obligation).await.unwrap();
+        assert_eq!(
+            liquidated.deposits[0].deposited_amount,
+            1_000_000_000 - quote.withdraw_collateral_amount
+        );
+        assert_eq!(liquidated.borrows[0].borrowed_amount().to_num::<u64>(), 24 * LAMPORTS_PER_SOL);
+    }
+}
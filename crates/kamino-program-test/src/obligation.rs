@@ -0,0 +1,400 @@
+//! Users with obligations in a deployed market, and the obligation state the
+//! client's planners read.
+
+use std::error::Error;
+
+use kamino_client::{
+    actions::ObligationState,
+    instruction::{
+        deposit_reserve_liquidity, init_obligation, init_user_metadata, refresh_reserve,
+        ReserveAccounts,
+    },
+    obligation::ObligationType,
+    pda,
+    token::{create_associated_token_account, KLEND_TOKEN_PROGRAM},
+};
+use kamino_lend::state::{Obligation, Reserve};
+use solana_sdk::{
+    native_token::LAMPORTS_PER_SOL,
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    system_instruction,
+};
+
+use crate::KaminoTestContext;
+
+impl KaminoTestContext {
+    /// Funds `owner` with 1 SOL for rent, creates its user metadata, with
+    /// the owner of `referrer_user_metadata` as referrer, and opens its
+    /// vanilla obligation in `lending_market`.
+    pub async fn open_obligation(
+        &mut self,
+        owner: &Keypair,
+        lending_market: &Pubkey,
+        referrer_user_metadata: Option<&Pubkey>,
+    ) -> Result<Pubkey, Box<dyn Error>> {
+        let obligation_type = ObligationType::default();
+        let instructions = [
+            system_instruction::transfer(&self.payer().pubkey(), &owner.pubkey(), LAMPORTS_PER_SOL),
+            init_user_metadata(&owner.pubkey(), referrer_user_metadata)?,
+            init_obligation(&owner.pubkey(), lending_market, &spl_token::ID, &obligation_type)?,
+        ];
+        self.process_instructions(&instructions, &[owner]).await?;
+        Ok(obligation_type.address(lending_market, &owner.pubkey()))
+    }
+
+    /// Creates the user metadata of `user`, e.g. a referrer, without an
+    /// obligation.
+    pub async fn create_user_metadata(&mut self, user: &Keypair) -> Result<Pubkey, Box<dyn Error>> {
+        let instructions = [
+            system_instruction::transfer(&self.payer().pubkey(), &user.pubkey(), LAMPORTS_PER_SOL),
+            init_user_metadata(&user.pubkey(), None)?,
+        ];
+        self.process_instructions(&instructions, &[user]).await?;
+        Ok(pda::get_user_metadata(&user.pubkey()))
+    }
+
+    /// Mints `amount` of `reserve`'s liquidity to the payer and deposits it,
+    /// so that borrowers have liquidity to draw. Returns the payer's
+    /// collateral account.
+    pub async fn supply_liquidity(
+        &mut self,
+        reserve: &Pubkey,
+        amount: u64,
+    ) -> Result<Pubkey, Box<dyn Error>> {
+        let payer = self.payer().pubkey();
+        let state: Reserve = self.get_anchor_account(reserve).await?;
+        let accounts = ReserveAccounts::from_state(reserve, &state, &KLEND_TOKEN_PROGRAM);
+        self.mint_to(&accounts.liquidity_mint, &payer, amount).await?;
+        let instructions = [
+            create_associated_token_account(
+                &payer,
+                &payer,
+                &accounts.collateral_mint,
+                &accounts.token_program,
+            ),
+            refresh_reserve(reserve, &state)?,
+            deposit_reserve_liquidity(&payer, &accounts, amount)?,
+        ];
+        self.process_instructions(&instructions, &[]).await?;
+        Ok(accounts.user_collateral_ata(&payer))
+    }
+
+    /// The state of `obligation` with its reserves and `extra_reserves`.
+    pub async fn get_obligation_state(
+        &mut self,
+        obligation: &Pubkey,
+        extra_reserves: &[Pubkey],
+    ) -> Result<ObligationState, Box<dyn Error>> {
+        let state: Obligation = self.get_anchor_account(obligation).await?;
+        let mut reserves = std::collections::HashMap::new();
+        for reserve in ObligationState::reserve_addresses(&state, extra_reserves) {
+            reserves.insert(reserve, self.get_anchor_account(&reserve).await?);
+        }
+        Ok(ObligationState {
+            address: *obligation,
+            lending_market: self.get_anchor_account(&state.lending_market).await?,
+            reserves,
+            obligation: state,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use kamino_client::{
+        actions::{plan_borrow, plan_repay, plan_withdraw, Amount},
+        collateral::{
+            plan_deposit_collateral, plan_deposit_liquidity, plan_redeem_collateral, quote_deposit,
+            quote_redeem,
+        },
+        error::{InstructionFailure, KaminoClientError},
+        health::{collateral_to_liquidity, max_withdraw_collateral},
+        instruction::{
+            init_obligation_farms_for_reserve, init_referrer_token_state,
+            update_single_reserve_config,
+            withdraw_obligation_collateral_and_redeem_reserve_collateral,
+        },
+        refresh::{plan_refreshes, ObligationAction},
+        reserve_config::ReserveConfigUpdate,
+    };
+    use kamino_lend::{state::ReferrerTokenState, typedefs::ReserveFarmKind, LendingError};
+    use solana_sdk::{instruction::InstructionError, transaction::TransactionError};
+
+    use super::*;
+    use crate::test_util::usdc_depositor;
+
+    #[tokio::test]
+    async fn test_init_obligation_types() {
+        let mut ctx = KaminoTestContext::start().await.unwrap();
+        let addresses = ctx.deploy_local_market().await.unwrap();
+        let market = addresses.lending_market.unwrap();
+        let (coll_mint, debt_mint) =
+            (addresses.reserves["SOL"].mint, addresses.reserves["USDC"].mint);
+        let owner = Keypair::new();
+        let vanilla = ctx.open_obligation(&owner, &market, None).await.unwrap();
+        let obligation: Obligation = ctx.get_anchor_account(&vanilla).await.unwrap();
+        assert_eq!((obligation.tag, obligation.owner), (0, owner.pubkey()));
+
+        let obligation_types = [
+            ObligationType::Multiply { coll_mint, debt_mint },
+            ObligationType::Lending { mint: debt_mint },
+            ObligationType::Leverage { coll_mint, debt_mint },
+        ];
+        for obligation_type in obligation_types {
+            let init = init_obligation(&owner.pubkey(), &market, &spl_token::ID, &obligation_type)
+                .unwrap();
+            ctx.process_instructions(&[init], &[&owner]).await.unwrap();
+            let address = obligation_type.address(&market, &owner.pubkey());
+            let obligation: Obligation = ctx.get_anchor_account(&address).await.unwrap();
+            assert_eq!(obligation.tag, u64::from(obligation_type.tag()));
+            assert_eq!(obligation.owner, owner.pubkey());
+        }
+
+        // klend only takes obligation id 0, the last byte of the arguments,
+        // even for an obligation at the address id 1 derives.
+        let obligation_type = ObligationType::Lending { mint: coll_mint };
+        let mut init =
+            init_obligation(&owner.pubkey(), &market, &spl_token::ID, &obligation_type).unwrap();
+        *init.data.last_mut().unwrap() = 1;
+        init.accounts[2].pubkey =
+            pda::get_obligation(&market, &owner.pubkey(), 2, 1, &coll_mint, &coll_mint);
+        let error = ctx.process_instructions(&[init], &[&owner]).await.unwrap_err();
+        let failure = InstructionFailure::from_transaction_error(&error.unwrap()).unwrap();
+        assert_eq!(failure.lending_error(), Some(LendingError::InvalidObligationId));
+    }
+
+    #[tokio::test]
+    async fn test_borrow_and_repay() {
+        let mut ctx = KaminoTestContext::start().await.unwrap();
+        let owner = Keypair::new();
+        let (addresses, obligation) = usdc_depositor(&mut ctx, &owner, None).await;
+        let sol = addresses.reserves["SOL"].clone();
+
+        let state = ctx.get_obligation_state(&obligation, &[sol.reserve]).await.unwrap();
+        let error = plan_repay(&owner.pubkey(), &state, &sol.reserve, Amount::Max);
+        assert!(error.is_err());
+        let instructions =
+            plan_borrow(&owner.pubkey(), &state, &sol.reserve, None, 2 * LAMPORTS_PER_SOL).unwrap();
+        ctx.process_instructions(&instructions, &[&owner]).await.unwrap();
+        let sol_account = ctx.create_token_account(&sol.mint, &owner.pubkey()).await.unwrap();
+        assert_eq!(ctx.get_token_balance(&sol_account).await.unwrap(), 2 * LAMPORTS_PER_SOL);
+        let borrowed: Obligation = ctx.get_anchor_account(&obligation).await.unwrap();
+        assert_eq!(borrowed.borrows[0].borrow_reserve, sol.reserve);
+
+        ctx.warp_seconds(3_600).await.unwrap();
+        ctx.set_reserve_price(&sol.reserve, 20.0).await.unwrap();
+        ctx.set_reserve_price(&addresses.reserves["USDC"].reserve, 1.0).await.unwrap();
+        ctx.mint_to(&sol.mint, &owner.pubkey(), LAMPORTS_PER_SOL).await.unwrap();
+        let state = ctx.get_obligation_state(&obligation, &[]).await.unwrap();
+        let instructions = plan_repay(&owner.pubkey(), &state, &sol.reserve, Amount::Max).unwrap();
+        ctx.process_instructions(&instructions, &[&owner]).await.unwrap();
+        let repaid: Obligation = ctx.get_anchor_account(&obligation).await.unwrap();
+        assert_eq!(repaid.has_debt, 0);
+        let balance = ctx.get_token_balance(&sol_account).await.unwrap();
+        assert!(balance < LAMPORTS_PER_SOL, "repaid without interest: {balance}");
+    }
+
+    #[tokio::test]
+    async fn test_borrow_with_referrer() {
+        let mut ctx = KaminoTestContext::start().await.unwrap();
+        let referrer = Keypair::new();
+        let referrer_user_metadata = ctx.create_user_metadata(&referrer).await.unwrap();
+        let owner = Keypair::new();
+        let (addresses, obligation) =
+            usdc_depositor(&mut ctx, &owner, Some(&referrer_user_metadata)).await;
+        let sol = addresses.reserves["SOL"].clone();
+        let state = ctx.get_obligation_state(&obligation, &[sol.reserve]).await.unwrap();
+        assert_eq!(state.obligation.referrer, referrer.pubkey());
+
+        let borrow = plan_borrow(
+            &owner.pubkey(),
+            &state,
+            &sol.reserve,
+            Some(&referrer.pubkey()),
+            LAMPORTS_PER_SOL,
+        )
+        .unwrap();
+        // klend panics on a missing referrer token state rather than failing
+        // with a lending error.
+        let error = ctx.process_instructions(&borrow, &[&owner]).await.unwrap_err();
+        assert_eq!(
+            error.unwrap(),
+            TransactionError::InstructionError(4, InstructionError::ProgramFailedToComplete)
+        );
+
+        let init = init_referrer_token_state(
+            &owner.pubkey(),
+            &referrer.pubkey(),
+            &state.reserve_accounts(&sol.reserve).unwrap(),
+        )
+        .unwrap();
+        let instructions: Vec<_> = [init].into_iter().chain(borrow).collect();
+        ctx.process_instructions(&instructions, &[&owner]).await.unwrap();
+        let token_state: ReferrerTokenState = ctx
+            .get_anchor_account(&pda::get_referrer_token_state(&referrer.pubkey(), &sol.reserve))
+            .await
+            .unwrap();
+        assert_eq!(token_state.mint, sol.mint);
+    }
+
+    #[tokio::test]
+    async fn test_withdraw() {
+        let mut ctx = KaminoTestContext::start().await.unwrap();
+        let owner = Keypair::new();
+        let (addresses, obligation) = usdc_depositor(&mut ctx, &owner, None).await;
+        let (sol, usdc) = (addresses.reserves["SOL"].clone(), addresses.reserves["USDC"].clone());
+        let state = ctx.get_obligation_state(&obligation, &[sol.reserve]).await.unwrap();
+        let borrow =
+            plan_borrow(&owner.pubkey(), &state, &sol.reserve, None, 2 * LAMPORTS_PER_SOL).unwrap();
+        ctx.process_instructions(&borrow, &[&owner]).await.unwrap();
+        let usdc_account = ctx.create_token_account(&usdc.mint, &owner.pubkey()).await.unwrap();
+
+        // $40 of debt against $1,000 at 80% LTV leaves $950 withdrawable.
+        let now = ctx.clock().await.unwrap().unix_timestamp as u64;
+        let state = ctx.get_obligation_state(&obligation, &[]).await.unwrap();
+        let error =
+            plan_withdraw(&owner.pubkey(), &state, &usdc.reserve, Amount::Exact(951_000_000), now)
+                .unwrap_err();
+        assert!(matches!(error, KaminoClientError::InvalidInput(_)), "{error}");
+
+        let market = addresses.lending_market.unwrap();
+        let payer = ctx.payer().pubkey();
+        // Deposits count against the interval's total, so reset it too.
+        let updates = [
+            ReserveConfigUpdate::DepositWithdrawalCap {
+                capacity: 100_000_000,
+                interval_length_seconds: 86_400,
+            },
+            ReserveConfigUpdate::DepositWithdrawalCapCurrentTotal(0),
+        ];
+        for update in &updates {
+            let update =
+                update_single_reserve_config(&payer, &market, &usdc.reserve, update).unwrap();
+            ctx.process_instructions(&[update], &[]).await.unwrap();
+        }
+        let state = ctx.get_obligation_state(&obligation, &[]).await.unwrap();
+        let error =
+            plan_withdraw(&owner.pubkey(), &state, &usdc.reserve, Amount::Exact(200_000_000), now)
+                .unwrap_err();
+        let KaminoClientError::WithdrawalCapReached { amount, remaining, .. } = error else {
+            panic!("{error}");
+        };
+        assert_eq!((amount, remaining), (200_000_000, 100_000_000));
+        // klend would reject the same withdrawal.
+        let withdraw = withdraw_obligation_collateral_and_redeem_reserve_collateral(
+            &owner.pubkey(),
+            &obligation,
+            &state.reserve_accounts(&usdc.reserve).unwrap(),
+            200_000_000,
+        )
+        .unwrap();
+        let plan = plan_refreshes(
+            &owner.pubkey(),
+            &obligation,
+            &state.obligation,
+            &ObligationAction::Withdraw { reserve: usdc.reserve },
+            &state.reserves,
+        )
+        .unwrap();
+        let error = ctx.process_instructions(&plan.wrap([withdraw]), &[&owner]).await.unwrap_err();
+        let failure = InstructionFailure::from_transaction_error(&error.unwrap()).unwrap();
+        assert_eq!(failure.lending_error(), Some(LendingError::WithdrawalCapReached));
+
+        // Max stops at the cap.
+        let instructions =
+            plan_withdraw(&owner.pubkey(), &state, &usdc.reserve, Amount::Max, now).unwrap();
+        ctx.process_instructions(&instructions, &[&owner]).await.unwrap();
+        assert_eq!(ctx.get_token_balance(&usdc_account).await.unwrap(), 100_000_000);
+        let state = ctx.get_obligation_state(&obligation, &[]).await.unwrap();
+        let error =
+            plan_withdraw(&owner.pubkey(), &state, &usdc.reserve, Amount::Max, now).unwrap_err();
+        assert!(matches!(error, KaminoClientError::WithdrawalCapReached { remaining: 0, .. }));
+
+        // Without a cap, Max leaves the obligation at its allowed borrow
+        // value.
+        let cap =
+            ReserveConfigUpdate::DepositWithdrawalCap { capacity: 0, interval_length_seconds: 0 };
+        let update = update_single_reserve_config(&payer, &market, &usdc.reserve, &cap).unwrap();
+        ctx.process_instructions(&[update], &[]).await.unwrap();
+        let state = ctx.get_obligation_state(&obligation, &[]).await.unwrap();
+        let instructions =
+            plan_withdraw(&owner.pubkey(), &state, &usdc.reserve, Amount::Max, now).unwrap();
+        ctx.process_instructions(&instructions, &[&owner]).await.unwrap();
+        let withdrawn = ctx.get_token_balance(&usdc_account).await.unwrap();
+        assert!(withdrawn.abs_diff(950_000_000) < 1_000_000, "withdrew {withdrawn}");
+        let state = ctx.get_obligation_state(&obligation, &[]).await.unwrap();
+        let max = max_withdraw_collateral(
+            &state.obligation,
+            &state.lending_market,
+            &state.reserves,
+            &usdc.reserve,
+        )
+        .unwrap();
+        assert!(collateral_to_liquidity(state.reserve(&usdc.reserve).unwrap(), max) < 1_000_000);
+    }
+
+    #[tokio::test]
+    async fn test_collateral_tokens() {
+        let mut ctx = KaminoTestContext::start().await.unwrap();
+        let borrower = Keypair::new();
+        let (addresses, obligation) = usdc_depositor(&mut ctx, &borrower, None).await;
+        let sol = addresses.reserves["SOL"].clone();
+        let state = ctx.get_obligation_state(&obligation, &[sol.reserve]).await.unwrap();
+        let borrow =
+            plan_borrow(&borrower.pubkey(), &state, &sol.reserve, None, 30 * LAMPORTS_PER_SOL)
+                .unwrap();
+        ctx.process_instructions(&borrow, &[&borrower]).await.unwrap();
+        // Interest accrued on the borrow moves the exchange rate off 1.
+        ctx.warp_seconds(30 * 86_400).await.unwrap();
+        ctx.set_reserve_price(&sol.reserve, 20.0).await.unwrap();
+        ctx.set_reserve_price(&addresses.reserves["USDC"].reserve, 1.0).await.unwrap();
+
+        let owner = Keypair::new();
+        let market = addresses.lending_market.unwrap();
+        let obligation = ctx.open_obligation(&owner, &market, None).await.unwrap();
+        let liquidity_account =
+            ctx.mint_to(&sol.mint, &owner.pubkey(), 10 * LAMPORTS_PER_SOL).await.unwrap();
+        let reserve: Reserve = ctx.get_anchor_account(&sol.reserve).await.unwrap();
+        let accounts = ReserveAccounts::from_state(&sol.reserve, &reserve, &KLEND_TOKEN_PROGRAM);
+        let collateral_account = accounts.user_collateral_ata(&owner.pubkey());
+
+        let clock = ctx.clock().await.unwrap();
+        let minted = quote_deposit(&reserve, clock.slot, 10 * LAMPORTS_PER_SOL).unwrap();
+        assert!(minted < 10 * LAMPORTS_PER_SOL);
+        let deposit =
+            plan_deposit_liquidity(&owner.pubkey(), &sol.reserve, &reserve, 10 * LAMPORTS_PER_SOL)
+                .unwrap();
+        ctx.process_instructions(&deposit, &[&owner]).await.unwrap();
+        assert_eq!(ctx.get_token_balance(&collateral_account).await.unwrap(), minted);
+
+        let reserve: Reserve = ctx.get_anchor_account(&sol.reserve).await.unwrap();
+        let redeemed = quote_redeem(&reserve, clock.slot, minted / 2).unwrap();
+        let redeem =
+            plan_redeem_collateral(&owner.pubkey(), &sol.reserve, &reserve, minted / 2, &clock)
+                .unwrap();
+        ctx.process_instructions(&redeem, &[&owner]).await.unwrap();
+        assert_eq!(ctx.get_token_balance(&liquidity_account).await.unwrap(), redeemed);
+
+        let remaining = minted - minted / 2;
+        let state = ctx.get_obligation_state(&obligation, &[sol.reserve]).await.unwrap();
+        // SOL has a collateral farm the obligation has to join first.
+        let init_farm = init_obligation_farms_for_reserve(
+            &owner.pubkey(),
+            &owner.pubkey(),
+            &obligation,
+            &accounts,
+            &reserve.farm_collateral,
+            ReserveFarmKind::Collateral,
+        )
+        .unwrap();
+        let deposit =
+            plan_deposit_collateral(&owner.pubkey(), &state, &sol.reserve, remaining).unwrap();
+        let instructions: Vec<_> = [init_farm].into_iter().chain(deposit).collect();
+        ctx.process_instructions(&instructions, &[&owner]).await.unwrap();
+        assert_eq!(ctx.get_token_balance(&collateral_account).await.unwrap(), 0);
+        let deposited: Obligation = ctx.get_anchor_account(&obligation).await.unwrap();
+        assert_eq!(deposited.deposits[0].deposit_reserve, sol.reserve);
+        assert_eq!(deposited.deposits[0].deposited_amount, remaining);
+    }
+}
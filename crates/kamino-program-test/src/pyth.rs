@@ -0,0 +1,168 @@
+//! Synthetic Pyth v2 price accounts, in place of the hand-patched mainnet
+//! dumps under `deps/prices`.
+
+use std::{error::Error, str::FromStr};
+
+use pyth_sdk_solana::state::{
+    AccountType, PriceInfo, PriceStatus, PriceType, Rational, SolanaPriceAccount, MAGIC, VERSION_2,
+};
+use solana_sdk::{account::Account, pubkey::Pubkey, rent::Rent};
+
+use crate::fixtures::{scale_price, AccountFixture, PRICE_DECIMALS, PYTH_ORACLE_ID};
+
+/// The feed values of a synthetic price account. The EMA and the previous
+/// price are set to the current price.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PythPrice {
+    /// Price in units of `10^expo`.
+    pub price: i64,
+    pub conf: u64,
+    pub expo: i32,
+    pub status: PriceStatus,
+    /// Unix timestamp of the aggregate price.
+    pub publish_time: i64,
+    pub slot: u64,
+}
+
+impl Default for PythPrice {
+    fn default() -> Self {
+        Self {
+            price: 0,
+            conf: 0,
+            expo: -(PRICE_DECIMALS as i32),
+            status: PriceStatus::Trading,
+            publish_time: 0,
+            slot: 0,
+        }
+    }
+}
+
+impl PythPrice {
+    /// A trading price of `price` USD.
+    pub fn from_usd(price: f64) -> Self { Self { price: scale_price(price), ..Default::default() } }
+
+    pub fn to_price_account(&self) -> SolanaPriceAccount {
+        let info = PriceInfo {
+            price: self.price,
+            conf: self.conf,
+            status: self.status,
+            pub_slot: self.slot,
+            ..Default::default()
+        };
+        SolanaPriceAccount {
+            magic: MAGIC,
+            ver: VERSION_2,
+            atype: AccountType::Price as u32,
+            size: std::mem::size_of::<SolanaPriceAccount>() as u32,
+            ptype: PriceType::Price,
+            expo: self.expo,
+            num: 1,
+            num_qt: 1,
+            last_slot: self.slot,
+            valid_slot: self.slot,
+            ema_price: Rational { val: self.price, numer: self.price, denom: 1 },
+            ema_conf: Rational { val: self.conf as i64, numer: self.conf as i64, denom: 1 },
+            timestamp: self.publish_time,
+            min_pub: 1,
+            prev_slot: self.slot,
+            prev_price: self.price,
+            prev_conf: self.conf,
+            prev_timestamp: self.publish_time,
+            agg: info,
+            ..Default::default()
+        }
+    }
+
+    /// A rent-exempt account owned by the Pyth oracle program.
+    pub fn to_account(&self) -> Account {
+        let data = bytemuck::bytes_of(&self.to_price_account()).to_vec();
+        Account {
+            lamports: Rent::default().minimum_balance(data.len()),
+            data,
+            owner: PYTH_ORACLE_ID,
+            executable: false,
+            rent_epoch: 0,
+        }
+    }
+
+    pub fn to_fixture(&self, address: &Pubkey) -> AccountFixture {
+        AccountFixture::new(*address, self.to_account())
+    }
+}
+
+pub fn parse_price_status(status: &str) -> Result<PriceStatus, Box<dyn Error>> {
+    Ok(match status.to_ascii_lowercase().as_str() {
+        "unknown" => PriceStatus::Unknown,
+        "trading" => PriceStatus::Trading,
+        "halted" => PriceStatus::Halted,
+        "auction" => PriceStatus::Auction,
+        "ignored" => PriceStatus::Ignored,
+        _ => return Err(format!("unknown price status {status}").into()),
+    })
+}
+
+/// Parses the arguments of the `pyth-price` binary:
+/// `<address> <price> [--conf N] [--expo N] [--status S] [--publish-time T]
+/// [--slot N]`.
+pub fn parse_args(
+    args: impl IntoIterator<Item = String>,
+) -> Result<(Pubkey, PythPrice), Box<dyn Error>> {
+    let mut args = args.into_iter();
+    let usage = "usage: pyth-price <address> <price> [--conf N] [--expo N] [--status S] \
+                 [--publish-time T] [--slot N]";
+    let address = Pubkey::from_str(&args.next().ok_or(usage)?)?;
+    let mut price = PythPrice { price: args.next().ok_or(usage)?.parse()?, ..Default::default() };
+    while let Some(flag) = args.next() {
+        let value = args.next().ok_or(usage)?;
+        match flag.as_str() {
+            "--conf" => price.conf = value.parse()?,
+            "--expo" => price.expo = value.parse()?,
+            "--status" => price.status = parse_price_status(&value)?,
+            "--publish-time" => price.publish_time = value.parse()?,
+            "--slot" => price.slot = value.parse()?,
+            _ => return Err(usage.into()),
+        }
+    }
+    Ok((address, price))
+}
+
+#[cfg(test)]
+mod tests {
+    use pyth_sdk_solana::state::load_price_account;
+
+    use super::*;
+    use crate::{deps_dir, DecodedAccount};
+
+    #[test]
+    fn test_price_account_loads() {
+        let spec = PythPrice { conf: 5, publish_time: 1_700_000_000, ..PythPrice::from_usd(21.5) };
+        let account = spec.to_account();
+        let loaded = load_price_account::<32, ()>(&account.data).unwrap();
+        let price = loaded.to_price_feed(&Pubkey::new_unique()).get_price_unchecked();
+        assert_eq!((price.price, price.conf, price.expo), (2_150_000_000, 5, -8));
+        assert_eq!(price.publish_time, 1_700_000_000);
+        assert_eq!(loaded.agg.status, PriceStatus::Trading);
+    }
+
+    #[test]
+    fn test_matches_deps_fixture_format() {
+        let existing = AccountFixture::load(deps_dir().join("prices/sol-1usd.json")).unwrap();
+        let synthetic = PythPrice::from_usd(1.0).to_fixture(&existing.pubkey);
+        assert_eq!(synthetic.account.lamports, existing.account.lamports);
+        assert_eq!(synthetic.account.data.len(), existing.account.data.len());
+        let DecodedAccount::PythPrice(price) = synthetic.decode().unwrap() else {
+            panic!("not a pyth price");
+        };
+        assert_eq!(price.agg.price, 100_000_000);
+    }
+
+    #[test]
+    fn test_parse_args() {
+        let args =
+            ["11111111111111111111111111111111", "150", "--expo", "-1", "--status", "halted"];
+        let (address, price) = parse_args(args.map(String::from)).unwrap();
+        assert_eq!(address, Pubkey::default());
+        assert_eq!((price.price, price.expo, price.status), (150, -1, PriceStatus::Halted));
+        assert!(parse_args(["x".to_string()]).is_err());
+    }
+}
@@ -0,0 +1,332 @@
+//! Moving prices and time mid-test, to reach klend's staleness, margin call
+//! and withdrawal-cap paths.
+
+use std::error::Error;
+
+use kamino_client::{instruction::refresh_reserve, oracle::ReserveOracles};
+use kamino_lend::state::Reserve;
+use solana_sdk::{clock::Clock, pubkey::Pubkey};
+
+use crate::{
+    pyth::PythPrice,
+    scope::{oracle_prices_account, ScopePrice, UNUSED_CHAIN_ENTRY},
+    switchboard::{AggregatorRound, SwitchboardAggregator, SwitchboardDecimal},
+    KaminoTestContext,
+};
+
+/// Slot duration the clock is warped with, as on mainnet.
+pub const MS_PER_SLOT: u64 = 400;
+
+impl KaminoTestContext {
+    pub async fn clock(&mut self) -> Result<Clock, Box<dyn Error>> {
+        Ok(self.context.banks_client.get_sysvar::<Clock>().await?)
+    }
+
+    /// Advances the bank by `slots` and the clock's timestamp by the time
+    /// they take.
+    pub async fn warp_slots(&mut self, slots: u64) -> Result<Clock, Box<dyn Error>> {
+        let seconds = slots * MS_PER_SLOT / 1000;
+        self.warp(slots.max(1), seconds as i64).await
+    }
+
+    /// Advances the clock's timestamp by `seconds` and the bank by the slots
+    /// that take, at least one.
+    pub async fn warp_seconds(&mut self, seconds: i64) -> Result<Clock, Box<dyn Error>> {
+        let slots = (seconds.max(0) as u64 * 1000 / MS_PER_SLOT).max(1);
+        self.warp(slots, seconds).await
+    }
+
+    async fn warp(&mut self, slots: u64, seconds: i64) -> Result<Clock, Box<dyn Error>> {
+        let clock = self.clock().await?;
+        self.context
+            .warp_to_slot(clock.slot + slots)
+            .map_err(|e| format!("cannot warp {slots} slots: {e:?}"))?;
+        let warped =
+            Clock { unix_timestamp: clock.unix_timestamp + seconds, ..self.clock().await? };
+        self.context.set_sysvar(&warped);
+        Ok(warped)
+    }
+
+    /// Writes the decoded fields of `aggregator` over the account at
+    /// `address`, creating it if it does not exist.
+    pub async fn set_switchboard_aggregator(
+        &mut self,
+        address: &Pubkey,
+        aggregator: &SwitchboardAggregator,
+    ) -> Result<(), Box<dyn Error>> {
+        let account = match self.context.banks_client.get_account(*address).await? {
+            Some(mut account) => {
+                aggregator.encode_into(&mut account.data)?;
+                account
+            }
+            None => aggregator.to_account(),
+        };
+        self.overwrite_account(address, account).await
+    }
+
+    /// Writes `prices` at their indexes of the scope price table at
+    /// `address`, creating an empty table if it does not exist.
+    pub async fn set_scope_prices(
+        &mut self,
+        address: &Pubkey,
+        prices: &[(u16, ScopePrice)],
+    ) -> Result<(), Box<dyn Error>> {
+        let mut account = match self.context.banks_client.get_account(*address).await? {
+            Some(account) => account,
+            None => oracle_prices_account(),
+        };
+        for (index, price) in prices {
+            price.encode_into(&mut account.data, *index)?;
+        }
+        self.overwrite_account(address, account).await
+    }
+
+    /// Sets every oracle configured on `reserve` to `price`, published at
+    /// the current slot and time. The first entry of a scope chain gets the
+    /// price and the following ones 1, so the chain multiplies to `price`;
+    /// TWAP sources get the same price.
+    pub async fn set_reserve_price(
+        &mut self,
+        reserve: &Pubkey,
+        price: f64,
+    ) -> Result<(), Box<dyn Error>> {
+        let state: Reserve = self.get_anchor_account(reserve).await?;
+        let token_info = &state.config.token_info;
+        let oracles = ReserveOracles::from_token_info(token_info);
+        if oracles.is_empty() {
+            return Err(format!("reserve {reserve} has no oracle configured").into());
+        }
+        let clock = self.clock().await?;
+
+        if let Some(pyth) = oracles.pyth {
+            let pyth_price = PythPrice {
+                publish_time: clock.unix_timestamp,
+                slot: clock.slot,
+                ..PythPrice::from_usd(price)
+            };
+            self.set_pyth_price(&pyth, &pyth_price).await?;
+        }
+        for aggregator in
+            [oracles.switchboard_price, oracles.switchboard_twap].into_iter().flatten()
+        {
+            let round = AggregatorRound {
+                num_success: 1,
+                is_closed: true,
+                round_open_slot: clock.slot,
+                round_open_timestamp: clock.unix_timestamp,
+                result: SwitchboardDecimal::from_usd(price),
+                ..Default::default()
+            };
+            let mut state = match self.context.banks_client.get_account(aggregator).await? {
+                Some(account) => SwitchboardAggregator::decode(&account.data)?,
+                None => SwitchboardAggregator::default(),
+            };
+            state.latest_confirmed_round = round;
+            self.set_switchboard_aggregator(&aggregator, &state).await?;
+        }
+        if let Some(scope_prices) = oracles.scope_prices {
+            let scope = &token_info.scope_configuration;
+            // The price chain goes last so it wins over an unset TWAP chain
+            // sharing its indexes.
+            let entries: Vec<_> = [scope.twap_chain, scope.price_chain]
+                .iter()
+                .flat_map(|chain| {
+                    chain.iter().take_while(|&&index| index != UNUSED_CHAIN_ENTRY).enumerate()
+                })
+                .map(|(position, &index)| {
+                    let value = if position == 0 { price } else { 1.0 };
+                    let scope_price = ScopePrice {
+                        last_updated_slot: clock.slot,
+                        unix_timestamp: clock.unix_timestamp as u64,
+                        ..ScopePrice::from_usd(value)
+                    };
+                    (index, scope_price)
+                })
+                .collect();
+            self.set_scope_prices(&scope_prices, &entries).await?;
+        }
+        Ok(())
+    }
+
+    /// Runs `refreshReserve` on its own and returns the refreshed reserve.
+    pub async fn refresh_reserve(&mut self, reserve: &Pubkey) -> Result<Reserve, Box<dyn Error>> {
+        let state: Reserve = self.get_anchor_account(reserve).await?;
+        self.process_instructions(&[refresh_reserve(reserve, &state)?], &[]).await?;
+        self.get_anchor_account(reserve).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use kamino_client::{
+        error::InstructionFailure,
+        instruction::{
+            update_entire_reserve_config, update_reserve_config_to, update_single_reserve_config,
+        },
+        interest::borrow_rate_curve,
+        reserve_config::{validate, ReserveConfigUpdate},
+    };
+    use kamino_lend::{
+        typedefs::{CurvePoint, PythConfiguration, ReserveConfig, TokenInfo},
+        FractionExtra,
+    };
+    use solana_sdk::signature::Signer;
+
+    use super::*;
+
+    /// A market with one reserve priced by the Pyth feed `oracle`.
+    async fn reserve_with_pyth_oracle(ctx: &mut KaminoTestContext, oracle: Pubkey) -> Pubkey {
+        let owner = ctx.payer().pubkey();
+        let market = ctx.create_lending_market().await.unwrap();
+        let mint = ctx.create_mint(6).await.unwrap();
+        let reserve = ctx.create_reserve(&market, &mint, &spl_token::ID).await.unwrap();
+        let config = ReserveConfig {
+            token_info: TokenInfo {
+                name: *b"TEST\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0",
+                max_age_price_seconds: 60,
+                pyth_configuration: PythConfiguration { price: oracle },
+                ..Default::default()
+            },
+            borrow_rate_curve: borrow_rate_curve(&[
+                CurvePoint { utilization_rate_bps: 0, borrow_rate_bps: 0 },
+                CurvePoint { utilization_rate_bps: 10_000, borrow_rate_bps: 1_000 },
+            ]),
+            borrow_factor_pct: 100,
+            deleveraging_threshold_slots_per_bps: 7200,
+            ..Default::default()
+        };
+        let update = update_entire_reserve_config(&reserve, &owner, &market, config).unwrap();
+        ctx.process_instructions(&[update], &[]).await.unwrap();
+        reserve
+    }
+
+    #[tokio::test]
+    async fn test_warp() {
+        let mut ctx = KaminoTestContext::start().await.unwrap();
+        let before = ctx.clock().await.unwrap();
+        let after = ctx.warp_seconds(60).await.unwrap();
+        assert_eq!(after.unix_timestamp, before.unix_timestamp + 60);
+        assert_eq!(after.slot, before.slot + 150);
+        assert_eq!(ctx.clock().await.unwrap(), after);
+
+        let after = ctx.warp_slots(25).await.unwrap();
+        assert_eq!(ctx.clock().await.unwrap().slot, after.slot);
+        assert_eq!(after.unix_timestamp, before.unix_timestamp + 70);
+    }
+
+    #[tokio::test]
+    async fn test_reprice_and_refresh_reserve() {
+        let mut ctx = KaminoTestContext::start().await.unwrap();
+        let reserve = reserve_with_pyth_oracle(&mut ctx, Pubkey::new_unique()).await;
+
+        ctx.set_reserve_price(&reserve, 35.0).await.unwrap();
+        let state = ctx.refresh_reserve(&reserve).await.unwrap();
+        assert_eq!(state.market_price().to_decimal().to_string(), "35");
+        assert_eq!(state.last_update.stale, 0);
+
+        ctx.set_reserve_price(&reserve, 12.5).await.unwrap();
+        ctx.warp_slots(10).await.unwrap();
+        let state = ctx.refresh_reserve(&reserve).await.unwrap();
+        assert_eq!(state.market_price().to_decimal().to_string(), "12.5");
+        assert_eq!(state.last_update.slot, ctx.clock().await.unwrap().slot);
+    }
+
+    #[tokio::test]
+    async fn test_stale_price_after_max_age() {
+        let mut ctx = KaminoTestContext::start().await.unwrap();
+        let reserve = reserve_with_pyth_oracle(&mut ctx, Pubkey::new_unique()).await;
+        ctx.set_reserve_price(&reserve, 35.0).await.unwrap();
+        let fresh = ctx.refresh_reserve(&reserve).await.unwrap().last_update.price_status;
+
+        ctx.warp_seconds(61).await.unwrap();
+        let stale = ctx.refresh_reserve(&reserve).await.unwrap().last_update.price_status;
+        assert_ne!(stale, fresh);
+
+        ctx.set_reserve_price(&reserve, 35.0).await.unwrap();
+        let refreshed = ctx.refresh_reserve(&reserve).await.unwrap().last_update.price_status;
+        assert_eq!(refreshed, fresh);
+    }
+
+    #[tokio::test]
+    async fn test_update_single_reserve_config() {
+        let mut ctx = KaminoTestContext::start().await.unwrap();
+        let reserve = reserve_with_pyth_oracle(&mut ctx, Pubkey::new_unique()).await;
+        let market = ctx.get_anchor_account::<Reserve>(&reserve).await.unwrap().lending_market;
+        let owner = ctx.payer().pubkey();
+        let updates = [
+            ReserveConfigUpdate::DepositLimit(1_000),
+            ReserveConfigUpdate::DepositWithdrawalCap { capacity: 50, interval_length_seconds: 60 },
+            ReserveConfigUpdate::ScopePriceChain([3, 4, u16::MAX, u16::MAX]),
+            ReserveConfigUpdate::Name(
+                *b"RENAMED\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0",
+            ),
+        ];
+        for update in &updates {
+            let instruction =
+                update_single_reserve_config(&owner, &market, &reserve, update).unwrap();
+            ctx.process_instructions(&[instruction], &[]).await.unwrap();
+        }
+
+        let config = ctx.get_anchor_account::<Reserve>(&reserve).await.unwrap().config;
+        assert_eq!(config.deposit_limit, 1_000);
+        assert_eq!(config.deposit_withdrawal_cap.config_capacity, 50);
+        assert_eq!(config.deposit_withdrawal_cap.config_interval_length_seconds, 60);
+        assert_eq!(config.token_info.scope_configuration.price_chain, [3, 4, u16::MAX, u16::MAX]);
+        assert_eq!(&config.token_info.name[..7], b"RENAMED");
+    }
+
+    #[tokio::test]
+    async fn test_update_reserve_config_to() {
+        let mut ctx = KaminoTestContext::start().await.unwrap();
+        let reserve = reserve_with_pyth_oracle(&mut ctx, Pubkey::new_unique()).await;
+        let state: Reserve = ctx.get_anchor_account(&reserve).await.unwrap();
+        let owner = ctx.payer().pubkey();
+        let desired = ReserveConfig {
+            loan_to_value_pct: 70,
+            liquidation_threshold_pct: 80,
+            max_liquidation_bonus_bps: 500,
+            ..state.config
+        };
+        let instructions = update_reserve_config_to(
+            &owner,
+            &state.lending_market,
+            &reserve,
+            &state.config,
+            &desired,
+        )
+        .unwrap();
+        assert_eq!(instructions.len(), 3);
+        for instruction in instructions {
+            ctx.process_instructions(&[instruction], &[]).await.unwrap();
+        }
+
+        let config = ctx.get_anchor_account::<Reserve>(&reserve).await.unwrap().config;
+        assert_eq!((config.loan_to_value_pct, config.liquidation_threshold_pct), (70, 80));
+        assert_eq!(config.max_liquidation_bonus_bps, 500);
+    }
+
+    #[tokio::test]
+    async fn test_validate_matches_klend() {
+        let mut ctx = KaminoTestContext::start().await.unwrap();
+        let reserve = reserve_with_pyth_oracle(&mut ctx, Pubkey::new_unique()).await;
+        let state: Reserve = ctx.get_anchor_account(&reserve).await.unwrap();
+        let owner = ctx.payer().pubkey();
+        assert_eq!(validate(&state.config), Ok(()));
+
+        let mut invalid = [state.config; 5];
+        invalid[0].loan_to_value_pct = 10;
+        invalid[1].bad_debt_liquidation_bonus_bps = 100;
+        invalid[2].borrow_rate_curve.points[0].utilization_rate_bps = 1;
+        invalid[3].borrow_rate_curve.points[10].utilization_rate_bps = 10_001;
+        invalid[4].token_info.pyth_configuration.price = Pubkey::default();
+        for config in invalid {
+            let errors = validate(&config).unwrap_err();
+            let instruction =
+                update_entire_reserve_config(&reserve, &owner, &state.lending_market, config)
+                    .unwrap();
+            let err = ctx.process_instructions(&[instruction], &[]).await.unwrap_err();
+            let failure = InstructionFailure::from_transaction_error(&err.unwrap()).unwrap();
+            assert_eq!(failure.lending_error(), Some(errors[0].lending_error()), "{errors:?}");
+        }
+    }
+}
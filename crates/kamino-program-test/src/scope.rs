@@ -0,0 +1,106 @@
+//! Scope's `OraclePrices` account, the price table klend reads scope chains
+//! from.
+
+use std::error::Error;
+
+use solana_sdk::{account::Account, pubkey, pubkey::Pubkey, rent::Rent};
+
+use crate::fixtures::{scale_price, PRICE_DECIMALS};
+
+pub const SCOPE_ID: Pubkey = pubkey!("HFn8GnPADiny6XqUoWE8uRPPxb29ikn4yTuPa9MF2fWJ");
+
+/// `sha256("account:OraclePrices")[..8]`.
+pub const ORACLE_PRICES_DISCRIMINATOR: [u8; 8] = [89, 128, 118, 221, 6, 72, 180, 146];
+
+/// Index marking the unused entries of a reserve's price chain.
+pub const UNUSED_CHAIN_ENTRY: u16 = u16::MAX;
+
+pub const MAX_ENTRIES: usize = 512;
+
+const PRICES_OFFSET: usize = 8 + 32;
+const DATED_PRICE_LEN: usize = 56;
+pub const ORACLE_PRICES_LEN: usize = PRICES_OFFSET + MAX_ENTRIES * DATED_PRICE_LEN;
+
+/// One entry of the table: `value * 10^-exp`, with the slot and time it was
+/// last updated.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ScopePrice {
+    pub value: u64,
+    pub exp: u64,
+    pub last_updated_slot: u64,
+    pub unix_timestamp: u64,
+}
+
+impl ScopePrice {
+    /// `price` USD, which must not be negative.
+    pub fn from_usd(price: f64) -> Self {
+        Self { value: scale_price(price) as u64, exp: PRICE_DECIMALS.into(), ..Default::default() }
+    }
+
+    pub fn decode(data: &[u8], index: u16) -> Result<Self, Box<dyn Error>> {
+        let entry = entry(data, index)?;
+        let field = |i: usize| u64::from_le_bytes(entry[i * 8..(i + 1) * 8].try_into().unwrap());
+        Ok(Self {
+            value: field(0),
+            exp: field(1),
+            last_updated_slot: field(2),
+            unix_timestamp: field(3),
+        })
+    }
+
+    /// Writes the entry at `index`, leaving the reserved fields untouched.
+    pub fn encode_into(&self, data: &mut [u8], index: u16) -> Result<(), Box<dyn Error>> {
+        check_layout(data)?;
+        let start = PRICES_OFFSET + usize::from(index) * DATED_PRICE_LEN;
+        let encoded = [self.value, self.exp, self.last_updated_slot, self.unix_timestamp]
+            .map(u64::to_le_bytes)
+            .concat();
+        data.get_mut(start..start + encoded.len())
+            .ok_or_else(|| format!("scope index {index} is out of range"))?
+            .copy_from_slice(&encoded);
+        Ok(())
+    }
+}
+
+/// An empty, rent-exempt price table owned by the scope program.
+pub fn oracle_prices_account() -> Account {
+    let mut data = vec![0; ORACLE_PRICES_LEN];
+    data[..8].copy_from_slice(&ORACLE_PRICES_DISCRIMINATOR);
+    Account {
+        lamports: Rent::default().minimum_balance(data.len()),
+        data,
+        owner: SCOPE_ID,
+        executable: false,
+        rent_epoch: 0,
+    }
+}
+
+fn entry(data: &[u8], index: u16) -> Result<&[u8], Box<dyn Error>> {
+    check_layout(data)?;
+    let start = PRICES_OFFSET + usize::from(index) * DATED_PRICE_LEN;
+    data.get(start..start + DATED_PRICE_LEN)
+        .ok_or_else(|| format!("scope index {index} is out of range").into())
+}
+
+fn check_layout(data: &[u8]) -> Result<(), Box<dyn Error>> {
+    if data.len() < ORACLE_PRICES_LEN || data[..8] != ORACLE_PRICES_DISCRIMINATOR {
+        return Err("not a scope oracle prices account".into());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_price_round_trip() {
+        let mut account = oracle_prices_account();
+        let price =
+            ScopePrice { last_updated_slot: 7, unix_timestamp: 9, ..ScopePrice::from_usd(1.5) };
+        price.encode_into(&mut account.data, 511).unwrap();
+        assert_eq!(ScopePrice::decode(&account.data, 511).unwrap(), price);
+        assert_eq!(ScopePrice::decode(&account.data, 0).unwrap(), ScopePrice::default());
+        assert!(price.encode_into(&mut account.data, 512).is_err());
+    }
+}
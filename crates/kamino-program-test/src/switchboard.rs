@@ -0,0 +1,133 @@
+//! The part of Switchboard v2's `AggregatorAccountData` klend reads.
+//!
+//! The account is a packed C struct; only the name and the latest confirmed
+//! round are decoded, at their fixed offsets.
+
+use std::error::Error;
+
+use solana_sdk::{account::Account, pubkey, pubkey::Pubkey, rent::Rent};
+
+use crate::fixtures::{scale_price, PRICE_DECIMALS};
+
+pub const SWITCHBOARD_V2_ID: Pubkey = pubkey!("SW1TCH7qEPTdLsDHRgPuMQjbQxKdH2aBStViMFnt64f");
+
+/// `sha256("account:AggregatorAccountData")[..8]`.
+pub const AGGREGATOR_DISCRIMINATOR: [u8; 8] = [217, 230, 65, 101, 201, 162, 27, 125];
+
+/// Size of `AggregatorAccountData`, discriminator included.
+pub const AGGREGATOR_LEN: usize = 3851;
+
+const NAME_OFFSET: usize = 8;
+const LATEST_CONFIRMED_ROUND_OFFSET: usize = 341;
+const ROUND_LEN: usize = 65;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SwitchboardDecimal {
+    pub mantissa: i128,
+    pub scale: u32,
+}
+
+impl SwitchboardDecimal {
+    pub fn from_usd(price: f64) -> Self {
+        Self { mantissa: scale_price(price).into(), scale: PRICE_DECIMALS }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct AggregatorRound {
+    pub num_success: u32,
+    pub num_error: u32,
+    pub is_closed: bool,
+    pub round_open_slot: u64,
+    pub round_open_timestamp: i64,
+    pub result: SwitchboardDecimal,
+    pub std_deviation: SwitchboardDecimal,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SwitchboardAggregator {
+    pub name: [u8; 32],
+    pub latest_confirmed_round: AggregatorRound,
+}
+
+impl SwitchboardAggregator {
+    pub fn decode(data: &[u8]) -> Result<Self, Box<dyn Error>> {
+        check_layout(data)?;
+        let mut round = Reader(&data[LATEST_CONFIRMED_ROUND_OFFSET..]);
+        Ok(Self {
+            name: data[NAME_OFFSET..NAME_OFFSET + 32].try_into()?,
+            latest_confirmed_round: AggregatorRound {
+                num_success: u32::from_le_bytes(round.take()),
+                num_error: u32::from_le_bytes(round.take()),
+                is_closed: round.take::<1>()[0] != 0,
+                round_open_slot: u64::from_le_bytes(round.take()),
+                round_open_timestamp: i64::from_le_bytes(round.take()),
+                result: round.decimal(),
+                std_deviation: round.decimal(),
+            },
+        })
+    }
+
+    /// Writes the decoded fields over `data`, leaving the rest untouched.
+    pub fn encode_into(&self, data: &mut [u8]) -> Result<(), Box<dyn Error>> {
+        check_layout(data)?;
+        let round = &self.latest_confirmed_round;
+        data[NAME_OFFSET..NAME_OFFSET + 32].copy_from_slice(&self.name);
+        let encoded = [
+            &round.num_success.to_le_bytes()[..],
+            &round.num_error.to_le_bytes(),
+            &[u8::from(round.is_closed)],
+            &round.round_open_slot.to_le_bytes(),
+            &round.round_open_timestamp.to_le_bytes(),
+            &round.result.mantissa.to_le_bytes(),
+            &round.result.scale.to_le_bytes(),
+            &round.std_deviation.mantissa.to_le_bytes(),
+            &round.std_deviation.scale.to_le_bytes(),
+        ]
+        .concat();
+        data[LATEST_CONFIRMED_ROUND_OFFSET..LATEST_CONFIRMED_ROUND_OFFSET + ROUND_LEN]
+            .copy_from_slice(&encoded);
+        Ok(())
+    }
+
+    /// A rent-exempt aggregator holding only the decoded fields, owned by
+    /// the Switchboard v2 program.
+    pub fn to_account(&self) -> Account {
+        let mut data = vec![0; AGGREGATOR_LEN];
+        data[..8].copy_from_slice(&AGGREGATOR_DISCRIMINATOR);
+        self.encode_into(&mut data).expect("aggregator layout");
+        Account {
+            lamports: Rent::default().minimum_balance(data.len()),
+            data,
+            owner: SWITCHBOARD_V2_ID,
+            executable: false,
+            rent_epoch: 0,
+        }
+    }
+}
+
+fn check_layout(data: &[u8]) -> Result<(), Box<dyn Error>> {
+    if data.len() < LATEST_CONFIRMED_ROUND_OFFSET + ROUND_LEN
+        || data[..8] != AGGREGATOR_DISCRIMINATOR
+    {
+        return Err("not a switchboard v2 aggregator".into());
+    }
+    Ok(())
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl Reader<'_> {
+    fn take<const N: usize>(&mut self) -> [u8; N] {
+        let (head, tail) = self.0.split_at(N);
+        self.0 = tail;
+        head.try_into().unwrap()
+    }
+
+    fn decimal(&mut self) -> SwitchboardDecimal {
+        SwitchboardDecimal {
+            mantissa: i128::from_le_bytes(self.take()),
+            scale: u32::from_le_bytes(self.take()),
+        }
+    }
+}
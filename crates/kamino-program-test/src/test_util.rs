@@ -0,0 +1,46 @@
+use kamino_client::{
+    instruction::deposit_reserve_liquidity_and_obligation_collateral,
+    refresh::{plan_refreshes, ObligationAction},
+};
+use solana_sdk::{
+    native_token::LAMPORTS_PER_SOL,
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+};
+
+use crate::{deploy::MarketAddresses, KaminoTestContext};
+
+/// The local market with 100 SOL supplied, and an obligation of `owner`
+/// with 1,000 USDC deposited.
+pub async fn usdc_depositor(
+    ctx: &mut KaminoTestContext,
+    owner: &Keypair,
+    referrer_user_metadata: Option<&Pubkey>,
+) -> (MarketAddresses, Pubkey) {
+    let addresses = ctx.deploy_local_market().await.unwrap();
+    let market = addresses.lending_market.unwrap();
+    let obligation = ctx.open_obligation(owner, &market, referrer_user_metadata).await.unwrap();
+
+    let usdc = addresses.reserves["USDC"].clone();
+    ctx.mint_to(&usdc.mint, &owner.pubkey(), 1_000_000_000).await.unwrap();
+    let state = ctx.get_obligation_state(&obligation, &[usdc.reserve]).await.unwrap();
+    let deposit = deposit_reserve_liquidity_and_obligation_collateral(
+        &owner.pubkey(),
+        &obligation,
+        &state.reserve_accounts(&usdc.reserve).unwrap(),
+        1_000_000_000,
+    )
+    .unwrap();
+    let plan = plan_refreshes(
+        &owner.pubkey(),
+        &obligation,
+        &state.obligation,
+        &ObligationAction::Deposit { reserve: usdc.reserve },
+        &state.reserves,
+    )
+    .unwrap();
+    ctx.process_instructions(&plan.wrap([deposit]), &[owner]).await.unwrap();
+    let sol = &addresses.reserves["SOL"].reserve;
+    ctx.supply_liquidity(sol, 100 * LAMPORTS_PER_SOL).await.unwrap();
+    (addresses, obligation)
+}
@@ -0,0 +1,216 @@
+//! Token fixtures: mints of either token program, associated token
+//! accounts and wrapped SOL.
+
+use std::error::Error;
+
+use kamino_client::token::{
+    create_associated_token_account, create_mint, unwrap_sol, wrap_sol, MintInfo,
+};
+use solana_sdk::{
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+};
+use spl_token::native_mint;
+use spl_token_2022::{extension::StateWithExtensions, instruction::mint_to, state::Account};
+
+use crate::KaminoTestContext;
+
+/// Decimals of the mainnet mints reserve fixtures stand in for.
+pub const SOL_DECIMALS: u8 = native_mint::DECIMALS;
+pub const USDC_DECIMALS: u8 = 6;
+pub const BONK_DECIMALS: u8 = 5;
+
+/// A mint created by [`KaminoTestContext::create_mint_with`], with the
+/// payer as mint authority.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MintSpec {
+    pub token_program: Pubkey,
+    pub decimals: u8,
+    pub freeze_authority: Option<Pubkey>,
+    /// Minted to the payer on creation.
+    pub supply: u64,
+}
+
+impl Default for MintSpec {
+    fn default() -> Self {
+        Self {
+            token_program: spl_token::ID,
+            decimals: USDC_DECIMALS,
+            freeze_authority: None,
+            supply: 0,
+        }
+    }
+}
+
+impl KaminoTestContext {
+    /// Creates a classic token mint with the payer as mint authority.
+    pub async fn create_mint(&mut self, decimals: u8) -> Result<Pubkey, Box<dyn Error>> {
+        self.create_mint_with(&MintSpec { decimals, ..Default::default() }).await
+    }
+
+    pub async fn create_mint_with(&mut self, spec: &MintSpec) -> Result<Pubkey, Box<dyn Error>> {
+        let mint = Keypair::new();
+        let payer = self.payer().pubkey();
+        let instructions = create_mint(
+            &payer,
+            &mint.pubkey(),
+            &payer,
+            spec.freeze_authority.as_ref(),
+            spec.decimals,
+            &spec.token_program,
+        )?;
+        self.process_instructions(&instructions, &[&mint]).await?;
+        if spec.supply > 0 {
+            self.mint_to(&mint.pubkey(), &payer, spec.supply).await?;
+        }
+        Ok(mint.pubkey())
+    }
+
+    pub async fn get_mint(&mut self, mint: &Pubkey) -> Result<MintInfo, Box<dyn Error>> {
+        Ok(MintInfo::unpack(mint, &self.get_account(mint).await?)?)
+    }
+
+    /// Creates the associated token account of `owner` unless it exists, and
+    /// returns it.
+    pub async fn create_token_account(
+        &mut self,
+        mint: &Pubkey,
+        owner: &Pubkey,
+    ) -> Result<Pubkey, Box<dyn Error>> {
+        let payer = self.payer().pubkey();
+        let mint = self.get_mint(mint).await?;
+        let instruction =
+            create_associated_token_account(&payer, owner, &mint.address, &mint.token_program);
+        self.process_instructions(&[instruction], &[]).await?;
+        Ok(mint.associated_token_address(owner))
+    }
+
+    /// Mints `amount` of `mint` to the associated token account of `owner`,
+    /// creating it if needed, and returns that account.
+    pub async fn mint_to(
+        &mut self,
+        mint: &Pubkey,
+        owner: &Pubkey,
+        amount: u64,
+    ) -> Result<Pubkey, Box<dyn Error>> {
+        let payer = self.payer().pubkey();
+        let mint = self.get_mint(mint).await?;
+        let token_account = mint.associated_token_address(owner);
+        let instructions = [
+            create_associated_token_account(&payer, owner, &mint.address, &mint.token_program),
+            mint_to(&mint.token_program, &mint.address, &token_account, &payer, &[], amount)?,
+        ];
+        self.process_instructions(&instructions, &[]).await?;
+        Ok(token_account)
+    }
+
+    pub async fn get_token_balance(
+        &mut self,
+        token_account: &Pubkey,
+    ) -> Result<u64, Box<dyn Error>> {
+        let account = self.get_account(token_account).await?;
+        Ok(StateWithExtensions::<Account>::unpack(&account.data)?.base.amount)
+    }
+
+    /// Wraps `lamports` of the payer into the wrapped SOL account of
+    /// `owner`, creating it if needed, and returns that account.
+    pub async fn wrap_sol(
+        &mut self,
+        owner: &Pubkey,
+        lamports: u64,
+    ) -> Result<Pubkey, Box<dyn Error>> {
+        let payer = self.payer().pubkey();
+        self.process_instructions(&wrap_sol(&payer, owner, lamports)?, &[]).await?;
+        Ok(spl_associated_token_account::get_associated_token_address(owner, &native_mint::ID))
+    }
+
+    /// Closes the wrapped SOL account of `owner`, returning its lamports to
+    /// them.
+    pub async fn unwrap_sol(&mut self, owner: &Keypair) -> Result<(), Box<dyn Error>> {
+        self.process_instructions(&[unwrap_sol(&owner.pubkey())?], &[owner]).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use kamino_client::{
+        error::KaminoClientError,
+        token::{check_reserve_mint, transfer_checked, UnsupportedMint},
+    };
+    use solana_program_test::BanksClientError;
+    use solana_sdk::{instruction::InstructionError, transaction::TransactionError};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_token_fixtures() {
+        let mut ctx = KaminoTestContext::start().await.unwrap();
+        let payer = ctx.payer().pubkey();
+        let freeze_authority = Pubkey::new_unique();
+        for decimals in [SOL_DECIMALS, USDC_DECIMALS, BONK_DECIMALS] {
+            let spec = MintSpec {
+                decimals,
+                freeze_authority: Some(freeze_authority),
+                supply: 10u64.pow(decimals.into()),
+                ..Default::default()
+            };
+            let mint = ctx.create_mint_with(&spec).await.unwrap();
+            let info = ctx.get_mint(&mint).await.unwrap();
+            assert_eq!((info.decimals, info.supply), (decimals, spec.supply));
+            assert_eq!(info.freeze_authority, Some(freeze_authority));
+            let account = info.associated_token_address(&payer);
+            assert_eq!(ctx.get_token_balance(&account).await.unwrap(), spec.supply);
+        }
+
+        let mint = ctx.create_mint(BONK_DECIMALS).await.unwrap();
+        let owner = Keypair::new();
+        let account = ctx.create_token_account(&mint, &owner.pubkey()).await.unwrap();
+        ctx.create_token_account(&mint, &owner.pubkey()).await.unwrap();
+        ctx.mint_to(&mint, &owner.pubkey(), 5).await.unwrap();
+        ctx.mint_to(&mint, &owner.pubkey(), 7).await.unwrap();
+        assert_eq!(ctx.get_token_balance(&account).await.unwrap(), 12);
+
+        let wrapped = ctx.wrap_sol(&owner.pubkey(), 1_000_000).await.unwrap();
+        ctx.wrap_sol(&owner.pubkey(), 500_000).await.unwrap();
+        assert_eq!(ctx.get_token_balance(&wrapped).await.unwrap(), 1_500_000);
+        let rent = ctx.get_account(&wrapped).await.unwrap().lamports - 1_500_000;
+        ctx.unwrap_sol(&owner).await.unwrap();
+        assert!(ctx.context.banks_client.get_account(wrapped).await.unwrap().is_none());
+        let lamports = ctx.get_account(&owner.pubkey()).await.unwrap().lamports;
+        assert_eq!(lamports, 1_500_000 + rent);
+    }
+
+    #[tokio::test]
+    async fn test_token_2022_mint() {
+        let mut ctx = KaminoTestContext::start().await.unwrap();
+        let payer = ctx.payer().pubkey();
+        let spec =
+            MintSpec { token_program: spl_token_2022::ID, decimals: 9, ..Default::default() };
+        let mint = ctx.create_mint_with(&spec).await.unwrap();
+        let source = ctx.mint_to(&mint, &payer, 1_000).await.unwrap();
+        ctx.mint_to(&mint, &payer, 1_000).await.unwrap();
+        let recipient = Pubkey::new_unique();
+        let destination = ctx.mint_to(&mint, &recipient, 0).await.unwrap();
+
+        let info = ctx.get_mint(&mint).await.unwrap();
+        assert_eq!((info.token_program, info.supply), (spl_token_2022::ID, 2_000));
+        let transfer = transfer_checked(&source, &info, &destination, &payer, 300).unwrap();
+        ctx.process_instructions(&[transfer], &[]).await.unwrap();
+        assert_eq!(ctx.get_token_balance(&destination).await.unwrap(), 300);
+
+        let error = check_reserve_mint(&mint, &ctx.get_account(&mint).await.unwrap()).unwrap_err();
+        let KaminoClientError::UnsupportedMint { reasons, .. } = error else {
+            panic!("{error}");
+        };
+        assert_eq!(reasons, [UnsupportedMint::TokenProgram(spl_token_2022::ID)]);
+
+        let market = ctx.create_lending_market().await.unwrap();
+        let error = ctx.create_reserve(&market, &mint, &spl_token_2022::ID).await.unwrap_err();
+        let error = error.downcast::<BanksClientError>().unwrap();
+        assert_eq!(
+            error.unwrap(),
+            TransactionError::InstructionError(0, InstructionError::Custom(3007))
+        );
+    }
+}
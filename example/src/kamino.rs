@@ -1,4 +1,4 @@
-use std::{collections::HashMap, error::Error, str::FromStr};
+use std::{collections::HashMap, str::FromStr};
 
 use anchor_client::{
     anchor_lang::{
@@ -17,6 +17,15 @@ use anchor_client::{
 };
 use anchor_spl::mint;
 use encoding_rs::UTF_8;
+use kamino_client::{
+    error::KaminoClientError,
+    instruction::{self, ReserveAccounts},
+    obligation::ObligationType,
+    pda,
+    reserve_config::validate,
+    rpc::{get_account, get_anchor_account, send_transaction},
+    token::check_reserve_mint,
+};
 use kamino_lend::{
     accounts as kamino_accounts, instruction as kamino_instruction, state as kamino_state,
     typedefs as kamino_typedefs,
@@ -28,27 +37,20 @@ pub fn create_lending_market(
     program: &Program<&Keypair>,
     payer: &Keypair,
     lending_market: &Keypair,
-) -> Result<Signature, Box<dyn Error>> {
+) -> Result<Signature, KaminoClientError> {
     let size = std::mem::size_of::<kamino_state::LendingMarket>() as u64 + 8;
-    let market_authority = pda::get_market_authority(&lending_market.pubkey());
     let rpc_client = program.rpc();
-    let res = program
-        .request()
-        .instruction(create_account(
+    let instructions = [
+        create_account(
             &payer.pubkey(),
             &lending_market.pubkey(),
             rpc_client.get_minimum_balance_for_rent_exemption(size as usize)?,
             size,
             &KAMINO_LENDING_ID,
-        ))
-        .instruction(
-            instruction::init_lending_market(&payer.pubkey(), &lending_market.pubkey(), [0; 32])
-                .unwrap(),
-        )
-        .signer(payer)
-        .signer(lending_market)
-        .send()?;
-    Ok(res)
+        ),
+        instruction::init_lending_market(&payer.pubkey(), &lending_market.pubkey(), [0; 32])?,
+    ];
+    send_transaction(&rpc_client, &payer.pubkey(), &instructions, &[payer, lending_market])
 }
 
 pub fn init_reserve(
@@ -57,36 +59,27 @@ pub fn init_reserve(
     lending_market: &Keypair,
     reserve: &Keypair,
     mint: &Pubkey,
-    token_program_id: &Pubkey,
-) -> Result<Signature, Box<dyn Error>> {
+) -> Result<Signature, KaminoClientError> {
     let size = std::mem::size_of::<kamino_state::Reserve>() as u64 + 8;
-    let market_authority = pda::get_market_authority(&lending_market.pubkey());
-    let [reserve_liquidity_supply, reserve_collateral_mint, reserve_collateral_supply, reserve_fee_vault] =
-        get_reserve_pdas(&lending_market.pubkey(), mint);
     let rpc_client = program.rpc();
-    let res = program
-        .request()
-        .instruction(create_account(
+    let mint_info = check_reserve_mint(mint, &get_account(&rpc_client, mint)?)?;
+    let instructions = [
+        create_account(
             &payer.pubkey(),
             &reserve.pubkey(),
             rpc_client.get_minimum_balance_for_rent_exemption(size as usize)?,
             size,
             &KAMINO_LENDING_ID,
-        ))
-        .instruction(
-            instruction::init_reserve(
-                &lending_market.pubkey(),
-                &reserve.pubkey(),
-                mint,
-                &payer.pubkey(),
-                token_program_id,
-            )
-            .unwrap(),
-        )
-        .signer(payer)
-        .signer(reserve)
-        .send()?;
-    Ok(res)
+        ),
+        instruction::init_reserve(
+            &lending_market.pubkey(),
+            &reserve.pubkey(),
+            mint,
+            &payer.pubkey(),
+            &mint_info.token_program,
+        )?,
+    ];
+    send_transaction(&rpc_client, &payer.pubkey(), &instructions, &[payer, reserve])
 }
 
 pub fn update_reserve_config(
@@ -95,54 +88,38 @@ pub fn update_reserve_config(
     lending_market: &Pubkey,
     reserve: &Pubkey,
     reserve_config: kamino_typedefs::ReserveConfig,
-) -> Result<Signature, Box<dyn Error>> {
-    let res = program
-        .request()
-        .instruction(instruction::upadte_entire_reserve_config(
-            reserve,
-            &payer.pubkey(),
-            lending_market,
-            reserve_config,
-        )?)
-        .signer(payer)
-        .send()?;
-    Ok(res)
+) -> Result<Signature, KaminoClientError> {
+    validate(&reserve_config)?;
+    let instructions = [instruction::update_entire_reserve_config(
+        reserve,
+        &payer.pubkey(),
+        lending_market,
+        reserve_config,
+    )?];
+    send_transaction(&program.rpc(), &payer.pubkey(), &instructions, &[payer])
 }
 
 pub fn init_user_metadata(
     program: &Program<&Keypair>,
     payer: &Keypair,
-) -> Result<Signature, Box<dyn Error>> {
-    let res = program
-        .request()
-        .instruction(instruction::init_user_metadata(&payer.pubkey()).unwrap())
-        .signer(payer)
-        .send()?;
-    Ok(res)
+) -> Result<Signature, KaminoClientError> {
+    let instructions = [instruction::init_user_metadata(&payer.pubkey(), None)?];
+    send_transaction(&program.rpc(), &payer.pubkey(), &instructions, &[payer])
 }
 
 pub fn init_obligation(
     program: &Program<&Keypair>,
     payer: &Keypair,
     lending_market: &Pubkey,
-) -> Result<Signature, Box<dyn Error>> {
-    let res = program
-        .request()
-        .instruction(
-            instruction::init_obligation(
-                &payer.pubkey(),
-                lending_market,
-                &spl_token::ID,
-                0,
-                0,
-                [0; 32],
-                [0; 32],
-            )
-            .unwrap(),
-        )
-        .signer(payer)
-        .send()?;
-    Ok(res)
+    obligation_type: &ObligationType,
+) -> Result<Signature, KaminoClientError> {
+    let instructions = [instruction::init_obligation(
+        &payer.pubkey(),
+        lending_market,
+        &spl_token::ID,
+        obligation_type,
+    )?];
+    send_transaction(&program.rpc(), &payer.pubkey(), &instructions, &[payer])
 }
 
 pub fn deposit_reserve_liquidity_and_obligation_collateral(
@@ -153,25 +130,22 @@ pub fn deposit_reserve_liquidity_and_obligation_collateral(
     mint: &Pubkey,
     liquidity_amount: u64,
     token_program_id: &Pubkey,
-) -> Result<Signature, Box<dyn Error>> {
-    let pyth_oracle = Pubkey::from_str("E4v1BBgoso9s64TQvmyownAVJbhbEPGyzA3qn4n46qj9").unwrap();
-    let res = program
-        .request()
-        .instruction(instruction::refresh_reserve(reserve, lending_market, &pyth_oracle).unwrap())
-        .instruction(instruction::refresh_obligation(&payer.pubkey(), lending_market).unwrap())
-        .instruction(
-            instruction::deposit_reserve_liquidity_and_obligation_collateral(
-                lending_market,
-                &payer.pubkey(),
-                mint,
-                reserve,
-                &token_program_id,
-                liquidity_amount,
-            )
-            .unwrap(),
-        )
-        .signer(payer)
-        .send()?;
+) -> Result<Signature, KaminoClientError> {
+    let rpc_client = program.rpc();
+    let reserve_state: kamino_state::Reserve = get_anchor_account(&rpc_client, reserve)?;
+    let obligation = pda::get_user_obligation(lending_market, &payer.pubkey());
+    let obligation_state: kamino_state::Obligation = get_anchor_account(&rpc_client, &obligation)?;
+    let instructions = [
+        instruction::refresh_reserve(reserve, &reserve_state)?,
+        instruction::refresh_obligation(&obligation, &obligation_state)?,
+        instruction::deposit_reserve_liquidity_and_obligation_collateral(
+            &payer.pubkey(),
+            &obligation,
+            &ReserveAccounts::new(lending_market, reserve, mint, token_program_id),
+            liquidity_amount,
+        )?,
+    ];
+    let res = send_transaction(&rpc_client, &payer.pubkey(), &instructions, &[payer])?;
     println!("{:?}", res);
     Ok(res)
 }
@@ -185,225 +159,6 @@ pub fn get_reserve_pdas(lending_market: &Pubkey, mint: &Pubkey) -> [Pubkey; 4] {
     ];
 }
 
-pub mod instruction {
-    use std::error::Error;
-
-    use anchor_client::{
-        anchor_lang::{prelude::borsh::to_vec, InstructionData, ToAccountMetas},
-        solana_sdk::{
-            instruction::Instruction,
-            pubkey::Pubkey,
-            system_program::ID as SYSTEM_ID,
-            sysvar::{instructions::ID as SYSVAR_INSTRUCTIONS_ID, rent::ID as SYSVAR_RENT_ID},
-        },
-    };
-    use kamino_lend::{
-        accounts, instruction,
-        typedefs::{InitObligationArgs, ReserveConfig},
-        ID as KAMINO_LENDING_ID,
-    };
-    use spl_associated_token_account::get_associated_token_address;
-
-    use super::pda::get_user_obligation;
-    use crate::kamino::pda;
-
-    pub fn init_lending_market(
-        owner: &Pubkey,
-        lending_market: &Pubkey,
-        quote_currency: [u8; 32],
-    ) -> Result<Instruction, Box<dyn Error>> {
-        let market_authority = pda::get_market_authority(&lending_market);
-        Ok(Instruction {
-            program_id: KAMINO_LENDING_ID,
-            accounts: accounts::InitLendingMarket {
-                lending_market_owner: *owner,
-                lending_market: *lending_market,
-                lending_market_authority: market_authority,
-                system_program: SYSTEM_ID,
-                rent: SYSVAR_RENT_ID,
-            }
-            .to_account_metas(Some(true)),
-            data: instruction::InitLendingMarket { _quote_currency: quote_currency }.data(),
-        })
-    }
-
-    pub fn init_reserve(
-        lending_market: &Pubkey,
-        reserve: &Pubkey,
-        reserve_liquidity_mint: &Pubkey,
-        lending_market_owner: &Pubkey,
-        token_program: &Pubkey,
-    ) -> Result<Instruction, Box<dyn Error>> {
-        let market_authority = pda::get_market_authority(&lending_market);
-        let reserve_liquidity_supply =
-            pda::get_reserve_liquidity_supply(lending_market, reserve_liquidity_mint);
-        let reserve_collateral_mint =
-            pda::get_reserve_collateral_mint(lending_market, reserve_liquidity_mint);
-        let reserve_collateral_supply =
-            pda::get_reserve_collateral_supply(lending_market, reserve_liquidity_mint);
-        let reserve_fee_vault = pda::get_reserve_fee_vault(lending_market, reserve_liquidity_mint);
-        Ok(Instruction {
-            program_id: KAMINO_LENDING_ID,
-            accounts: accounts::InitReserve {
-                reserve: *reserve,
-                reserve_liquidity_supply,
-                reserve_collateral_mint,
-                reserve_collateral_supply,
-                fee_receiver: reserve_fee_vault,
-                reserve_liquidity_mint: *reserve_liquidity_mint,
-                lending_market_owner: *lending_market_owner,
-                lending_market: *lending_market,
-                lending_market_authority: market_authority,
-                system_program: SYSTEM_ID,
-                rent: SYSVAR_RENT_ID,
-                token_program: *token_program,
-            }
-            .to_account_metas(Some(true)),
-            data: instruction::InitReserve {}.data(),
-        })
-    }
-
-    pub fn upadte_entire_reserve_config(
-        reserve: &Pubkey,
-        lending_market_owner: &Pubkey,
-        lending_market: &Pubkey,
-        reserve_config: ReserveConfig,
-    ) -> Result<Instruction, Box<dyn Error>> {
-        let encoded_config = to_vec::<ReserveConfig>(&reserve_config).unwrap();
-        Ok(Instruction {
-            program_id: KAMINO_LENDING_ID,
-            accounts: accounts::UpdateEntireReserveConfig {
-                reserve: *reserve,
-                lending_market_owner: *lending_market_owner,
-                lending_market: *lending_market,
-            }
-            .to_account_metas(Some(true)),
-            data: instruction::UpdateEntireReserveConfig {
-                _mode: 25,
-                _value: encoded_config.try_into().unwrap(),
-            }
-            .data(),
-        })
-    }
-
-    pub fn init_user_metadata(user: &Pubkey) -> Result<Instruction, Box<dyn Error>> {
-        Ok(Instruction {
-            program_id: KAMINO_LENDING_ID,
-            accounts: accounts::InitUserMetadata {
-                user_metadata: pda::get_user_metadata(user),
-                owner: *user,
-                fee_payer: *user,
-                referrer_user_metadata: KAMINO_LENDING_ID,
-                system_program: SYSTEM_ID,
-                rent: SYSVAR_RENT_ID,
-            }
-            .to_account_metas(Some(true)),
-            data: instruction::InitUserMetadata { _user_lookup_table: Pubkey::default() }.data(),
-        })
-    }
-
-    pub fn init_obligation(
-        owner: &Pubkey,
-        lending_market: &Pubkey,
-        token_program: &Pubkey,
-        tag: u8,
-        id: u8,
-        seed1: [u8; 32],
-        seed2: [u8; 32],
-    ) -> Result<Instruction, Box<dyn Error>> {
-        Ok(Instruction {
-            program_id: KAMINO_LENDING_ID,
-            accounts: accounts::InitObligation {
-                obligation_owner: *owner,
-                fee_payer: *owner,
-                obligation: pda::get_user_obligation(lending_market, owner),
-                lending_market: *lending_market,
-                seed1_account: Pubkey::from(seed1),
-                seed2_account: Pubkey::from(seed2),
-                owner_user_metadata: pda::get_user_metadata(owner),
-                token_program: *token_program,
-                system_program: SYSTEM_ID,
-                rent: SYSVAR_RENT_ID,
-            }
-            .to_account_metas(Some(true)),
-            data: instruction::InitObligation { _args: InitObligationArgs { tag, id } }.data(),
-        })
-    }
-
-    pub fn refresh_reserve(
-        reserve: &Pubkey,
-        lending_market: &Pubkey,
-        pyth_oracle: &Pubkey,
-    ) -> Result<Instruction, Box<dyn Error>> {
-        Ok(Instruction {
-            program_id: KAMINO_LENDING_ID,
-            accounts: accounts::RefreshReserve {
-                reserve: *reserve,
-                lending_market: *lending_market,
-                pyth_oracle: *pyth_oracle,
-                switchboard_price_oracle: KAMINO_LENDING_ID,
-                switchboard_twap_oracle: KAMINO_LENDING_ID,
-                scope_prices: KAMINO_LENDING_ID,
-            }
-            .to_account_metas(Some(true)),
-            data: instruction::RefreshReserve {}.data(),
-        })
-    }
-
-    pub fn refresh_obligation(
-        user: &Pubkey,
-        lending_market: &Pubkey,
-    ) -> Result<Instruction, Box<dyn Error>> {
-        Ok(Instruction {
-            program_id: KAMINO_LENDING_ID,
-            accounts: accounts::RefreshObligation {
-                obligation: pda::get_user_obligation(lending_market, user),
-                lending_market: *lending_market,
-            }
-            .to_account_metas(Some(true)),
-            data: instruction::RefreshObligation {}.data(),
-        })
-    }
-
-    pub fn deposit_reserve_liquidity_and_obligation_collateral(
-        lending_market: &Pubkey,
-        user: &Pubkey,
-        mint: &Pubkey,
-        reserve: &Pubkey,
-        token_program: &Pubkey,
-        deposit_amount: u64,
-    ) -> Result<Instruction, Box<dyn Error>> {
-        let [reserve_liquidity_supply, reserve_collateral_mint, reserve_collateral_supply, reserve_fee_vault] = [
-            pda::get_reserve_liquidity_supply(lending_market, mint),
-            pda::get_reserve_collateral_mint(lending_market, mint),
-            pda::get_reserve_collateral_supply(lending_market, mint),
-            pda::get_reserve_fee_vault(lending_market, mint),
-        ];
-        Ok(Instruction {
-            program_id: KAMINO_LENDING_ID,
-            accounts: accounts::DepositReserveLiquidityAndObligationCollateral {
-                owner: *user,
-                obligation: get_user_obligation(lending_market, user),
-                user_source_liquidity: get_associated_token_address(user, mint),
-                reserve: *reserve,
-                lending_market: *lending_market,
-                lending_market_authority: pda::get_market_authority(lending_market),
-                reserve_destination_deposit_collateral: reserve_collateral_supply,
-                reserve_liquidity_supply,
-                reserve_collateral_mint,
-                token_program: *token_program,
-                placeholder_user_destination_collateral: KAMINO_LENDING_ID,
-                instruction_sysvar_account: SYSVAR_INSTRUCTIONS_ID,
-            }
-            .to_account_metas(Some(true)),
-            data: instruction::DepositReserveLiquidityAndObligationCollateral {
-                _liquidity_amount: deposit_amount,
-            }
-            .data(),
-        })
-    }
-}
-
 pub mod types {
     use anchor_client::solana_sdk::pubkey::Pubkey;
     pub struct ReserveConfigParams {
@@ -443,8 +198,9 @@ pub mod utils {
     use crate::kamino::types::ReserveConfigParams;
     use anchor_client::solana_sdk::pubkey::Pubkey;
     use encoding_rs::UTF_8;
+    use kamino_client::interest;
     use kamino_lend::typedefs::{
-        BorrowRateCurve, CurvePoint, PriceHeuristic, PythConfiguration, ReserveConfig, ReserveFees,
+        CurvePoint, PriceHeuristic, PythConfiguration, ReserveConfig, ReserveFees,
         TokenInfo, WithdrawalCaps,
     };
     use std::str::FromStr;
@@ -495,23 +251,11 @@ pub mod utils {
                 scope_configuration: Default::default(),
                 padding: [0; 20],
             },
-            borrow_rate_curve: BorrowRateCurve {
-                points: {
-                    let mut curve_points = vec![
-                        CurvePoint { utilization_rate_bps: 0, borrow_rate_bps: 1 },
-                        CurvePoint { utilization_rate_bps: 100, borrow_rate_bps: 100 },
-                        CurvePoint { utilization_rate_bps: 10000, borrow_rate_bps: 100000 },
-                    ];
-                    curve_points.extend(vec![
-                        CurvePoint {
-                            utilization_rate_bps: 10000,
-                            borrow_rate_bps: 100000
-                        };
-                        8
-                    ]);
-                    curve_points.try_into().unwrap()
-                },
-            },
+            borrow_rate_curve: interest::borrow_rate_curve(&[
+                CurvePoint { utilization_rate_bps: 0, borrow_rate_bps: 1 },
+                CurvePoint { utilization_rate_bps: 100, borrow_rate_bps: 100 },
+                CurvePoint { utilization_rate_bps: 10000, borrow_rate_bps: 100000 },
+            ]),
             deposit_withdrawal_cap: WithdrawalCaps {
                 config_capacity: 0,
                 current_total: 0,
@@ -544,59 +288,6 @@ pub mod utils {
     }
 }
 
-pub mod pda {
-    use kamino_lend::ID as KAMINO_LENDING_ID;
-    use spl_token::solana_program::pubkey::Pubkey;
-    pub fn get_market_authority(lending_market: &Pubkey) -> Pubkey {
-        Pubkey::find_program_address(&[b"lma", lending_market.as_ref()], &KAMINO_LENDING_ID).0
-    }
-
-    pub fn get_reserve_liquidity_supply(lending_market: &Pubkey, mint: &Pubkey) -> Pubkey {
-        Pubkey::find_program_address(
-            &[b"reserve_liq_supply", lending_market.as_ref(), mint.as_ref()],
-            &KAMINO_LENDING_ID,
-        )
-        .0
-    }
-    pub fn get_reserve_collateral_mint(lending_market: &Pubkey, mint: &Pubkey) -> Pubkey {
-        Pubkey::find_program_address(
-            &[b"reserve_coll_mint", lending_market.as_ref(), mint.as_ref()],
-            &KAMINO_LENDING_ID,
-        )
-        .0
-    }
-    pub fn get_reserve_collateral_supply(lending_market: &Pubkey, mint: &Pubkey) -> Pubkey {
-        Pubkey::find_program_address(
-            &[b"reserve_coll_supply", lending_market.as_ref(), mint.as_ref()],
-            &KAMINO_LENDING_ID,
-        )
-        .0
-    }
-    pub fn get_reserve_fee_vault(lending_market: &Pubkey, mint: &Pubkey) -> Pubkey {
-        Pubkey::find_program_address(
-            &[b"fee_receiver", lending_market.as_ref(), mint.as_ref()],
-            &KAMINO_LENDING_ID,
-        )
-        .0
-    }
-
-    pub fn get_user_obligation(lending_market: &Pubkey, user: &Pubkey) -> Pubkey {
-        let tag = 0u8;
-        let id = 0u8;
-        let seed1 = [0; 32];
-        let seed2 = [0; 32];
-        Pubkey::find_program_address(
-            &[&[tag], &[id], user.as_ref(), lending_market.as_ref(), &seed1, &seed2],
-            &KAMINO_LENDING_ID,
-        )
-        .0
-    }
-
-    pub fn get_user_metadata(user: &Pubkey) -> Pubkey {
-        Pubkey::find_program_address(&[b"user_meta", user.as_ref()], &KAMINO_LENDING_ID).0
-    }
-}
-
 #[cfg(test)]
 mod tests {
     use std::{thread::sleep, time::Duration};
@@ -609,6 +300,7 @@ mod tests {
         },
         Client, Cluster,
     };
+    use kamino_program_test::KaminoTestContext;
 
     use super::*;
     use crate::token::{create_token_mint, mint_token};
@@ -621,11 +313,19 @@ mod tests {
             program.account::<kamino_state::LendingMarket>(lending_market.pubkey()).unwrap();
         println!("{:?}", market_account.lending_market_owner);
         let mint = Keypair::new();
-        create_token_mint(&program.rpc(), &payer, &mint, &payer.pubkey(), &spl_token::ID).unwrap();
-        mint_token(&program.rpc(), &payer, &mint.pubkey(), 100000000, &spl_token::ID).unwrap();
-        let reserve = Keypair::new();
-        init_reserve(&program, &payer, &lending_market, &reserve, &mint.pubkey(), &spl_token::ID)
+        create_token_mint(&program.rpc(), &payer, &mint, &payer.pubkey(), 9, &spl_token::ID)
             .unwrap();
+        mint_token(
+            &program.rpc(),
+            &payer,
+            &mint.pubkey(),
+            &payer.pubkey(),
+            100000000,
+            &spl_token::ID,
+        )
+        .unwrap();
+        let reserve = Keypair::new();
+        init_reserve(&program, &payer, &lending_market, &reserve, &mint.pubkey()).unwrap();
         let reserve_config =
             utils::make_reserve_config("SOL", types::ReserveConfigParams::default());
         update_reserve_config(
@@ -639,7 +339,8 @@ mod tests {
         if !check_if_user_metadata_initialized(&program, &payer) {
             init_user_metadata(&program, &payer).unwrap();
         }
-        init_obligation(&program, &payer, &lending_market.pubkey()).unwrap();
+        init_obligation(&program, &payer, &lending_market.pubkey(), &ObligationType::default())
+            .unwrap();
         (lending_market.pubkey(), mint.pubkey(), reserve.pubkey())
     }
 
@@ -655,6 +356,7 @@ mod tests {
     }
 
     #[test]
+    #[ignore = "needs a local validator; see test_deposit_in_process"]
     fn test_deposit() {
         let path = "/Users/daiwanwei/.config/solana/id.json";
         let payer = read_keypair_file(&path).expect("invalid payer keypair file");
@@ -673,4 +375,58 @@ mod tests {
         )
         .unwrap();
     }
+
+    #[tokio::test]
+    async fn test_deposit_in_process() {
+        let mut ctx = KaminoTestContext::start().await.unwrap();
+        let payer = ctx.payer().pubkey();
+        let lending_market = ctx.create_lending_market().await.unwrap();
+        let mint = ctx.create_mint(6).await.unwrap();
+        ctx.mint_to(&mint, &payer, 100000000).await.unwrap();
+        let reserve = ctx.create_reserve(&lending_market, &mint, &spl_token::ID).await.unwrap();
+        let reserve_config =
+            utils::make_reserve_config("SOL", types::ReserveConfigParams::default());
+        let instructions = [
+            instruction::update_entire_reserve_config(
+                &reserve,
+                &payer,
+                &lending_market,
+                reserve_config,
+            )
+            .unwrap(),
+            instruction::init_user_metadata(&payer, None).unwrap(),
+            instruction::init_obligation(
+                &payer,
+                &lending_market,
+                &spl_token::ID,
+                &ObligationType::default(),
+            )
+            .unwrap(),
+        ];
+        for instruction in instructions {
+            ctx.process_instructions(&[instruction], &[]).await.unwrap();
+        }
+
+        let obligation = pda::get_user_obligation(&lending_market, &payer);
+        let reserve_state: kamino_state::Reserve = ctx.get_anchor_account(&reserve).await.unwrap();
+        let obligation_state: kamino_state::Obligation =
+            ctx.get_anchor_account(&obligation).await.unwrap();
+        let deposit = [
+            instruction::refresh_reserve(&reserve, &reserve_state).unwrap(),
+            instruction::refresh_obligation(&obligation, &obligation_state).unwrap(),
+            instruction::deposit_reserve_liquidity_and_obligation_collateral(
+                &payer,
+                &obligation,
+                &ReserveAccounts::new(&lending_market, &reserve, &mint, &spl_token::ID),
+                100,
+            )
+            .unwrap(),
+        ];
+        ctx.process_instructions(&deposit, &[]).await.unwrap();
+
+        let obligation_state: kamino_state::Obligation =
+            ctx.get_anchor_account(&obligation).await.unwrap();
+        assert_eq!(obligation_state.deposits[0].deposit_reserve, reserve);
+        assert!(obligation_state.deposits[0].deposited_amount > 0);
+    }
 }
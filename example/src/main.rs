@@ -40,7 +40,7 @@ fn main() -> Result<(), Box<dyn Error>> {
     println!("size: {}", size);
     let size = 4664;
     println!("size: {}", size);
-    let res = create_lending_market(&program, &payer, &lending_market).unwrap();
+    let res = create_lending_market(&program, &payer, &lending_market)?;
     println!("{:?}", res);
     Ok(())
 }
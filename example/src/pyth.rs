@@ -15,6 +15,7 @@ mod tests {
     use super::*;
 
     #[test]
+    #[ignore = "reads a devnet price account"]
     fn test_pyth() {
         let path = "/Users/daiwanwei/.config/solana/id.json";
         let payer = read_keypair_file(&path).expect("invalid payer keypair file");
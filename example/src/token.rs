@@ -1,62 +1,54 @@
-use std::error::Error;
-
 use anchor_client::{
     solana_client::rpc_client::RpcClient,
     solana_sdk::{
-        message::Message,
         signature::{read_keypair_file, Keypair, Signature},
         signer::Signer,
-        system_instruction::create_account,
-        transaction::Transaction,
     },
     Client,
 };
-use spl_associated_token_account::{
-    get_associated_token_address, instruction::create_associated_token_account,
-};
-use spl_token::{
-    instruction::{initialize_mint, mint_to},
-    solana_program::pubkey::Pubkey,
-    ID as TOKEN_ID,
+use kamino_client::{
+    error::KaminoClientError,
+    rpc::send_transaction,
+    token::{create_associated_token_account, create_mint},
 };
+use spl_associated_token_account::get_associated_token_address_with_program_id;
+use spl_token::{solana_program::pubkey::Pubkey, ID as TOKEN_ID};
+use spl_token_2022::instruction::mint_to;
 
 pub fn create_token_mint(
     client: &RpcClient,
     payer: &Keypair,
     mint: &Keypair,
     mint_authority: &Pubkey,
+    decimals: u8,
     token_program_id: &Pubkey,
-) -> Result<Signature, Box<dyn Error>> {
-    let create_account_ix = create_account(
+) -> Result<Signature, KaminoClientError> {
+    let instructions = create_mint(
         &payer.pubkey(),
         &mint.pubkey(),
-        client.get_minimum_balance_for_rent_exemption(82)?,
-        82,
-        &token_program_id,
-    );
-    let intialize_ix = initialize_mint(&token_program_id, &mint.pubkey(), mint_authority, None, 6)?;
-    let message = Message::new(&[create_account_ix, intialize_ix], Some(&payer.pubkey()));
-    let blockhash = client.get_latest_blockhash()?;
-    let tx = Transaction::new(&[payer, &mint], message, blockhash);
-    let sig = client.send_and_confirm_transaction(&tx)?;
-    Ok(sig)
+        mint_authority,
+        None,
+        decimals,
+        token_program_id,
+    )?;
+    send_transaction(client, &payer.pubkey(), &instructions, &[payer, mint])
 }
 
 pub fn mint_token(
     client: &RpcClient,
     payer: &Keypair,
     mint: &Pubkey,
+    recipient: &Pubkey,
     amount: u64,
     token_program_id: &Pubkey,
-) -> Result<Signature, Box<dyn Error>> {
-    let user_ata = get_associated_token_address(&payer.pubkey(), mint);
+) -> Result<Signature, KaminoClientError> {
+    let recipient_ata =
+        get_associated_token_address_with_program_id(recipient, mint, token_program_id);
     let create_ata_ix =
-        create_associated_token_account(&payer.pubkey(), &payer.pubkey(), mint, token_program_id);
-    let mint_ix = mint_to(&TOKEN_ID, mint, &user_ata, &payer.pubkey(), &[], amount).unwrap();
-    let message = Message::new(&[create_ata_ix, mint_ix], Some(&payer.pubkey()));
-    let tx = Transaction::new(&[&payer], message, client.get_latest_blockhash().unwrap());
-    let sig = client.send_and_confirm_transaction(&tx).unwrap();
-    Ok(sig)
+        create_associated_token_account(&payer.pubkey(), recipient, mint, token_program_id);
+    let mint_ix = mint_to(token_program_id, mint, &recipient_ata, &payer.pubkey(), &[], amount)
+        .map_err(|error| KaminoClientError::InvalidInput(error.to_string()))?;
+    send_transaction(client, &payer.pubkey(), &[create_ata_ix, mint_ix], &[payer])
 }
 
 #[cfg(test)]
@@ -65,6 +57,7 @@ mod tests {
 
     use super::*;
     #[test]
+    #[ignore = "needs a local validator"]
     pub fn test_create_token_mint() {
         let client = RpcClient::new("http://localhost:8899");
         let path = "/Users/daiwanwei/.config/solana/id.json";
@@ -78,19 +71,23 @@ mod tests {
         println!("{:?}", sig);
         let bal = client.get_balance(&payer.pubkey()).unwrap();
         println!("{:?}", bal);
-        let res = create_token_mint(&client, &payer, &mint, &payer.pubkey(), &TOKEN_ID).unwrap();
+        let res = create_token_mint(&client, &payer, &mint, &payer.pubkey(), 6, &TOKEN_ID).unwrap();
     }
 
     #[test]
+    #[ignore = "needs a local validator"]
     fn test_mint_token() {
         let client = RpcClient::new("http://localhost:8899");
         let path = "/Users/daiwanwei/.config/solana/id.json";
         let payer = read_keypair_file(&path).expect("invalid payer keypair file");
         let mint = Keypair::new();
         let blockhash = client.get_latest_blockhash().unwrap();
-        let res = create_token_mint(&client, &payer, &mint, &payer.pubkey(), &TOKEN_ID).unwrap();
+        let res = create_token_mint(&client, &payer, &mint, &payer.pubkey(), 6, &TOKEN_ID).unwrap();
         let amount = 1000000;
-        let mint_res = mint_token(&client, &payer, &mint.pubkey(), amount, &TOKEN_ID).unwrap();
+        let mint_res =
+            mint_token(&client, &payer, &mint.pubkey(), &payer.pubkey(), amount, &TOKEN_ID)
+                .unwrap();
+        mint_token(&client, &payer, &mint.pubkey(), &payer.pubkey(), amount, &TOKEN_ID).unwrap();
         println!("{:?}", mint_res);
     }
 }
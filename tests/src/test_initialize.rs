@@ -1,28 +1,40 @@
-use std::str::FromStr;
-
 use anchor_client::{
+    anchor_lang::{InstructionData, ToAccountMetas},
     solana_sdk::{
-        commitment_config::CommitmentConfig, pubkey::Pubkey, signature::read_keypair_file,
+        account_info::AccountInfo, entrypoint::ProgramResult, instruction::Instruction,
+        pubkey::Pubkey,
     },
-    Client, Cluster,
 };
+use kamino_program_test::{program_test, KaminoTestContext};
+use solana_program_test::processor;
 
-#[test]
-fn test_initialize() {
-    let program_id = "7PUjTUEsbk6d84ABAp84v91ZPggk24rantFnNxdEq8V";
-    let anchor_wallet = std::env::var("ANCHOR_WALLET").unwrap();
-    let payer = read_keypair_file(&anchor_wallet).unwrap();
-
-    let client = Client::new_with_options(Cluster::Localnet, &payer, CommitmentConfig::confirmed());
-    let program_id = Pubkey::from_str(program_id).unwrap();
-    let program = client.program(program_id).unwrap();
+fn process_instruction<'info>(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo<'info>],
+    data: &[u8],
+) -> ProgramResult {
+    // Anchor's entry ties the borrow of the accounts slice to the account
+    // infos' lifetime. SAFETY: the account infos outlive the call, and entry
+    // keeps no reference to the slice past it.
+    let accounts: &'info [AccountInfo<'info>] =
+        unsafe { std::slice::from_raw_parts(accounts.as_ptr(), accounts.len()) };
+    kamino_playground::entry(program_id, accounts, data)
+}
 
-    let tx = program
-        .request()
-        .accounts(kamino_playground::accounts::Initialize {})
-        .args(kamino_playground::instruction::Initialize {})
-        .send()
-        .expect("");
+#[tokio::test]
+async fn test_initialize() {
+    let mut program_test = program_test().unwrap();
+    program_test.add_program(
+        "kamino_playground",
+        kamino_playground::ID,
+        processor!(process_instruction),
+    );
+    let mut ctx = KaminoTestContext { context: program_test.start_with_context().await };
 
-    println!("Your transaction signature {}", tx);
+    let instruction = Instruction {
+        program_id: kamino_playground::ID,
+        accounts: kamino_playground::accounts::Initialize {}.to_account_metas(None),
+        data: kamino_playground::instruction::Initialize {}.data(),
+    };
+    ctx.process_instructions(&[instruction], &[]).await.unwrap();
 }